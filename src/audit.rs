@@ -0,0 +1,52 @@
+use std::collections::BTreeMap;
+
+use reqwest::header::HeaderMap;
+use serde::Serialize;
+use url::Url;
+
+/// Security headers every page should be served with.
+const SECURITY_HEADERS: &[&str] = &[
+    "strict-transport-security",
+    "content-security-policy",
+    "x-content-type-options",
+    "x-frame-options",
+    "referrer-policy",
+];
+
+/// The security headers of a page: values of the present ones, and the missing ones.
+#[derive(Debug, Serialize)]
+pub struct HeaderAudit {
+    pub url: String,
+    pub present: BTreeMap<String, String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<String>,
+}
+
+impl HeaderAudit {
+    /// Audit the headers of a page. `Strict-Transport-Security` is ignored over plain HTTP, so it
+    /// is only expected on `https://` pages.
+    pub fn new(url: &Url, headers: &HeaderMap) -> Self {
+        let mut present = BTreeMap::new();
+        let mut missing = vec![];
+
+        for name in SECURITY_HEADERS {
+            match headers.get(*name) {
+                Some(value) => {
+                    present.insert(
+                        name.to_string(),
+                        value.to_str().unwrap_or_default().to_string(),
+                    );
+                }
+                None if *name == "strict-transport-security" && url.scheme() != "https" => {}
+                None => missing.push(name.to_string()),
+            }
+        }
+
+        Self {
+            url: url.to_string(),
+            present,
+            missing,
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::File;
+
+use serde::{Deserialize, Serialize};
+
+use crate::history;
+use crate::report::{Entry, Verdict};
+
+/// A result of a previous run.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Previous {
+    pub url: String,
+    pub status: u16,
+    pub verdict: Verdict,
+}
+
+impl From<&Entry> for Previous {
+    fn from(entry: &Entry) -> Self {
+        Self {
+            url: entry.url.clone(),
+            status: entry.status,
+            verdict: entry.verdict,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PreviousReport {
+    results: Vec<Previous>,
+}
+
+/// Load the results of a previous run: a JSON report (--report), or the last run of a results
+/// database (--db, `.sqlite` or `.db` files).
+pub fn load(path: &str) -> Result<Vec<Previous>, Box<dyn Error>> {
+    if path.ends_with(".sqlite") || path.ends_with(".db") {
+        return history::last_results(path);
+    }
+
+    let report: PreviousReport = serde_json::from_reader(File::open(path)?)?;
+
+    Ok(report.results)
+}
+
+/// How a URL changed since the previous run.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// The URL fails, but did not (or was not found).
+    Broken,
+
+    /// The URL failed, but does not anymore.
+    Recovered,
+
+    /// The URL status changed, without breaking nor recovering it.
+    StatusChanged,
+
+    /// The URL has not been found this time (no page links to it anymore).
+    Gone,
+}
+
+/// A URL whose result changed since the previous run.
+#[derive(Debug, Serialize)]
+pub struct Change {
+    pub url: String,
+    pub kind: ChangeKind,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<u16>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<u16>,
+}
+
+/// Compare the results of the current run with the previous ones, sorted by URL.
+pub fn changes(previous: &[Previous], current: &[Entry]) -> Vec<Change> {
+    let before: HashMap<&str, &Previous> = previous.iter().map(|x| (x.url.as_str(), x)).collect();
+    let mut changes: Vec<Change> = current
+        .iter()
+        .filter_map(|entry| {
+            let previous = before.get(entry.url.as_str());
+            let failed = previous.is_some_and(|x| x.verdict == Verdict::Failure);
+            let fails = entry.verdict == Verdict::Failure;
+            let kind = match previous {
+                _ if fails && !failed => ChangeKind::Broken,
+                Some(_) if failed && !fails => ChangeKind::Recovered,
+                Some(x) if x.status != entry.status => ChangeKind::StatusChanged,
+                _ => return None,
+            };
+
+            Some(Change {
+                url: entry.url.clone(),
+                kind,
+                before: previous.map(|x| x.status),
+                after: Some(entry.status),
+            })
+        })
+        .collect();
+
+    let found: HashSet<&str> = current.iter().map(|x| x.url.as_str()).collect();
+
+    changes.extend(
+        previous
+            .iter()
+            .filter(|x| !found.contains(&x.url.as_str()))
+            .map(|x| Change {
+                url: x.url.clone(),
+                kind: ChangeKind::Gone,
+                before: Some(x.status),
+                after: None,
+            }),
+    );
+
+    changes.sort_by(|a, b| a.url.cmp(&b.url));
+    changes
+}
@@ -0,0 +1,68 @@
+use std::error::Error;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use openssl::asn1::Asn1Time;
+use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+use serde::Serialize;
+
+/// How long to wait for a host to complete the TLS handshake.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The leaf certificate of an `https://` host.
+#[derive(Debug, Serialize)]
+pub struct Certificate {
+    pub host: String,
+    pub port: u16,
+    pub subject: String,
+    pub expires: String,
+    pub days_left: i32,
+
+    /// Whether the certificate expires within the warning window (or already expired).
+    pub expiring: bool,
+}
+
+/// Get the subject common name of a certificate.
+fn common_name(cert: &openssl::x509::X509Ref) -> String {
+    cert.subject_name()
+        .entries_by_nid(openssl::nid::Nid::COMMONNAME)
+        .next()
+        .and_then(|x| x.data().to_string().ok())
+        .unwrap_or_default()
+}
+
+/// Connect to a host and read its leaf certificate.
+///
+/// The certificate is not verified: an invalid one already fails every request, but its expiry
+/// date is still worth reporting.
+pub fn fetch(host: &str, port: u16, window: Duration) -> Result<Certificate, Box<dyn Error>> {
+    let address = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| format!("cannot resolve {}", host))?;
+    let stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    let mut connector = SslConnector::builder(SslMethod::tls())?;
+    connector.set_verify(SslVerifyMode::NONE);
+
+    let ssl = connector.build().connect(host, stream)?;
+    let cert = ssl
+        .ssl()
+        .peer_certificate()
+        .ok_or_else(|| format!("{} sent no certificate", host))?;
+
+    let remaining = Asn1Time::days_from_now(0)?.diff(cert.not_after())?;
+    let remaining_secs = i64::from(remaining.days) * 86400 + i64::from(remaining.secs);
+
+    Ok(Certificate {
+        host: host.to_string(),
+        port,
+        subject: common_name(&cert),
+        expires: cert.not_after().to_string(),
+        days_left: remaining.days,
+        expiring: remaining_secs < window.as_secs() as i64,
+    })
+}
@@ -0,0 +1,74 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use serde::Serialize;
+
+use crate::report::{Entry, Verdict};
+
+/// An issue of a Code Quality report (the Code Climate format, read by GitLab).
+#[derive(Debug, Serialize)]
+pub struct Issue {
+    pub description: String,
+    pub check_name: &'static str,
+
+    /// Identify the issue across runs: the broken URL, hashed.
+    pub fingerprint: String,
+    pub severity: &'static str,
+    pub location: Location,
+}
+
+/// Where an issue lives: the page the URL has been found on (the URL itself for entrypoints).
+#[derive(Debug, Serialize)]
+pub struct Location {
+    pub path: String,
+    pub lines: Lines,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Lines {
+    pub begin: u32,
+}
+
+/// Map the status class of a result to a severity: the network errors block, server errors are
+/// critical, client errors major, the other failures and warnings minor.
+fn severity(entry: &Entry) -> &'static str {
+    match (entry.verdict, entry.status) {
+        (Verdict::Failure, 0) => "blocker",
+        (Verdict::Failure, 500..=599) => "critical",
+        (Verdict::Failure, 400..=499) => "major",
+        _ => "minor",
+    }
+}
+
+/// Get an issue for each failed (or warned) URL.
+pub fn issues(results: &[Entry]) -> Vec<Issue> {
+    results
+        .iter()
+        .filter(|x| x.verdict != Verdict::Success)
+        .map(|entry| {
+            let mut hasher = DefaultHasher::new();
+
+            entry.url.hash(&mut hasher);
+
+            let description = match (entry.status, &entry.error) {
+                (0, Some(error)) => format!("{} is unreachable: {}", entry.url, error),
+                (0, None) => format!("{} has not been checked", entry.url),
+                (status, _) => format!("{} answers {}", entry.url, status),
+            };
+
+            Issue {
+                description,
+                check_name: match entry.verdict {
+                    Verdict::Failure => "broken-link",
+                    _ => "link-warning",
+                },
+                fingerprint: format!("{:016x}", hasher.finish()),
+                severity: severity(entry),
+                location: Location {
+                    path: entry.found_on.first().unwrap_or(&entry.url).clone(),
+                    lines: Lines { begin: 1 },
+                },
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,286 @@
+use clap::{App, Arg, ArgSettings};
+
+/// The shells completion scripts can be generated for.
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish", "powershell"];
+
+/// An option of the command line, as completed.
+struct Opt<'a> {
+    short: Option<char>,
+    long: Option<&'a str>,
+    help: String,
+    takes_value: bool,
+    values: &'a [&'a str],
+}
+
+/// Tell whether an argument is positional (the entrypoints), given without any flag.
+fn is_positional(arg: &Arg) -> bool {
+    arg.get_short().is_none() && arg.get_long().is_none()
+}
+
+/// Get the first sentence of a help text, short enough for a completion menu (`e.g. ` does not
+/// end it).
+fn summary(help: &str) -> String {
+    let help = help.lines().next().unwrap_or_default();
+    let end = help
+        .match_indices(". ")
+        .map(|(x, _)| x)
+        .find(|x| help[x + 2..].starts_with(char::is_uppercase))
+        .unwrap_or(help.len());
+
+    help[..end].trim_end_matches('.').to_string()
+}
+
+/// List the options of a command, `--help` and `--version` included (added by clap when
+/// parsing only).
+fn options<'a>(app: &'a App) -> Vec<Opt<'a>> {
+    let builtin = [("help", 'h', "Prints help information"), ("version", 'V', "Prints version")];
+    let mut options: Vec<Opt> = app
+        .get_arguments()
+        .iter()
+        .filter(|x| !is_positional(x) && !x.is_set(ArgSettings::Hidden))
+        .map(|x: &Arg| Opt {
+            short: x.get_short(),
+            long: x.get_long(),
+            help: summary(x.get_about().unwrap_or_default()),
+            takes_value: x.is_set(ArgSettings::TakesValue),
+            values: x.get_possible_values().unwrap_or_default(),
+        })
+        .collect();
+
+    for (long, short, help) in builtin.iter() {
+        options.push(Opt {
+            short: Some(*short),
+            long: Some(long),
+            help: help.to_string(),
+            takes_value: false,
+            values: &[],
+        });
+    }
+
+    options
+}
+
+/// Get the names an option can be given with (`-H` and `--header`).
+fn flags(opt: &Opt) -> Vec<String> {
+    opt.short
+        .map(|x| format!("-{}", x))
+        .into_iter()
+        .chain(opt.long.map(|x| format!("--{}", x)))
+        .collect()
+}
+
+/// Generate the completion script of a shell (see [`SHELLS`]).
+pub fn generate(app: &App, shell: &str) -> String {
+    let name = app.get_name();
+    let options = options(app);
+    let commands: Vec<(&str, String)> = app
+        .get_subcommands()
+        .iter()
+        .map(|x| (x.get_name(), summary(x.get_about().unwrap_or_default())))
+        .collect();
+
+    match shell {
+        "bash" => bash(name, &options, &commands),
+        "zsh" => zsh(name, &options, &commands),
+        "fish" => fish(name, &options, &commands),
+        _ => powershell(name, &options, &commands),
+    }
+}
+
+fn bash(name: &str, options: &[Opt], commands: &[(&str, String)]) -> String {
+    let function = format!("_{}", name.replace('-', "_"));
+    let mut words: Vec<String> = options.iter().flat_map(flags).collect();
+    let mut script = format!(
+        "{}() {{\n    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\"\n    \
+         local prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"\n\n    case \"$prev\" in\n",
+        function
+    );
+
+    words.extend(commands.iter().map(|(command, _)| command.to_string()));
+
+    for opt in options.iter().filter(|x| x.takes_value) {
+        let reply = match opt.values {
+            [] => "compgen -f -- \"$cur\"".to_string(),
+            values => format!("compgen -W \"{}\" -- \"$cur\"", values.join(" ")),
+        };
+
+        script.push_str(&format!(
+            "        {})\n            COMPREPLY=($({}))\n            return\n            ;;\n",
+            flags(opt).join("|"),
+            reply
+        ));
+    }
+
+    script.push_str(&format!(
+        "    esac\n\n    COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n}}\n\ncomplete -F {} {}\n",
+        words.join(" "),
+        function,
+        name
+    ));
+    script
+}
+
+fn zsh(name: &str, options: &[Opt], commands: &[(&str, String)]) -> String {
+    let escape = |x: &str| {
+        x.replace('\'', "'\\''")
+            .replace('[', "\\[")
+            .replace(']', "\\]")
+            .replace(':', "\\:")
+    };
+    let mut script = format!("#compdef {}\n\n_arguments -s \\\n", name);
+
+    for opt in options {
+        let value = match (opt.takes_value, opt.values) {
+            (false, _) => String::new(),
+            (true, []) => ": :_files".to_string(),
+            (true, values) => format!(": :({})", values.join(" ")),
+        };
+        let help = escape(&opt.help);
+
+        for flag in flags(opt) {
+            script.push_str(&format!("    '{}[{}]{}' \\\n", flag, help, value));
+        }
+    }
+
+    let commands: Vec<String> = commands
+        .iter()
+        .map(|(command, help)| format!("{}\\:\"{}\"", command, help.replace('"', "\\\"")))
+        .collect();
+
+    script.push_str(&format!(
+        "    '1:: :(({}))' \\\n    '*::entrypoint:_urls'\n",
+        commands.join(" ").replace('\'', "'\\''")
+    ));
+    script
+}
+
+fn fish(name: &str, options: &[Opt], commands: &[(&str, String)]) -> String {
+    let quote = |x: &str| format!("'{}'", x.replace('\\', "\\\\").replace('\'', "\\'"));
+    let mut script = String::new();
+
+    for (command, help) in commands {
+        script.push_str(&format!(
+            "complete -c {} -n __fish_use_subcommand -f -a {} -d {}\n",
+            name,
+            command,
+            quote(help)
+        ));
+    }
+
+    for opt in options {
+        let mut line = format!("complete -c {}", name);
+
+        if let Some(short) = opt.short {
+            line.push_str(&format!(" -s {}", short));
+        }
+
+        if let Some(long) = opt.long {
+            line.push_str(&format!(" -l {}", long));
+        }
+
+        match (opt.takes_value, opt.values) {
+            (false, _) => {}
+            (true, []) => line.push_str(" -r"),
+            (true, values) => line.push_str(&format!(" -r -f -a {}", quote(&values.join(" ")))),
+        }
+
+        script.push_str(&format!("{} -d {}\n", line, quote(&opt.help)));
+    }
+
+    script
+}
+
+fn powershell(name: &str, options: &[Opt], commands: &[(&str, String)]) -> String {
+    let quote = |x: &str| format!("'{}'", x.replace('\'', "''"));
+    let mut candidates: Vec<String> = commands
+        .iter()
+        .map(|(command, help)| {
+            format!("        @({}, 'Command', {})", quote(command), quote(help))
+        })
+        .collect();
+
+    for opt in options {
+        for flag in flags(opt) {
+            candidates.push(format!(
+                "        @({}, 'ParameterName', {})",
+                quote(&flag),
+                quote(if opt.help.is_empty() { &flag } else { &opt.help })
+            ));
+        }
+    }
+
+    format!(
+        "Register-ArgumentCompleter -Native -CommandName {} -ScriptBlock {{\n    \
+         param($wordToComplete, $commandAst, $cursorPosition)\n\n    \
+         $candidates = @(\n{}\n    )\n\n    \
+         $candidates | Where-Object {{ $_[0] -like \"$wordToComplete*\" }} | ForEach-Object {{\n\
+         \x20       [System.Management.Automation.CompletionResult]::new(\
+         $_[0], $_[0], $_[1], $_[2])\n\
+         \x20   }}\n}}\n",
+        quote(name),
+        candidates.join(",\n")
+    )
+}
+
+/// Generate the manual page of the command (roff, section 1), from its options and subcommands.
+pub fn man(app: &App) -> String {
+    let escape = |x: &str| {
+        x.replace('\\', "\\e")
+            .replace('-', "\\-")
+            .lines()
+            .map(|x| match x.starts_with('.') || x.starts_with('\'') {
+                true => format!("\\&{}", x),
+                false => x.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let name = app.get_name();
+    let mut page = format!(
+        ".TH {} 1 \"\" \"{} {}\"\n.SH NAME\n{} \\- crawl a website to detect broken links\n\
+         .SH SYNOPSIS\n\\fB{}\\fR [OPTIONS] [ENTRYPOINT]... [SUBCOMMAND]\n.SH OPTIONS\n",
+        name.to_uppercase(),
+        name,
+        env!("CARGO_PKG_VERSION"),
+        escape(name),
+        escape(name)
+    );
+
+    for arg in app.get_arguments().iter().filter(|x| !x.is_set(ArgSettings::Hidden)) {
+        let mut names: Vec<String> = arg
+            .get_short()
+            .map(|x| format!("\\fB\\-{}\\fR", x))
+            .into_iter()
+            .chain(arg.get_long().map(|x| format!("\\fB\\-\\-{}\\fR", escape(x))))
+            .collect();
+
+        if is_positional(arg) {
+            names.push(format!("[\\fI{}\\fR]...", arg.get_name().to_uppercase()));
+        } else if arg.is_set(ArgSettings::TakesValue) {
+            let last = names.pop().unwrap_or_default();
+
+            names.push(format!("{}=\\fI{}\\fR", last, escape(&arg.get_name().to_uppercase())));
+        }
+
+        page.push_str(&format!(".TP\n{}\n", names.join(", ")));
+        page.push_str(&escape(arg.get_about().unwrap_or_default()));
+
+        if let Some(values) = arg.get_possible_values() {
+            page.push_str(&format!("\n.RS\nPossible values: {}\n.RE", escape(&values.join(", "))));
+        }
+
+        page.push('\n');
+    }
+
+    page.push_str(".SH SUBCOMMANDS\n");
+
+    for command in app.get_subcommands() {
+        page.push_str(&format!(
+            ".TP\n\\fB{}\\fR\n{}\n",
+            escape(command.get_name()),
+            escape(command.get_about().unwrap_or_default())
+        ));
+    }
+
+    page
+}
@@ -0,0 +1,286 @@
+use std::env;
+use std::error::Error;
+use std::ffi::OsString;
+use std::fs;
+use std::path::Path;
+
+use clap::{App, Arg, ArgMatches, ArgSettings};
+use toml::{Table, Value};
+
+/// The configuration file read when no --config is given, if it exists.
+const DEFAULT_PATH: &str = ".http-status-check.toml";
+
+/// The prefix of the environment variables setting options (`HSC_CHECK_EXTERNAL`).
+const ENV_PREFIX: &str = "HSC_";
+
+/// Complete the command line arguments with the options set by `HSC_*` environment variables,
+/// then with the ones of the configuration file (--config, or `.http-status-check.toml` in the
+/// working directory).
+///
+/// Variables and keys are named after the long options (`HSC_AUTH`, `check_external = true`,
+/// the `entrypoint` too), `[[host]]` tables are host policies, `[[content]]` tables content
+/// rules and `[[extract]]` tables extractors. The command line wins over the environment, which
+/// wins over the configuration file, conflicting options included.
+///
+/// The tables are merged with the files given by --host-policy, --content-rules and --extractors
+/// instead: the configuration file is loaded first, the given files then extending (or
+/// overriding) it.
+pub fn args(app: App, cli: Vec<OsString>) -> Result<Vec<OsString>, Box<dyn Error>> {
+    let matches = match app.clone().try_get_matches_from(&cli) {
+        Ok(matches) => matches,
+        // The actual parsing reports the error (or prints the help).
+        Err(_) => return Ok(cli),
+    };
+    let env = env_args(&app, &matches)?;
+    let cli = prepend(cli, env);
+    let matches = match app.clone().try_get_matches_from(&cli) {
+        Ok(matches) => matches,
+        Err(_) => return Ok(cli),
+    };
+    let path = match matches.value_of("config") {
+        Some(path) => path.to_string(),
+        None if Path::new(DEFAULT_PATH).is_file() => DEFAULT_PATH.to_string(),
+        None => return Ok(cli),
+    };
+
+    Ok(prepend(cli, config_args(&app, &matches, &path)?))
+}
+
+/// Insert arguments right after the binary name, so that the given ones come last.
+fn prepend(cli: Vec<OsString>, args: Vec<String>) -> Vec<OsString> {
+    let mut cli = cli.into_iter();
+
+    cli.next()
+        .into_iter()
+        .chain(args.into_iter().map(OsString::from))
+        .chain(cli)
+        .collect()
+}
+
+/// Check if an option is already set, or conflicts with a set one.
+fn is_overridden(app: &App, matches: &ArgMatches, arg: &Arg) -> bool {
+    let given = |x: &Arg| matches.occurrences_of(x.get_name()) > 0;
+    let names = |x: &Arg| {
+        app.get_arg_conflicts_with(x)
+            .into_iter()
+            .map(|y| y.get_name().to_string())
+            .collect::<Vec<_>>()
+    };
+    let conflicts = |x: &Arg| {
+        names(x).iter().any(|y| y == arg.get_name())
+            || names(arg).iter().any(|y| y == x.get_name())
+    };
+
+    given(arg) || app.get_arguments().iter().filter(|x| given(x)).any(conflicts)
+}
+
+/// Get the arguments set by environment variables. Flags are set by `true` or `1` (or a count),
+/// lists are separated by newlines.
+fn env_args(app: &App, matches: &ArgMatches) -> Result<Vec<String>, String> {
+    let mut args = vec![];
+
+    for arg in app.get_arguments() {
+        if ["help", "version"].contains(&arg.get_name()) || is_overridden(app, matches, arg) {
+            continue;
+        }
+
+        let variable = format!("{}{}", ENV_PREFIX, arg.get_name().replace('-', "_").to_uppercase());
+        let value = match env::var(&variable) {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        let invalid = || format!("invalid value for {}", variable);
+        let value = if !arg.is_set(ArgSettings::TakesValue) && arg.get_index().is_none() {
+            match value.trim() {
+                "true" | "yes" => Value::Boolean(true),
+                "false" | "no" | "" => Value::Boolean(false),
+                x => Value::Integer(x.parse().map_err(|_| invalid())?),
+            }
+        } else if arg.is_set(ArgSettings::MultipleOccurrences)
+            || arg.is_set(ArgSettings::MultipleValues)
+        {
+            Value::Array(value.lines().map(|x| Value::String(x.to_string())).collect())
+        } else {
+            Value::String(value)
+        };
+
+        args.extend(arg_values(arg, value).map_err(|_| invalid())?);
+    }
+
+    Ok(args)
+}
+
+/// Get the arguments set by a configuration file, not already given.
+fn config_args(app: &App, matches: &ArgMatches, path: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let table: Table = toml::from_str(&fs::read_to_string(path)?)?;
+    let mut args = vec![];
+
+    debug!("Reading options from {}", path);
+
+    for (key, value) in table {
+        if key == "host" {
+            args.extend(vec!["--host-policy".to_string(), path.to_string()]);
+            continue;
+        }
+
+        if key == "content" {
+            args.extend(vec!["--content-rules".to_string(), path.to_string()]);
+            continue;
+        }
+
+        if key == "extract" {
+            args.extend(vec!["--extractors".to_string(), path.to_string()]);
+            continue;
+        }
+
+        let name = key.replace('_', "-");
+        let arg = app
+            .get_arguments()
+            .iter()
+            .find(|x| x.get_name() == name && name != "config")
+            .ok_or_else(|| format!("unknown option `{}` in {}", key, path))?;
+
+        if is_overridden(app, matches, arg) {
+            continue;
+        }
+
+        args.extend(arg_values(arg, value).map_err(|e| format!("{} in {}", e, path))?);
+    }
+
+    Ok(args)
+}
+
+/// Convert an option value into command line arguments: `true` (or a count) for flags, a value
+/// or a list of values otherwise.
+fn arg_values(arg: &Arg, value: Value) -> Result<Vec<String>, String> {
+    let long = arg.get_long().map(|x| format!("--{}", x));
+    let invalid = || format!("invalid value for `{}`", arg.get_name().replace('-', "_"));
+
+    if !arg.is_set(ArgSettings::TakesValue) && arg.get_index().is_none() {
+        let count = match value {
+            Value::Boolean(x) => x as i64,
+            Value::Integer(x) => x,
+            _ => return Err(invalid()),
+        };
+
+        return Ok((0..count).filter_map(|_| long.clone()).collect());
+    }
+
+    let values = match value {
+        Value::Array(values) => values,
+        value => vec![value],
+    };
+    let mut args = vec![];
+
+    for value in values {
+        let value = match value {
+            Value::String(x) => x,
+            Value::Array(_) | Value::Table(_) => return Err(invalid()),
+            x => x.to_string(),
+        };
+
+        args.extend(long.clone());
+        args.push(value);
+    }
+
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{Clap, IntoApp};
+
+    use super::*;
+    use crate::Opts;
+
+    /// Write a configuration file, and get the options of a command line reading it.
+    fn parse(name: &str, config: &str, cli: &[&str]) -> Result<Opts, Box<dyn Error>> {
+        let path = env::temp_dir().join(format!("hsc-config-{}-{}.toml", name, std::process::id()));
+
+        fs::write(&path, config)?;
+
+        let cli = ["http-status-check", "--config", path.to_str().unwrap_or_default()]
+            .iter()
+            .chain(cli)
+            .map(OsString::from)
+            .collect();
+        let args = args(Opts::into_app(), cli);
+
+        fs::remove_file(&path)?;
+        Ok(Opts::try_parse_from(args?).map_err(|e| e.to_string())?)
+    }
+
+    // The only test setting environment variables, the others not reading these options.
+    #[test]
+    fn cli_beats_env_beats_file() {
+        env::set_var("HSC_CONCURRENCY", "4");
+        env::set_var("HSC_LIMIT", "6");
+
+        let config = "entrypoint = 'https://example.com'\nconcurrency = 3\nlimit = 5\n\
+                      max_pages = 9";
+        let opts = parse("precedence", config, &["--concurrency", "7"]);
+
+        env::remove_var("HSC_CONCURRENCY");
+        env::remove_var("HSC_LIMIT");
+
+        let opts = opts.unwrap();
+
+        assert_eq!(opts.concurrency, 7);
+        assert_eq!(opts.limit, 6);
+        assert_eq!(opts.max_pages, Some(9));
+        assert_eq!(opts.entrypoint, ["https://example.com"]);
+    }
+
+    #[test]
+    fn conflicting_options() {
+        let config = "entrypoint = 'https://example.com'\ncheck_external = true\nverbose = 1";
+        let opts = parse("conflicts", config, &["--prime-cache", "--quiet"]).unwrap();
+
+        assert!(opts.prime_cache && !opts.check_external);
+        assert!(opts.quiet);
+        assert_eq!(opts.verbose, 0);
+    }
+
+    #[test]
+    fn flag_counts() {
+        let opts = parse("counts", "entrypoint = 'https://example.com'\nverbose = 3", &[]).unwrap();
+
+        assert_eq!(opts.verbose, 3);
+
+        let config = "entrypoint = 'https://example.com'\ncheck_external = false";
+        let opts = parse("booleans", config, &[]).unwrap();
+
+        assert!(!opts.check_external);
+        assert!(parse("invalid", "check_external = 'yes'", &["https://example.com"]).is_err());
+    }
+
+    #[test]
+    fn list_values() {
+        let config = "entrypoint = ['https://example.com', 'https://example.org']\n\
+                      auth_host = ['a.example.com', 'b.example.com']";
+        let opts = parse("lists", config, &["--auth-host", "c.example.com"]).unwrap();
+
+        assert_eq!(opts.entrypoint, ["https://example.com", "https://example.org"]);
+        assert_eq!(opts.auth_host, ["c.example.com"]);
+        assert!(parse("nested", "auth_host = [['a']]", &["https://example.com"]).is_err());
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let error = parse("unknown", "chek_external = true", &["https://example.com"]).unwrap_err();
+
+        assert!(error.to_string().starts_with("unknown option `chek_external` in "));
+        assert!(parse("config", "config = 'other.toml'", &["https://example.com"]).is_err());
+    }
+
+    #[test]
+    fn host_policies_are_merged() {
+        let config = "[[host]]\ndomain = 'example.com'\ndelay_ms = 100";
+        let opts = parse("hosts", config, &["--host-policy", "hosts.toml", "https://example.com"])
+            .unwrap();
+
+        assert_eq!(opts.host_policy.len(), 2);
+        assert!(opts.host_policy[0].contains("hsc-config-hosts"));
+        assert_eq!(opts.host_policy[1], "hosts.toml");
+    }
+}
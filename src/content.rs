@@ -0,0 +1,88 @@
+use std::error::Error;
+use std::fs;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::report::ContentFailure;
+
+#[derive(Debug, Deserialize)]
+struct RuleFile {
+    #[serde(default)]
+    content: Vec<RawRule>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawRule {
+    pattern: String,
+
+    #[serde(default)]
+    require: Vec<String>,
+
+    #[serde(default)]
+    forbid: Vec<String>,
+}
+
+/// What the body of the pages matching a URL pattern must (and must not) contain.
+#[derive(Debug)]
+struct ContentRule {
+    pattern: Regex,
+    require: Vec<Regex>,
+    forbid: Vec<Regex>,
+}
+
+/// The content rules of a crawl.
+#[derive(Debug, Default)]
+pub struct ContentRules {
+    rules: Vec<ContentRule>,
+}
+
+impl ContentRules {
+    /// Load the `[[content]]` rules of the given TOML files.
+    pub fn load(paths: &[String]) -> Result<Self, Box<dyn Error>> {
+        let mut rules = vec![];
+
+        for path in paths {
+            let file: RuleFile = toml::from_str(&fs::read_to_string(path)?)?;
+            let compile = |x: &[String]| x.iter().map(|x| Regex::new(x)).collect::<Result<_, _>>();
+
+            for rule in file.content {
+                rules.push(ContentRule {
+                    pattern: Regex::new(&rule.pattern)?,
+                    require: compile(&rule.require)?,
+                    forbid: compile(&rule.forbid)?,
+                });
+            }
+        }
+
+        Ok(Self { rules })
+    }
+
+    /// Check the body of a page against the rules matching its URL.
+    pub fn check(&self, url: &str, body: &str) -> Vec<ContentFailure> {
+        let mut failures = vec![];
+
+        for rule in self.rules.iter().filter(|x| x.pattern.is_match(url)) {
+            let failure = |reason: String| ContentFailure {
+                url: url.to_string(),
+                pattern: rule.pattern.to_string(),
+                reason,
+            };
+
+            failures.extend(
+                rule.require
+                    .iter()
+                    .filter(|x| !x.is_match(body))
+                    .map(|x| failure(format!("missing `{}`", x))),
+            );
+            failures.extend(
+                rule.forbid
+                    .iter()
+                    .filter(|x| x.is_match(body))
+                    .map(|x| failure(format!("contains `{}`", x))),
+            );
+        }
+
+        failures
+    }
+}
@@ -0,0 +1,399 @@
+use std::error::Error;
+use std::fs;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use reqwest::header::{HeaderValue, COOKIE, SET_COOKIE};
+use reqwest::{Request, Response};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::middleware::Middleware;
+
+/// A cookie, as stored in a cookie jar file.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Cookie {
+    pub domain: String,
+
+    /// Whether the cookie is also sent to subdomains of `domain`.
+    #[serde(default)]
+    pub include_subdomains: bool,
+
+    #[serde(default = "default_path")]
+    pub path: String,
+
+    #[serde(default)]
+    pub secure: bool,
+
+    /// Expiration, as a UNIX timestamp (0 for session cookies).
+    #[serde(default)]
+    pub expires: u64,
+
+    pub name: String,
+    pub value: String,
+
+    /// Loaded from the cookie file (--cookies-from): kept for the whole crawl, even if a
+    /// response sets a cookie of the same name.
+    #[serde(skip)]
+    pinned: bool,
+}
+
+fn default_path() -> String {
+    "/".to_string()
+}
+
+/// Get the current UNIX timestamp.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_secs())
+}
+
+impl Cookie {
+    /// Parse a `Set-Cookie` header received from the given URL. The `Expires` attribute is
+    /// ignored (the cookie lasts for the crawl), `Max-Age` is not.
+    ///
+    /// Cookies whose `Domain` doesn't match the host, or is a public suffix, are rejected.
+    fn parse(header: &str, url: &Url) -> Option<Self> {
+        let mut attributes = header.split(';');
+        let (name, value) = attributes.next()?.split_once('=')?;
+        let directory = url.path().rsplit_once('/').map_or("", |(x, _)| x);
+        let mut cookie = Self {
+            domain: url.host_str()?.to_lowercase(),
+            include_subdomains: false,
+            path: if directory.is_empty() {
+                default_path()
+            } else {
+                directory.to_string()
+            },
+            secure: false,
+            expires: 0,
+            name: name.trim().to_string(),
+            value: value.trim().to_string(),
+            pinned: false,
+        };
+
+        for attribute in attributes {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+
+            match key.trim().to_lowercase().as_str() {
+                "domain" if !value.trim().is_empty() => {
+                    let domain = value.trim().trim_start_matches('.').to_lowercase();
+
+                    // A public suffix is only accepted as the host itself (RFC 6265 §5.3).
+                    if psl::suffix_str(&domain) == Some(domain.as_str()) {
+                        if domain != cookie.domain {
+                            return None;
+                        }
+                    } else if crate::is_domain_or_subdomain(&cookie.domain, &domain) {
+                        cookie.domain = domain;
+                        cookie.include_subdomains = true;
+                    } else {
+                        return None;
+                    }
+                }
+                "path" if value.trim().starts_with('/') => cookie.path = value.trim().to_string(),
+                "secure" => cookie.secure = true,
+                "max-age" => match value.trim().parse::<i64>() {
+                    Ok(age) if age <= 0 => cookie.expires = 1,
+                    Ok(age) => cookie.expires = now() + age as u64,
+                    Err(_) => {}
+                },
+                _ => {}
+            }
+        }
+
+        Some(cookie)
+    }
+
+    fn is_expired(&self) -> bool {
+        self.expires != 0 && self.expires <= now()
+    }
+
+    /// Check if the cookie must be sent with a request to the given URL.
+    fn matches(&self, url: &Url) -> bool {
+        let host = url.host_str().unwrap_or_default().to_lowercase();
+        let path = url.path();
+
+        let subdomain = self.include_subdomains && host.ends_with(&format!(".{}", self.domain));
+
+        (host == self.domain || subdomain)
+            && (path == self.path
+                || path.starts_with(&format!("{}/", self.path.trim_end_matches('/'))))
+            && (!self.secure || url.scheme() == "https")
+            && !self.is_expired()
+    }
+}
+
+/// Parse a Netscape cookie file (as written by curl and browser extensions).
+fn parse_netscape(content: &str) -> Vec<Cookie> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+
+            if line.starts_with('#') {
+                return None;
+            }
+
+            let fields: Vec<&str> = line.split('\t').collect();
+
+            match fields.as_slice() {
+                [domain, subdomains, path, secure, expires, name, value] => Some(Cookie {
+                    domain: domain.trim_start_matches('.').to_lowercase(),
+                    include_subdomains: subdomains.eq_ignore_ascii_case("TRUE"),
+                    path: path.to_string(),
+                    secure: secure.eq_ignore_ascii_case("TRUE"),
+                    expires: expires.parse().unwrap_or_default(),
+                    name: name.to_string(),
+                    value: value.trim_end().to_string(),
+                    pinned: false,
+                }),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Write cookies in the Netscape format.
+fn to_netscape(cookies: &[Cookie]) -> String {
+    let mut content = String::from("# Netscape HTTP Cookie File\n");
+
+    for cookie in cookies {
+        let flag = |x: bool| if x { "TRUE" } else { "FALSE" };
+        let domain = match cookie.include_subdomains {
+            true => format!(".{}", cookie.domain),
+            false => cookie.domain.clone(),
+        };
+
+        content.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            domain,
+            flag(cookie.include_subdomains),
+            cookie.path,
+            flag(cookie.secure),
+            cookie.expires,
+            cookie.name,
+            cookie.value
+        ));
+    }
+
+    content
+}
+
+/// Send cookies with requests, and store the cookies set by responses.
+#[derive(Debug, Default)]
+pub struct Cookies {
+    cookies: Mutex<Vec<Cookie>>,
+}
+
+impl Cookies {
+    /// Load a cookie jar, in the JSON format (a list of cookies) or the Netscape one.
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path)?;
+        let mut cookies: Vec<Cookie> = match content.trim_start().starts_with('[') {
+            true => serde_json::from_str(&content)?,
+            false => parse_netscape(&content),
+        };
+
+        cookies.iter_mut().for_each(|x| x.pinned = true);
+
+        debug!("Loaded {} cookies from {}", cookies.len(), path);
+        Ok(Self {
+            cookies: Mutex::new(cookies),
+        })
+    }
+
+    /// Get the cookies of the jar, the expired ones left out.
+    pub fn current(&self) -> Vec<Cookie> {
+        self.cookies
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|x| !x.is_expired())
+            .cloned()
+            .collect()
+    }
+
+    /// Save the cookie jar, in the JSON format for `.json` files and the Netscape one otherwise.
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let cookies = self.current();
+
+        match path.ends_with(".json") {
+            true => serde_json::to_writer_pretty(fs::File::create(path)?, &cookies)?,
+            false => fs::write(path, to_netscape(&cookies))?,
+        }
+
+        Ok(())
+    }
+}
+
+impl Middleware for Cookies {
+    fn before(&self, request: &mut Request) -> Result<Option<Response>, Box<dyn Error>> {
+        let header = self
+            .cookies
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|x| x.matches(request.url()))
+            .map(|x| format!("{}={}", x.name, x.value))
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        if !header.is_empty() {
+            request.headers_mut().insert(COOKIE, HeaderValue::from_str(&header)?);
+        }
+
+        Ok(None)
+    }
+
+    fn after(&self, request: &Request, response: &mut Response) {
+        let mut cookies = self.cookies.lock().unwrap();
+
+        for header in response.headers().get_all(SET_COOKIE) {
+            let cookie = match header.to_str().ok().and_then(|x| Cookie::parse(x, request.url())) {
+                Some(cookie) => cookie,
+                None => continue,
+            };
+
+            let same = |x: &Cookie| {
+                x.name == cookie.name && x.domain == cookie.domain && x.path == cookie.path
+            };
+
+            if cookies.iter().any(|x| x.pinned && same(x)) {
+                debug!("{} sets cookie {}, kept from the cookie file", request.url(), cookie.name);
+                continue;
+            }
+
+            debug!("{} sets cookie {}", request.url(), cookie.name);
+            cookies.retain(|x| !same(x));
+            cookies.push(cookie);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    #[test]
+    fn parse_attributes() {
+        let cookie = Cookie::parse(
+            "sid=abc; Path=/app; Domain=.Example.com; Secure; HttpOnly",
+            &url("https://www.example.com/login"),
+        )
+        .unwrap();
+
+        assert_eq!((cookie.name.as_str(), cookie.value.as_str()), ("sid", "abc"));
+        assert_eq!(cookie.domain, "example.com");
+        assert!(cookie.include_subdomains);
+        assert_eq!(cookie.path, "/app");
+        assert!(cookie.secure);
+        assert_eq!(cookie.expires, 0);
+    }
+
+    #[test]
+    fn parse_default_path_and_host() {
+        let cookie = Cookie::parse("a=1", &url("https://example.com/docs/page")).unwrap();
+
+        assert_eq!(cookie.domain, "example.com");
+        assert!(!cookie.include_subdomains);
+        assert_eq!(cookie.path, "/docs");
+    }
+
+    #[test]
+    fn parse_rejects_foreign_domains() {
+        let page = url("https://www.example.com/");
+
+        assert!(Cookie::parse("a=1; Domain=other.com", &page).is_none());
+        assert!(Cookie::parse("a=1; Domain=ample.com", &page).is_none());
+        assert!(Cookie::parse("a=1; Domain=shop.example.com", &page).is_none());
+        assert!(Cookie::parse("a=1; Domain=com", &page).is_none());
+        assert!(Cookie::parse("a=1; Domain=co.uk", &url("https://shop.co.uk/")).is_none());
+        assert!(Cookie::parse("a=1; Domain=github.io", &url("https://me.github.io/")).is_none());
+    }
+
+    #[test]
+    fn parse_public_suffix_host() {
+        let cookie = Cookie::parse("a=1; Domain=localhost", &url("http://localhost/")).unwrap();
+
+        assert_eq!(cookie.domain, "localhost");
+        assert!(!cookie.include_subdomains);
+    }
+
+    #[test]
+    fn parse_max_age() {
+        let page = url("https://example.com/");
+
+        assert!(Cookie::parse("a=1; Max-Age=60", &page).unwrap().expires > now());
+        assert!(Cookie::parse("a=1; Max-Age=0", &page).unwrap().is_expired());
+
+        let cookie = Cookie::parse("a=1; Max-Age=soon; Path=/app", &page).unwrap();
+
+        assert_eq!(cookie.expires, 0);
+        assert_eq!(cookie.path, "/app");
+    }
+
+    #[test]
+    fn matches() {
+        let header = "a=1; Domain=example.com; Path=/app; Secure";
+        let cookie = Cookie::parse(header, &url("https://example.com/")).unwrap();
+
+        assert!(cookie.matches(&url("https://example.com/app")));
+        assert!(cookie.matches(&url("https://www.example.com/app/page")));
+        assert!(!cookie.matches(&url("http://example.com/app")));
+        assert!(!cookie.matches(&url("https://example.com/application")));
+        assert!(!cookie.matches(&url("https://notexample.com/app")));
+
+        let cookie = Cookie::parse("a=1", &url("https://example.com/")).unwrap();
+
+        assert!(cookie.matches(&url("http://example.com/page")));
+        assert!(!cookie.matches(&url("https://www.example.com/")));
+    }
+
+    #[test]
+    fn netscape_round_trip() {
+        let content = "# Netscape HTTP Cookie File\n\
+                       .example.com\tTRUE\t/\tTRUE\t4102444800\tsid\tabc\n\
+                       example.org\tFALSE\t/app\tFALSE\t0\tlang\ten\n";
+        let http_only = "#HttpOnly_example.net\tFALSE\t/\tFALSE\t0\tx\ty\n";
+        let cookies = parse_netscape(&format!("{}{}", content, http_only));
+
+        assert_eq!(cookies.len(), 3);
+        assert_eq!(cookies[0].domain, "example.com");
+        assert!(cookies[0].include_subdomains && cookies[0].secure);
+        assert_eq!(cookies[0].expires, 4102444800);
+        assert_eq!(cookies[1].path, "/app");
+        assert_eq!(cookies[2].domain, "example.net");
+        assert_eq!(to_netscape(&cookies[..2]), content);
+    }
+
+    #[test]
+    fn pinned_cookies_are_kept() {
+        let jar = Cookies {
+            cookies: Mutex::new(parse_netscape("example.com\tFALSE\t/\tFALSE\t0\tsid\tsession\n")),
+        };
+
+        jar.cookies.lock().unwrap()[0].pinned = true;
+
+        let request = Request::new(reqwest::Method::GET, url("https://example.com/"));
+        let mut response: Response = http::Response::builder()
+            .header(SET_COOKIE, "sid=anonymous")
+            .header(SET_COOKIE, "other=1")
+            .body(vec![])
+            .unwrap()
+            .into();
+
+        jar.after(&request, &mut response);
+
+        let cookies = jar.cookies.lock().unwrap();
+
+        assert_eq!(cookies.len(), 2);
+        assert_eq!(cookies[0].value, "session");
+        assert_eq!(cookies[1].name, "other");
+    }
+}
@@ -0,0 +1,2335 @@
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::hash::{Hash, Hasher};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use clap::Clap;
+use futures::Stream;
+use percent_encoding::percent_decode_str;
+use regex::Regex;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, Proxy, StatusCode};
+use serde::{Deserialize, Serialize};
+use url::{ParseError, Url};
+
+use crate::audit::HeaderAudit;
+use crate::cookies::Cookies;
+use crate::deprecation::Deprecations;
+use crate::error_page::ErrorPage;
+use crate::events::{CrawlEvent, Events};
+use crate::extract::{Format, Resource};
+use crate::frontier::{Frontier, Segment};
+use crate::middleware::{Auth, Cache, Chain, Rewrites, UserAgents};
+use crate::network::NetworkError;
+use crate::opts::{CheckOpts, Command, Opts};
+use crate::plugin::{Event, Plugins};
+use crate::policy::HostPolicies;
+use crate::report::{
+    BrokenAnchor, Budget, Duplicate, DuplicateContent, Entry, HreflangError, Hop, InsecureLink,
+    OffDomainRedirect, RedirectGroup, RedirectedUrl, Stage, Stages, Verdict,
+};
+use crate::revalidate::Revalidation;
+use crate::robots::Robots;
+use crate::static_dir::StaticDir;
+use crate::usage::Usage;
+use crate::validate::{Validator, Validators};
+use crate::worker::{run_workers, Worker};
+use crate::{client_builder, host, is_static_resource, matches_any_domain, read_urls, user_agent};
+use crate::{content, diff, dns, equity, extract, extractors, notify, prime, render, report};
+use crate::{resolve, revalidate, seo, suggest, tls, wayback};
+#[cfg(feature = "http3")]
+use crate::network;
+#[cfg(feature = "wasm")]
+use crate::wasm;
+
+/// A response can be an already-parsed URL or even a in-progress URL.
+///
+/// This struct is really important because it counts the number of time a link if found and also
+/// stores the response status code in order to summarize informations.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct Response {
+    /// Keep track number of found links.
+    count: u32,
+
+    /// Keep track of response status code.
+    pub(crate) status: u16,
+
+    /// Keep track of the redirect chain (every URL followed after this one).
+    redirects: Vec<String>,
+
+    /// Notes added by plugins (or by the crawler itself).
+    pub(crate) annotations: Vec<String>,
+
+    /// Whether the body has been skipped because of its size.
+    pub(crate) too_large: bool,
+
+    /// Whether the page looks like a missing page, despite its successful status.
+    pub(crate) soft_404: bool,
+
+    /// Whether a validator (or an --assert rule) rejected the response.
+    pub(crate) invalid: bool,
+
+    /// Whether the URL has not been requested, its host budget being exhausted.
+    skipped: bool,
+
+    /// Whether the URL failed on its origin, but has been served by the fallback host (or over
+    /// the other scheme).
+    pub(crate) fallback: bool,
+
+    /// The kind of resource, guessed from where the URL has been found first.
+    pub(crate) resource: Resource,
+
+    /// The pages this URL has been found on, sharing the keys of the crawled URLs.
+    referrers: Vec<Arc<str>>,
+
+    /// How many links away from an entrypoint the URL has been found first.
+    depth: u32,
+
+    /// How long the response headers took to arrive, the redirects included.
+    pub(crate) ttfb: Option<Duration>,
+
+    /// How long the body took to download, once the headers arrived (if it has been).
+    pub(crate) download: Option<Duration>,
+
+    /// The HTTP version of the (last) response, e.g. `HTTP/1.1`.
+    version: Option<String>,
+
+    /// Why HTTP/3 failed, the URL having been requested over TCP instead (--http3).
+    #[serde(default)]
+    http3_fallback: Option<String>,
+
+    /// The `Content-Encoding` of the response (e.g. `gzip`), if compressed.
+    encoding: Option<String>,
+
+    /// Whether the response is text, worth compressing.
+    compressible: bool,
+
+    /// The size of the response body, as advertised (or downloaded).
+    pub(crate) length: Option<u64>,
+
+    /// The `Last-Modified` header of the response, as given.
+    last_modified: Option<String>,
+
+    /// The `Age` header of the response: how long it has been cached, in seconds.
+    age: Option<u64>,
+
+    /// The `Expires` header of the response, as given.
+    expires: Option<String>,
+
+    /// The --capture-headers of the response, by lowercase name (several values being joined).
+    headers: BTreeMap<String, String>,
+
+    /// Whether the response has been served by a cache, according to its headers.
+    cache_status: Option<prime::CacheStatus>,
+
+    /// Why the request failed, if it did, and the class of the failure (network errors only).
+    pub(crate) error: Option<String>,
+    pub(crate) error_class: Option<NetworkError>,
+
+    /// The console errors and the failed XHR/fetch requests of the page, if rendered (--render).
+    #[serde(default)]
+    pub(crate) console_errors: Vec<String>,
+    #[serde(default)]
+    pub(crate) failed_requests: Vec<String>,
+
+    /// The performance timings of the page, if rendered (--render).
+    #[serde(default)]
+    pub(crate) timings: Option<render::Timings>,
+
+    /// Where the screenshot of the page has been saved, if it failed (--screenshots).
+    #[serde(default)]
+    pub(crate) screenshot: Option<String>,
+}
+
+impl Response {
+    /// Create a new Response instance.
+    pub(crate) fn new(status: u16, count: u32) -> Self {
+        Self {
+            status,
+            count,
+            redirects: vec![],
+            annotations: vec![],
+            too_large: false,
+            soft_404: false,
+            invalid: false,
+            skipped: false,
+            fallback: false,
+            resource: Resource::Page,
+            referrers: vec![],
+            depth: 0,
+            ttfb: None,
+            download: None,
+            version: None,
+            http3_fallback: None,
+            encoding: None,
+            compressible: false,
+            length: None,
+            last_modified: None,
+            age: None,
+            expires: None,
+            headers: BTreeMap::new(),
+            cache_status: None,
+            error: None,
+            error_class: None,
+            console_errors: vec![],
+            failed_requests: vec![],
+            timings: None,
+            screenshot: None,
+        }
+    }
+
+    /// Increment the count by 1.
+    fn increment(&mut self) {
+        self.increment_by(1);
+    }
+
+    /// Increment the count by `n`.
+    fn increment_by(&mut self, value: u32) {
+        self.count += value;
+    }
+
+    /// Remember a page this URL has been found on (once per page).
+    fn add_referrer(&mut self, referrer: Option<Arc<str>>) {
+        if let Some(referrer) = referrer.filter(|x| !self.referrers.contains(x)) {
+            self.referrers.push(referrer);
+        }
+    }
+
+    /// Update the status.
+    fn set_status(&mut self, status: u16) {
+        self.status = status;
+    }
+
+    /// Get how long the response took, the body included.
+    pub(crate) fn time(&self) -> Option<Duration> {
+        self.ttfb.map(|x| x + self.download.unwrap_or_default())
+    }
+}
+
+/// The crawl progress saved with --state, to resume an interrupted crawl.
+#[derive(Deserialize, Serialize)]
+struct Checkpoint<'a> {
+    pending: Cow<'a, VecDeque<Arc<str>>>,
+
+    /// The pending URLs spilled to disk, after the ones above.
+    #[serde(default)]
+    spilled: Cow<'a, [Segment]>,
+    external: Cow<'a, VecDeque<Arc<str>>>,
+    responses: Cow<'a, HashMap<Arc<str>, Response>>,
+
+    /// The hashes of every URL met, including the ones left out of the responses
+    /// (--failures-only).
+    #[serde(default)]
+    visited: Cow<'a, HashSet<u64>>,
+}
+
+/// How often the crawl progress is saved with --state.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The settings a client is built with, besides the shared ones: a proxy, a client certificate
+/// (and its private key) and whether invalid certificates are accepted.
+type ClientKey = (Option<String>, Option<(String, String)>, bool);
+
+/// Crawler handles responses, pending queue and other cool stuffs.
+///
+/// This struct is the base of our crawler. It handles the base URL and other options. Build it
+/// with a [`CrawlerBuilder`].
+#[derive(Debug)]
+pub struct Crawler {
+    pub(crate) bases: Vec<Url>,
+    pub(crate) opts: Opts,
+    pub(crate) pending: Frontier,
+    pub(crate) external: VecDeque<Arc<str>>,
+
+    /// Every URL met, but the successful ones with --failures-only. The queues and the referrers
+    /// share its key rather than copying it (the other maps, e.g. of canonical URLs or redirects,
+    /// still hold their own copies).
+    pub(crate) responses: HashMap<Arc<str>, Response>,
+
+    /// The hashes of every URL met, the successful ones having no response anymore with
+    /// --failures-only.
+    visited: HashSet<u64>,
+    pub(crate) stages: Stages,
+    pub(crate) policies: HostPolicies,
+    pub(crate) last_requests: HashMap<String, Instant>,
+
+    /// How many requests are in flight on every host (--per-host-concurrency).
+    pub(crate) in_flight: HashMap<String, usize>,
+
+    /// Set on Ctrl-C (or `SIGTERM`): no URL is dequeued anymore, the results collected so far
+    /// being reported.
+    pub(crate) interrupted: Arc<AtomicBool>,
+    host_times: HashMap<String, Duration>,
+    pub(crate) robots: HashMap<String, Robots>,
+    resolver: Option<Url>,
+    pub(crate) overrides: Arc<std::sync::Mutex<resolve::Overrides>>,
+    pub(crate) dns: Option<Arc<dns::Resolver>>,
+    pub(crate) addresses: HashMap<String, SocketAddr>,
+    pub(crate) client: Client,
+    clients: HashMap<ClientKey, Client>,
+
+    /// The clients of --http3: over QUIC, and over TCP (negotiating HTTP/2) when falling back.
+    #[cfg(feature = "http3")]
+    pub(crate) http3: Option<(Client, Client)>,
+
+    /// Why HTTP/3 failed on every host not answering over QUIC, with --http3.
+    #[cfg(feature = "http3")]
+    pub(crate) http3_failures: HashMap<String, String>,
+    pub(crate) redirects: HashMap<String, String>,
+
+    /// The in-scope URLs answering a permanent redirect (301 or 308).
+    pub(crate) permanent_redirects: HashSet<String>,
+    pub(crate) plugins: Plugins,
+    pub(crate) seeds: Option<HashSet<String>>,
+    pub(crate) static_dir: Option<Arc<StaticDir>>,
+    pub(crate) middlewares: Chain,
+
+    /// The credentials sent to some hosts, also part of the middlewares.
+    pub(crate) auths: Vec<Arc<Auth>>,
+    fragments: HashMap<String, HashSet<String>>,
+    anchors: HashMap<String, HashSet<String>>,
+    canonicals: HashMap<String, String>,
+
+    /// The pages which belong to the --sitemap, by requested URL.
+    pub(crate) indexable: HashSet<String>,
+    alternates: HashMap<String, Vec<extract::Alternate>>,
+    pub(crate) content_rules: content::ContentRules,
+    pub(crate) extractors: extractors::Extractors,
+    pub(crate) content_failures: Vec<report::ContentFailure>,
+    pub(crate) mixed_content: Vec<report::MixedContent>,
+
+    /// The same-domain `http://` links found on `https://` pages (page, link), with
+    /// --https-only-links.
+    http_links: BTreeSet<(String, String)>,
+
+    /// How many `mailto:`, `tel:`, `javascript:`... links have been skipped, by scheme.
+    pub(crate) other_schemes: BTreeMap<String, usize>,
+
+    /// The hosts only reachable on one scheme, with --scheme-fallback.
+    pub(crate) single_scheme_hosts: BTreeMap<String, report::SingleSchemeHost>,
+    pub(crate) invalid_mailto: Vec<report::InvalidMailto>,
+    pub(crate) invalid_urls: Vec<report::InvalidUrl>,
+    pub(crate) header_audits: Vec<HeaderAudit>,
+    pub(crate) seo_audits: Vec<seo::SeoAudit>,
+
+    /// How many URLs of every template have been queued and skipped, with --max-per-pattern.
+    patterns: HashMap<String, (usize, usize)>,
+
+    /// Whether every URL found would be requested, and why, with --dry-run.
+    plan: BTreeMap<String, (bool, String)>,
+
+    /// The same-domain pages found by the hash of their (whitespace normalized) body.
+    pub(crate) contents: HashMap<u64, BTreeSet<String>>,
+
+    /// The assets of Open Graph and Twitter card tags, and their property (`og:image`...).
+    pub(crate) social: HashMap<String, &'static str>,
+
+    /// The origins whose `/favicon.ico` has been queued.
+    favicons: HashSet<String>,
+    pub(crate) edges: Vec<equity::Edge>,
+    pub(crate) usage: Arc<Usage>,
+    pub(crate) deprecations: Arc<Deprecations>,
+    pub(crate) cookies: Arc<Cookies>,
+    pub(crate) revalidation: Option<Arc<Revalidation>>,
+    pub(crate) renderer: Option<Arc<render::Renderer>>,
+    pub(crate) webhook: Option<notify::Webhook>,
+    pub(crate) events: Events,
+    pub(crate) validators: Validators,
+    pub(crate) rewrites: Arc<Rewrites>,
+    pub(crate) error_pages: HashMap<String, ErrorPage>,
+    saved_at: Instant,
+
+    /// How many HTML pages have been parsed, and URLs checked, for --max-pages and --max-checks.
+    pub(crate) pages: usize,
+    checks: usize,
+    #[cfg(feature = "wasm")]
+    pub(crate) wasm_plugins: wasm::WasmPlugins,
+}
+
+/// Build a [`Crawler`], the options not set defaulting to the ones of the command line.
+#[derive(Debug)]
+pub struct CrawlerBuilder {
+    opts: Opts,
+    validators: Validators,
+}
+
+impl CrawlerBuilder {
+    /// Start building a crawler of the given site.
+    pub fn new(url: &str) -> Self {
+        Self {
+            opts: Opts::parse_from(["http-status-check", url]),
+            validators: Validators::default(),
+        }
+    }
+
+    /// Crawl another site at once, links between the sites counting as internal.
+    pub fn entrypoint(mut self, url: &str) -> Self {
+        self.opts.entrypoint.push(url.to_string());
+        self
+    }
+
+    /// How many URLs are requested at once (5 by default).
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.opts.concurrency = concurrency;
+        self
+    }
+
+    /// How many URLs of the same host are requested at once (unlimited by default).
+    pub fn per_host_concurrency(mut self, max: usize) -> Self {
+        self.opts.per_host_concurrency = Some(max);
+        self
+    }
+
+    /// Whether external links are checked too (`false` by default).
+    pub fn check_external(mut self, check_external: bool) -> Self {
+        self.opts.check_external = check_external;
+        self
+    }
+
+    /// Do not crawl the URLs matching this regex.
+    pub fn exclude(mut self, pattern: Regex) -> Self {
+        self.opts.exclude.push(pattern);
+        self
+    }
+
+    /// Limit the number of URLs to crawl (0, by default, for no limit).
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.opts.limit = limit;
+        self
+    }
+
+    /// Send this `User-Agent` header instead of the default one.
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.opts.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    /// Send this header with every request.
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.opts.header.push((name, value));
+        self
+    }
+
+    /// Crawl like a good citizen: obey `robots.txt`, wait between two requests to a host, use a
+    /// single worker and revalidate the pages of the previous runs. A contact address must be
+    /// given with [`CrawlerBuilder::from`].
+    pub fn polite(mut self, polite: bool) -> Self {
+        self.opts.polite = polite;
+        self
+    }
+
+    /// Send this `From` header (an email address) with every request.
+    pub fn from(mut self, from: HeaderValue) -> Self {
+        self.opts.from = Some(from);
+        self
+    }
+
+    /// Check every downloaded response (HTML documents and stylesheets) with this validator,
+    /// failing the rejected ones.
+    pub fn validator(mut self, validator: impl Validator + 'static) -> Self {
+        self.validators.push(Arc::new(validator));
+        self
+    }
+
+    /// Build the crawler.
+    ///
+    /// # Errors
+    ///
+    /// When an option cannot be used, e.g. an entrypoint is not a valid URL.
+    pub fn build(self) -> Result<Crawler, Box<dyn Error>> {
+        let mut crawler = Crawler::new(self.opts)?;
+
+        crawler.validators.extend(self.validators);
+        Ok(crawler)
+    }
+}
+
+impl Crawler {
+    /// Stream the progress of the crawl, until it is finished. Events are buffered, so the
+    /// stream can be consumed at its own pace (e.g. while awaiting [`Crawler::run`]).
+    pub fn events(&mut self) -> impl Stream<Item = CrawlEvent> {
+        self.events.subscribe()
+    }
+
+    /// Crawl the sites (their pages, then the external links), and get the result of every
+    /// URL.
+    pub async fn run(self) -> Vec<Entry> {
+        let mut crawler = self.crawl().await;
+
+        crawler.stages.enter(Stage::Verification);
+        crawler.entries()
+    }
+
+    /// Probe the error pages, then run the discovery and external checks stages.
+    pub(crate) async fn crawl(mut self) -> Self {
+        self.events.start();
+
+        let mut worker = Worker::new(self).await;
+
+        // A build directory has no server, thus no error page of its own, and listed URLs are
+        // only checked.
+        if worker.static_dir.is_none() && worker.opts.input.is_none() && !worker.opts.dry_run {
+            for base in worker.bases.clone() {
+                if let Err(e) = worker.probe_error_page(&base).await {
+                    warn!("Cannot probe the error page of {}: {}", base, e);
+                }
+            }
+        }
+
+        let crawler = Arc::clone(&worker.crawler);
+
+        worker.stages.enter(Stage::Discovery);
+        worker.unlock();
+        run_workers(&crawler, false).await;
+        worker.relock().await;
+
+        if !worker.opts.dry_run {
+            worker.stages.enter(Stage::ExternalChecks);
+            worker.unlock();
+            run_workers(&crawler, true).await;
+            worker.relock().await;
+        }
+
+        drop(crawler);
+
+        let mut crawler = worker.into_inner();
+
+        crawler.events.finish();
+        crawler
+    }
+
+    /// Create a new crawler instance, failing if a file given in the options cannot be loaded
+    /// (or a credential is invalid).
+    pub(crate) fn new(mut opts: Opts) -> Result<Self, Box<dyn Error>> {
+        if opts.polite && opts.from.is_none() {
+            return Err("--polite requires a contact address (--from)".into());
+        }
+
+        if opts.polite && opts.revalidate_cache.is_none() {
+            opts.revalidate_cache = Some(revalidate::DEFAULT_PATH.to_string());
+        }
+
+        let input = opts
+            .input
+            .as_deref()
+            .map(read_urls)
+            .transpose()
+            .map_err(|e| format!("cannot read the input URLs: {}", e))?;
+
+        // Without entrypoint, relative input URLs are completed with the first one.
+        let entrypoints = match &input {
+            Some(urls) if opts.entrypoint.is_empty() => {
+                urls.iter().take(1).cloned().collect::<Vec<_>>()
+            }
+            _ => opts.entrypoint.clone(),
+        };
+        let mut bases: Vec<Url> = entrypoints
+            .iter()
+            .map(|x| Url::parse(x).map_err(|e| format!("cannot parse the entrypoint {}: {}", x, e)))
+            .collect::<Result<_, _>>()?;
+        let mut middlewares = Chain::default();
+        let mut auths = vec![];
+
+        for url in &mut bases {
+            if let Some(auth) = Auth::from_url(url) {
+                auths.push(Arc::new(auth));
+            }
+        }
+
+        // Credentials are only sent to the entrypoint hosts, and the explicitly listed ones.
+        let auth_hosts: Vec<String> = bases
+            .iter()
+            .filter_map(|x| x.host_str().map(str::to_string))
+            .chain(opts.auth_host.iter().cloned())
+            .collect();
+
+        if let Some(credentials) = &opts.auth {
+            auths.push(Arc::new(
+                Auth::basic(credentials, auth_hosts.clone())
+                    .ok_or("invalid --auth credentials, not a valid header value")?,
+            ));
+        }
+
+        if let Some(token) = &opts.bearer {
+            auths.push(Arc::new(
+                Auth::bearer(token, auth_hosts)
+                    .ok_or("invalid --bearer token, not a valid header value")?,
+            ));
+        }
+
+        for auth in &auths {
+            middlewares.push(auth.clone());
+        }
+
+        let policies = HostPolicies::load(&opts.host_policy)
+            .map_err(|e| format!("cannot load the host policies: {}", e))?;
+        let content_rules = content::ContentRules::load(&opts.content_rules)
+            .map_err(|e| format!("cannot load the content rules: {}", e))?;
+        let extractors = extractors::Extractors::load(&opts.extractors)
+            .map_err(|e| format!("cannot load the extractors: {}", e))?;
+        let plugins = Plugins::spawn(&opts.plugin, opts.plugin_timeout)
+            .map_err(|e| format!("cannot start the plugins: {}", e))?;
+        #[cfg(feature = "wasm")]
+        let wasm_plugins = wasm::WasmPlugins::load(&opts.wasm_plugin)
+            .map_err(|e| format!("cannot load the WASM plugins: {}", e))?;
+        let static_dir = match &opts.command {
+            Some(Command::Check(CheckOpts { dir: Some(dir), .. })) => {
+                Some(Arc::new(StaticDir::new(dir, &bases[0])))
+            }
+            _ => None,
+        };
+
+        if let Some(dir) = &static_dir {
+            middlewares.push(dir.clone());
+        }
+
+        let usage = Arc::new(Usage::default());
+        let deprecations = Arc::new(Deprecations::default());
+        let cookies = Arc::new(match &opts.cookies_from {
+            Some(path) => {
+                Cookies::load(path).map_err(|e| format!("cannot load the cookies: {}", e))?
+            }
+            None => Cookies::default(),
+        });
+        let rewrites = Arc::new(Rewrites::new(opts.rewrite.clone()));
+
+        middlewares.push(rewrites.clone());
+
+        // Caches are usually bypassed by cookies: pages are primed as anonymous visitors get them.
+        if !opts.prime_cache {
+            middlewares.push(cookies.clone());
+        }
+
+        if let Some(path) = &opts.user_agent_file {
+            middlewares.push(
+                UserAgents::load(path).map_err(|e| format!("cannot load the user agents: {}", e))?,
+            );
+        }
+        middlewares.push(Cache::default());
+
+        let renderer = match opts.render {
+            true => Some(Arc::new(render::Renderer::new(
+                &opts.browser,
+                opts.render_concurrency,
+                Some(&user_agent(&opts)),
+                opts.browser_no_sandbox,
+                opts.render_recycle_after,
+                opts.render_max_memory,
+            ))),
+            false => None,
+        };
+        let revalidation = opts
+            .revalidate_cache
+            .as_deref()
+            .map(Revalidation::load)
+            .transpose()
+            .map_err(|e| format!("cannot load the revalidation cache: {}", e))?
+            .map(Arc::new);
+
+        if let Some(revalidation) = &revalidation {
+            middlewares.push(revalidation.clone());
+        }
+        middlewares.push(usage.clone());
+        middlewares.push(deprecations.clone());
+
+        let record_types = match (opts.ipv4, opts.ipv6) {
+            (true, _) => vec![dns::RecordType::A],
+            (_, true) => vec![dns::RecordType::Aaaa],
+            _ => vec![dns::RecordType::A, dns::RecordType::Aaaa],
+        };
+        let dns = match (opts.dns, &opts.doh) {
+            (Some(server), _) => Some(dns::Server::Udp(server)),
+            (_, Some(url)) => Some(dns::Server::Https(url.clone())),
+            _ => None,
+        }
+        .map(|x| dns::Resolver::new(x, record_types))
+        .transpose()
+        .map_err(|e| format!("cannot build the resolver: {}", e))?
+        .map(Arc::new);
+
+        let mut overrides = resolve::Overrides::new();
+
+        // A host can be given several addresses, tried in order.
+        for (host, port, ip) in &opts.resolve {
+            overrides.entry((host.clone(), *port)).or_default().push(SocketAddr::new(*ip, *port));
+        }
+
+        let overrides = Arc::new(std::sync::Mutex::new(overrides));
+        let resolver = match opts.resolve.is_empty() && dns.is_none() {
+            true => None,
+            false => Some(
+                resolve::spawn(Arc::clone(&overrides), opts.ipv6)
+                    .map_err(|e| format!("cannot start the resolution proxy: {}", e))?,
+            ),
+        };
+
+        let client = client_builder(&opts, &usage)
+            .and_then(|x| Ok(x.build()?))
+            .map_err(|e| format!("cannot build the HTTP client: {}", e))?;
+        #[cfg(feature = "http3")]
+        let http3 = match opts.http3 {
+            true => {
+                let build = |quic: bool| -> Result<Client, Box<dyn Error>> {
+                    let builder = client_builder(&opts, &usage)?.use_rustls_tls().no_proxy();
+
+                    match quic {
+                        true => Ok(builder.http3_prior_knowledge().build()?),
+                        false => Ok(builder.build()?),
+                    }
+                };
+
+                let clients = build(true).and_then(|quic| Ok((quic, build(false)?)));
+
+                Some(clients.map_err(|e| format!("cannot build the HTTP/3 clients: {}", e))?)
+            }
+            false => None,
+        };
+        let mut validators = Validators::default();
+
+        for assertion in &opts.assert {
+            validators.push(Arc::new(assertion.clone()));
+        }
+
+        let webhook = opts
+            .webhook
+            .clone()
+            .map(notify::Webhook::new)
+            .transpose()
+            .map_err(|e| format!("cannot build the webhook client: {}", e))?;
+
+        let spill_dir = match &opts.state {
+            Some(path) => PathBuf::from(format!("{}.queue", path)),
+            None => std::env::temp_dir().join(format!("http-status-check-{}", std::process::id())),
+        };
+        let pending = Frontier::new(
+            opts.spill_queue,
+            spill_dir,
+            opts.state.is_some(),
+            opts.strategy == "dfs",
+        );
+
+        let mut crawler = Self {
+            bases,
+            opts,
+            pending,
+            external: VecDeque::new(),
+            responses: HashMap::new(),
+            visited: HashSet::new(),
+            stages: Stages::default(),
+            policies,
+            last_requests: HashMap::new(),
+            in_flight: HashMap::new(),
+            interrupted: Arc::new(AtomicBool::new(false)),
+            host_times: HashMap::new(),
+            robots: HashMap::new(),
+            resolver,
+            overrides,
+            dns,
+            addresses: HashMap::new(),
+            client,
+            clients: HashMap::new(),
+            #[cfg(feature = "http3")]
+            http3,
+            #[cfg(feature = "http3")]
+            http3_failures: HashMap::new(),
+            redirects: HashMap::new(),
+            permanent_redirects: HashSet::new(),
+            plugins,
+            seeds: None,
+            static_dir,
+            middlewares,
+            auths,
+            fragments: HashMap::new(),
+            anchors: HashMap::new(),
+            canonicals: HashMap::new(),
+            indexable: HashSet::new(),
+            alternates: HashMap::new(),
+            content_rules,
+            extractors,
+            content_failures: vec![],
+            mixed_content: vec![],
+            http_links: BTreeSet::new(),
+            other_schemes: BTreeMap::new(),
+            single_scheme_hosts: BTreeMap::new(),
+            invalid_mailto: vec![],
+            invalid_urls: vec![],
+            header_audits: vec![],
+            seo_audits: vec![],
+            contents: HashMap::new(),
+            patterns: HashMap::new(),
+            plan: BTreeMap::new(),
+            social: HashMap::new(),
+            favicons: HashSet::new(),
+            edges: vec![],
+            usage,
+            deprecations,
+            cookies,
+            revalidation,
+            renderer,
+            webhook,
+            events: Events::default(),
+            validators,
+            rewrites,
+            error_pages: HashMap::new(),
+            saved_at: Instant::now(),
+            pages: 0,
+            checks: 0,
+            #[cfg(feature = "wasm")]
+            wasm_plugins,
+        };
+
+        crawler.stages.enter(Stage::Seeding);
+
+        if crawler.opts.resume
+            && crawler.restore().map_err(|e| format!("cannot load the crawl state: {}", e))?
+        {
+            return Ok(crawler);
+        }
+
+        if let Some(urls) = input {
+            urls.iter().for_each(|x| crawler.queue(x, Resource::Page, None));
+            crawler.seeds = Some(HashSet::new());
+        } else if let (Some(spec), Some(url_map)) =
+            (&crawler.opts.changed_from, &crawler.opts.url_map)
+        {
+            let urls = diff::changed_urls(spec, url_map)
+                .map_err(|e| format!("cannot list the changed pages: {}", e))?;
+
+            if urls.is_empty() {
+                warn!("No changed page found in {}", spec);
+            }
+
+            urls.iter().for_each(|x| crawler.queue(x, Resource::Page, None));
+            crawler.seeds = Some(urls.into_iter().collect());
+        } else {
+            for base in crawler.bases.clone() {
+                crawler.queue(base.as_str(), Resource::Page, None);
+            }
+        }
+
+        Ok(crawler)
+    }
+
+    /// Restore the progress saved into the --state file, if it exists.
+    fn restore(&mut self) -> Result<bool, Box<dyn Error>> {
+        let path = self.opts.state.as_deref().unwrap_or_default();
+
+        if !std::path::Path::new(path).exists() {
+            warn!("No crawl state in {}, starting over", path);
+            return Ok(false);
+        }
+
+        let checkpoint: Checkpoint = serde_json::from_reader(std::fs::File::open(path)?)?;
+
+        self.responses = checkpoint.responses.into_owned();
+        self.visited = checkpoint.visited.into_owned();
+        self.visited.extend(self.responses.keys().map(|x| url_hash(x)));
+
+        // Deserialized URLs are distinct allocations: share the keys again.
+        let keys: HashSet<Arc<str>> = self.responses.keys().cloned().collect();
+        let share = |url: &Arc<str>| Arc::clone(keys.get(url).unwrap_or(url));
+
+        for response in self.responses.values_mut() {
+            response.referrers.iter_mut().for_each(|x| *x = share(x));
+        }
+
+        self.external = checkpoint.external.iter().map(share).collect();
+
+        let pending: Vec<(Arc<str>, u64)> =
+            checkpoint.pending.iter().map(|x| (share(x), self.rank(x))).collect();
+
+        self.pending.restore(pending, checkpoint.spilled.into_owned());
+
+        info!(
+            "Resuming the crawl of {}: {} URLs done, {} pending",
+            path,
+            self.visited.len().saturating_sub(self.pending.len() + self.external.len()),
+            self.pending.len() + self.external.len()
+        );
+        Ok(true)
+    }
+
+    /// Save the progress into the --state file, if the last save is old enough (or when forced).
+    ///
+    /// The file is replaced atomically, so a crawl killed while saving can still be resumed.
+    pub(crate) fn checkpoint(&mut self, force: bool) -> Result<(), Box<dyn Error>> {
+        let path = match &self.opts.state {
+            Some(path) if force || self.saved_at.elapsed() >= CHECKPOINT_INTERVAL => path,
+            _ => return Ok(()),
+        };
+        let (pending, spilled) = self.pending.save()?;
+        let checkpoint = Checkpoint {
+            pending: Cow::Owned(pending),
+            spilled: Cow::Owned(spilled),
+            external: Cow::Borrowed(&self.external),
+            responses: Cow::Borrowed(&self.responses),
+            visited: Cow::Borrowed(&self.visited),
+        };
+        let temporary = format!("{}.tmp", path);
+
+        serde_json::to_writer(std::fs::File::create(&temporary)?, &checkpoint)?;
+        std::fs::rename(&temporary, path)?;
+
+        debug!("Crawl state saved into {}", path);
+        self.pending.saved();
+        self.saved_at = Instant::now();
+        Ok(())
+    }
+
+    /// Build the report entry of a response, annotated and given its verdict.
+    fn entry(&self, url: &str, response: &Response) -> Entry {
+        let time = response.time();
+        let slow = time.filter(|x| {
+            response.resource == Resource::Page
+                && self.opts.slow_threshold.is_some_and(|max| *x > max)
+        });
+        let uncompressed = response.length.filter(|x| {
+            self.opts.require_compression
+                && response.compressible
+                && response.encoding.is_none()
+                && *x >= MIN_COMPRESSIBLE_SIZE
+        });
+        let stale = response
+            .last_modified
+            .as_deref()
+            .and_then(|x| httpdate::parse_http_date(x).ok())
+            .and_then(|x| SystemTime::now().duration_since(x).ok())
+            .filter(|x| self.opts.stale_after.is_some_and(|max| *x > max));
+        let mut annotations = response.annotations.clone();
+
+        if let Some(age) = stale {
+            annotations.push(format!(
+                "stale (last modified {} days ago)",
+                age.as_secs() / 86400
+            ));
+        }
+
+        if let Some(time) = slow {
+            annotations.push(format!("slow ({:.2}s)", time.as_secs_f64()));
+        }
+
+        // The browser metrics of rendered pages, against their budgets.
+        let timings = response.timings.clone().unwrap_or_default();
+        let over = |value: Option<u64>, budget: Option<Duration>| {
+            value.filter(|x| budget.is_some_and(|max| *x > max.as_millis() as u64))
+        };
+        let fcp = over(timings.fcp_ms, self.opts.fcp_budget);
+        let dom_content_loaded =
+            over(timings.dom_content_loaded_ms, self.opts.dom_content_loaded_budget);
+        let heavy = timings
+            .transfer_size
+            .filter(|x| self.opts.transfer_size_budget.is_some_and(|max| *x > max));
+
+        if let Some(ms) = fcp {
+            annotations.push(format!("slow (first contentful paint after {}ms)", ms));
+        }
+
+        if let Some(ms) = dom_content_loaded {
+            annotations.push(format!("slow (DOMContentLoaded after {}ms)", ms));
+        }
+
+        if let Some(size) = heavy {
+            annotations.push(format!("heavy ({} bytes transferred)", size));
+        }
+
+        if let Some(length) = uncompressed {
+            annotations.push(format!("served uncompressed ({} bytes)", length));
+        }
+
+        if !response.console_errors.is_empty() {
+            annotations.push(format!("{} console errors", response.console_errors.len()));
+        }
+
+        if !response.failed_requests.is_empty() {
+            annotations.push(format!("{} failed requests", response.failed_requests.len()));
+        }
+
+        if let Some(reason) = &response.http3_fallback {
+            annotations.push(format!("fell back from HTTP/3 ({})", reason));
+        }
+
+        Entry {
+            url: url.to_string(),
+            status: response.status,
+            count: response.count,
+            verdict: match self.verdict(url, response.status) {
+                _ if response.invalid => Verdict::Failure,
+                Verdict::Success
+                    if response.too_large
+                        || response.soft_404
+                        || response.fallback
+                        || slow.is_some()
+                        || fcp.is_some()
+                        || dom_content_loaded.is_some()
+                        || heavy.is_some()
+                        || stale.is_some()
+                        || uncompressed.is_some()
+                        || !response.console_errors.is_empty()
+                        || !response.failed_requests.is_empty()
+                    || response.http3_fallback.is_some() =>
+                {
+                    Verdict::Warning
+                }
+                _ if response.skipped => Verdict::Warning,
+                verdict => verdict,
+            },
+            resource: response.resource,
+            redirects: response.redirects.clone(),
+            annotations,
+            found_on: response.referrers.iter().map(|x| x.to_string()).collect(),
+            address: self.addresses.get(url).map(SocketAddr::to_string),
+            redirect_addresses: match response.redirects.is_empty() {
+                true => vec![],
+                false => response
+                    .redirects
+                    .iter()
+                    .map(|x| self.addresses.get(x.as_str()).map(SocketAddr::to_string))
+                    .collect(),
+            },
+            ttfb_ms: response.ttfb.map(|x| x.as_millis() as u64),
+            time_ms: time.map(|x| x.as_millis() as u64),
+            http_version: response.version.clone(),
+            http3_fallback: response.http3_fallback.clone(),
+            content_encoding: response.encoding.clone(),
+            last_modified: response.last_modified.clone(),
+            age_s: response.age,
+            expires: response.expires.clone(),
+            headers: response.headers.clone(),
+            cache_status: response.cache_status,
+            error: response.error.clone(),
+            error_class: response.error_class,
+            console_errors: response.console_errors.clone(),
+            failed_requests: response.failed_requests.clone(),
+            timings: response.timings.clone(),
+            screenshot: response.screenshot.clone(),
+            archived: None,
+            lang: self.language(url).map(|x| x.trim_start_matches('/').to_string()),
+        }
+    }
+
+    /// Build report entries from every collected response, sorted by URL.
+    pub(crate) fn entries(&self) -> Vec<Entry> {
+        let mut entries: Vec<Entry> =
+            self.responses.iter().map(|(url, response)| self.entry(url, response)).collect();
+
+        // Duplicates are collapsed into their canonical page.
+        for duplicate in self.duplicates() {
+            let position = entries.iter().position(|x| x.url == duplicate.url);
+            let entry = match position {
+                Some(position) => entries.swap_remove(position),
+                None => continue,
+            };
+
+            if let Some(canonical) = entries.iter_mut().find(|x| x.url == duplicate.canonical) {
+                canonical.count += entry.count;
+                canonical
+                    .found_on
+                    .extend(entry.found_on.into_iter().filter(|x| *x != duplicate.canonical));
+                canonical.found_on.sort();
+                canonical.found_on.dedup();
+            }
+        }
+
+        entries.sort_by(|a, b| a.url.cmp(&b.url));
+        entries
+    }
+
+    /// List the successful pages declaring another (known) page as their canonical URL.
+    pub(crate) fn duplicates(&self) -> Vec<Duplicate> {
+        let mut duplicates: Vec<Duplicate> = self
+            .canonicals
+            .iter()
+            .filter(|(url, canonical)| {
+                let success = match self.responses.get(url.as_str()) {
+                    Some(response) => (200..300).contains(&response.status),
+                    None => self.is_omitted(url),
+                };
+
+                success && self.visited.contains(&url_hash(canonical))
+            })
+            .map(|(url, canonical)| Duplicate {
+                url: url.clone(),
+                canonical: canonical.clone(),
+            })
+            .collect();
+
+        duplicates.sort_by(|a, b| a.url.cmp(&b.url));
+        duplicates
+    }
+
+    /// Group the same-domain pages serving the same content, e.g. a misconfigured rewrite
+    /// serving the homepage for any URL.
+    ///
+    /// Pages collapsed into their canonical URL declare being duplicates, they are left out.
+    pub(crate) fn duplicate_content(&self) -> Vec<DuplicateContent> {
+        let mut groups: Vec<DuplicateContent> = self
+            .contents
+            .values()
+            .map(|urls| DuplicateContent {
+                urls: urls
+                    .iter()
+                    .filter(|x| !self.canonicals.contains_key(*x))
+                    .cloned()
+                    .collect(),
+            })
+            .filter(|x| x.urls.len() > 1)
+            .collect();
+
+        groups.sort_by(|a, b| b.urls.len().cmp(&a.urls.len()).then_with(|| a.urls.cmp(&b.urls)));
+        groups
+    }
+
+    /// List the fragments linked to that are missing from their (crawled) target page.
+    ///
+    /// Pages that have not been parsed (external, not HTML...) cannot be verified.
+    pub(crate) fn broken_anchors(&self) -> Vec<BrokenAnchor> {
+        let mut broken = vec![];
+
+        for (url, fragments) in &self.fragments {
+            let anchors = match self.anchors.get(url) {
+                Some(anchors) => anchors,
+                None => continue,
+            };
+
+            for fragment in fragments.iter().filter(|x| !anchors.contains(*x)) {
+                warn!("Broken anchor - {}#{}", url, fragment);
+                broken.push(BrokenAnchor {
+                    url: url.clone(),
+                    fragment: fragment.clone(),
+                });
+            }
+        }
+
+        broken.sort_by(|a, b| (&a.url, &a.fragment).cmp(&(&b.url, &b.fragment)));
+        broken
+    }
+
+    /// List the plain `http://` links of `https://` pages, and whether they are redirected to
+    /// `https://` (unless they have not been crawled).
+    pub(crate) fn insecure_links(&self) -> Vec<InsecureLink> {
+        self.http_links
+            .iter()
+            .map(|(page, url)| InsecureLink {
+                page: page.clone(),
+                url: url.clone(),
+                redirects_to_https: match self.responses.get(url.as_str()) {
+                    Some(x) if x.status != 0 => {
+                        Some(x.redirects.first().is_some_and(|x| x.starts_with("https://")))
+                    }
+                    // Left out with --failures-only, it has not been redirected.
+                    None if self.is_omitted(url) => Some(false),
+                    _ => None,
+                },
+            })
+            .collect()
+    }
+
+    /// List the redirect chains ending on another domain: internal URLs leaving the site, and
+    /// external links whose destination is not on their registrable domain (`www.` prefixes and
+    /// other subdomains don't count).
+    pub(crate) fn off_domain_redirects(&self) -> Vec<OffDomainRedirect> {
+        let registrable = |url: &Url| {
+            url.domain()
+                .map(|x| psl::domain_str(x).unwrap_or(x).to_string())
+                .or_else(|| url.host_str().map(str::to_string))
+        };
+        let mut redirects: Vec<OffDomainRedirect> = self
+            .responses
+            .iter()
+            .filter_map(|(url, response)| {
+                let destination = Url::parse(response.redirects.last()?).ok()?;
+                let parsed = Url::parse(url).ok()?;
+                let internal = self.is_same_domain(&parsed);
+                let off_domain = match internal {
+                    true => !self.is_same_domain(&destination),
+                    false => registrable(&parsed) != registrable(&destination),
+                };
+
+                off_domain.then(|| OffDomainRedirect {
+                    url: url.to_string(),
+                    internal,
+                    destination: destination.to_string(),
+                    chain: response
+                        .redirects
+                        .iter()
+                        .map(|x| Hop {
+                            url: x.clone(),
+                            host: host(x),
+                        })
+                        .collect(),
+                })
+            })
+            .collect();
+
+        redirects.sort_by(|a, b| b.internal.cmp(&a.internal).then_with(|| a.url.cmp(&b.url)));
+        redirects
+    }
+
+    /// Group the linked URLs permanently redirected (every hop) into the same destination, the
+    /// groups linked from the most pages first.
+    pub(crate) fn redirect_groups(&self) -> Vec<RedirectGroup> {
+        let mut groups: BTreeMap<&str, Vec<RedirectedUrl>> = BTreeMap::new();
+
+        for (url, response) in self.responses.iter() {
+            let (destination, hops) = match response.redirects.split_last() {
+                Some(chain) => chain,
+                None => continue,
+            };
+            let permanent = std::iter::once(&**url)
+                .chain(hops.iter().map(String::as_str))
+                .all(|x| self.permanent_redirects.contains(x));
+
+            if permanent && !response.referrers.is_empty() {
+                groups.entry(destination).or_default().push(RedirectedUrl {
+                    url: url.to_string(),
+                    referrers: response.referrers.iter().map(|x| x.to_string()).collect(),
+                });
+            }
+        }
+
+        let mut groups: Vec<RedirectGroup> = groups
+            .into_iter()
+            .filter(|(_, urls)| urls.len() > 1)
+            .map(|(destination, mut urls)| {
+                let pages: HashSet<&str> =
+                    urls.iter().flat_map(|x| x.referrers.iter().map(String::as_str)).collect();
+                let referring_pages = pages.len();
+
+                urls.sort_by(|a, b| {
+                    b.referrers.len().cmp(&a.referrers.len()).then_with(|| a.url.cmp(&b.url))
+                });
+
+                RedirectGroup {
+                    destination: destination.to_string(),
+                    urls,
+                    referring_pages,
+                }
+            })
+            .collect();
+
+        groups.sort_by_key(|x| Reverse(x.referring_pages));
+        groups
+    }
+
+    /// List the URL templates whose --max-per-pattern budget was spent, the most skipped first.
+    pub(crate) fn budgets(&self) -> Vec<Budget> {
+        let mut budgets: Vec<Budget> = self
+            .patterns
+            .iter()
+            .filter(|(_, (_, skipped))| *skipped > 0)
+            .map(|(template, (crawled, skipped))| Budget {
+                template: template.clone(),
+                crawled: *crawled,
+                skipped: *skipped,
+            })
+            .collect();
+
+        budgets.sort_by(|a, b| b.skipped.cmp(&a.skipped).then_with(|| a.template.cmp(&b.template)));
+        budgets
+    }
+
+    /// List the alternate versions of pages (`hreflang`) that are broken, redirected, or not
+    /// linking back to the page.
+    ///
+    /// Alternates that have not been parsed (external, not HTML...) cannot be verified.
+    pub(crate) fn hreflang_errors(&self) -> Vec<HreflangError> {
+        let mut errors = vec![];
+
+        for (url, alternates) in &self.alternates {
+            for alternate in alternates.iter().filter(|x| x.href != *url) {
+                let reason = match self.responses.get(alternate.href.as_str()) {
+                    Some(x) if !x.redirects.is_empty() => {
+                        format!("redirected to {}", x.redirects[x.redirects.len() - 1])
+                    }
+                    Some(x) if !(200..300).contains(&x.status) => format!("status {}", x.status),
+                    // Left out with --failures-only, it answered 2xx without being redirected.
+                    None if !self.is_omitted(&alternate.href) => continue,
+                    _ if !self.anchors.contains_key(&alternate.href) => continue,
+                    _ => match self.alternates.get(&alternate.href) {
+                        Some(x) if x.iter().any(|x| x.href == *url) => continue,
+                        _ => "no return link".to_string(),
+                    },
+                };
+
+                warn!(
+                    "Hreflang error - {} ({}) on {}: {}",
+                    alternate.href, alternate.hreflang, url, reason
+                );
+                errors.push(HreflangError {
+                    url: url.clone(),
+                    hreflang: alternate.hreflang.clone(),
+                    alternate: alternate.href.clone(),
+                    reason,
+                });
+            }
+        }
+
+        errors.sort_by(|a, b| (&a.url, &a.alternate).cmp(&(&b.url, &b.alternate)));
+        errors
+    }
+
+    /// Add the closest Wayback Machine snapshot (if any) to the dead external links.
+    pub(crate) async fn archived(&mut self, results: &mut [Entry]) {
+        let api = Url::parse(wayback::API).expect("valid API URL");
+        let client = match self.client_for(&api) {
+            Ok(client) => client,
+            Err(e) => return warn!("Cannot query the Wayback Machine: {}", e),
+        };
+
+        for entry in results.iter_mut().filter(|x| x.status == 404 || x.status == 410) {
+            if Url::parse(&entry.url).map_or(true, |x| self.is_in_scope(&x)) {
+                continue;
+            }
+
+            match wayback::closest(&client, &entry.url).await {
+                Ok(Some(snapshot)) => {
+                    info!("{} is archived as {}", entry.url, snapshot);
+                    entry.archived = Some(snapshot);
+                }
+                Ok(None) => debug!("{} has never been archived", entry.url),
+                Err(e) => warn!("Cannot query the Wayback Machine for {}: {}", entry.url, e),
+            }
+        }
+    }
+
+    /// Queue the links of a page (parsed, or reused while unchanged) and record it.
+    pub(crate) fn on_page(
+        &mut self,
+        url: &str,
+        referrer: &str,
+        status: StatusCode,
+        redirects: Vec<String>,
+        page: revalidate::Page,
+    ) {
+        let format = page.format;
+
+        if format == Format::Html {
+            self.anchors.insert(url.to_string(), page.anchors);
+        }
+
+        // The favicon of a host is checked once, found on its first page.
+        let origin = Url::parse(referrer)
+            .ok()
+            .filter(|_| self.opts.check_icons && format == Format::Html)
+            .map(|x| x.origin().ascii_serialization());
+
+        if let Some(origin) = origin {
+            if self.favicons.insert(origin.clone()) {
+                self.queue(&format!("{}/favicon.ico", origin), Resource::Image, Some(referrer));
+            }
+        }
+
+        // Pages reached through several redirects are only parsed once for link edges.
+        let record_edges = (self.opts.link_equity.is_some() || self.opts.graph.is_some())
+            && format == Format::Html
+            && !self.edges.iter().any(|x| x.source == referrer);
+
+        for link in page.links {
+            if record_edges && link.resource == Resource::Page {
+                let target = self.normalize(&self.format_url(&link.href, Some(referrer)));
+
+                if Url::parse(&target).is_ok_and(|x| self.is_same_domain(&x)) {
+                    self.edges.push(equity::Edge {
+                        source: referrer.to_string(),
+                        target,
+                        text: link.text.clone(),
+                        rel: link.rel.clone(),
+                        nofollow: link.nofollow,
+                    });
+                }
+            }
+
+            if let Some(scheme) = extract::other_scheme(&link.href) {
+                self.skip_scheme(&link.href, scheme, referrer);
+                continue;
+            }
+
+            if link.nofollow && !self.opts.ignore_nofollow {
+                debug!("Skipping nofollow link {} found on {}", link.href, referrer);
+                continue;
+            }
+
+            // Stylesheet (manifest, feed and XML) references are relative to the document itself.
+            let href = match format {
+                Format::Html => link.href,
+                _ => Url::parse(url)
+                    .and_then(|x| x.join(&link.href))
+                    .map_or(link.href, String::from),
+            };
+
+            if referrer.starts_with("https://")
+                && link.resource != Resource::Page
+                && link.resource != Resource::Link
+                && self.format_url(&href, Some(referrer)).starts_with("http://")
+            {
+                warn!("Mixed content - {} loads {}", referrer, href);
+                self.mixed_content.push(report::MixedContent {
+                    page: referrer.to_string(),
+                    url: self.format_url(&href, Some(referrer)),
+                    resource: link.resource,
+                });
+            }
+
+            if self.opts.https_only_links && referrer.starts_with("https://") {
+                let target = self.normalize(&self.format_url(&href, Some(referrer)));
+
+                if target.starts_with("http://")
+                    && Url::parse(&target).is_ok_and(|x| self.is_same_domain(&x))
+                {
+                    warn!("Plain http:// link - {} links to {}", referrer, target);
+                    self.http_links.insert((referrer.to_string(), target));
+                }
+            }
+
+            self.queue(&href, link.resource, Some(referrer));
+        }
+
+        self.record(url, status, redirects);
+
+        let same_domain = Url::parse(referrer).is_ok_and(|x| self.is_same_domain(&x));
+
+        if format == Format::Html
+            && status.is_success()
+            && same_domain
+            && !page.noindex
+            && page.canonical.is_none()
+        {
+            self.indexable.insert(url.to_string());
+        }
+
+        if page.noindex {
+            if let Some(response) = self.responses.get_mut(url) {
+                response.annotations.push("noindex".to_string());
+            }
+        }
+
+        if let Some(canonical) = page.canonical {
+            debug!("{} is a duplicate of {}", url, canonical);
+            self.canonicals.insert(url.to_string(), canonical);
+        }
+
+        if !page.alternates.is_empty() {
+            let alternates = page
+                .alternates
+                .into_iter()
+                .map(|x| extract::Alternate {
+                    href: self.normalize(&self.format_url(&x.href, Some(referrer))),
+                    hreflang: x.hreflang,
+                })
+                .collect();
+
+            self.alternates.insert(referrer.to_string(), alternates);
+        }
+    }
+
+    /// Count a link which is not a web page, checking the addresses of `mailto:` links with
+    /// --check-mailto.
+    fn skip_scheme(&mut self, href: &str, scheme: String, page: &str) {
+        debug!("Skipping {} link found on {}", scheme, page);
+
+        let reason = Some(href)
+            .filter(|_| self.opts.check_mailto && scheme == "mailto")
+            .and_then(extract::mailto_error);
+
+        if let Some(reason) = reason {
+            warn!("Invalid mailto - {} on {} ({})", href, page, reason);
+            self.invalid_mailto.push(report::InvalidMailto {
+                page: page.to_string(),
+                href: href.to_string(),
+                reason,
+            });
+        }
+
+        *self.other_schemes.entry(scheme).or_default() += 1;
+    }
+
+    /// Classify a response status, according to the host policy.
+    pub(crate) fn verdict(&self, url: &str, status: u16) -> Verdict {
+        if self.opts.accept.contains(status) {
+            return Verdict::Success;
+        }
+
+        let policy = Url::parse(url).ok().and_then(|x| self.policies.find(&x).cloned());
+
+        match policy {
+            Some(policy) if policy.warn.contains(&status) => Verdict::Warning,
+            _ => Verdict::Failure,
+        }
+    }
+
+    /// Store the status of a response, and the redirect chain leading to it.
+    pub(crate) fn record(&mut self, url: &str, status: StatusCode, redirects: Vec<String>) {
+        let target = redirects.last().map_or(url, String::as_str);
+        let previous = self.responses.get(url);
+        let verdict = match previous {
+            Some(response) if response.invalid => Verdict::Failure,
+            _ => self.verdict(target, status.as_u16()),
+        };
+        let code = status.as_u16();
+        let duration_ms = previous.and_then(Response::time).map(|x| x.as_millis() as u64);
+        let referrer = previous.and_then(|x| x.referrers.first()).map(|x| &**x);
+
+        match verdict {
+            Verdict::Success => {
+                info!(url, status = code, duration_ms, referrer, "{} - {}", status, url)
+            }
+            Verdict::Warning => {
+                warn!(url, status = code, duration_ms, referrer, "{} - {}", status, url)
+            }
+            Verdict::Failure => {
+                error!(url, status = code, duration_ms, referrer, "{} - {}", status, url)
+            }
+        }
+
+        let reply = self.plugins.dispatch(&Event::Response {
+            url,
+            status: status.as_u16(),
+            redirects: &redirects,
+        });
+
+        self.visited.insert(url_hash(url));
+
+        let response = self
+            .responses
+            .entry(Arc::from(url))
+            .or_insert_with(|| Response::new(status.as_u16(), 1));
+
+        response.set_status(status.as_u16());
+        response.redirects = redirects;
+        response.annotations.extend(reply.annotations);
+
+        let time_ms = response.time().map(|x| x.as_millis() as u64);
+
+        self.events.emit(match verdict {
+            Verdict::Failure => CrawlEvent::Failed {
+                url: url.to_string(),
+                status: status.as_u16(),
+                error: None,
+                time_ms,
+            },
+            verdict => CrawlEvent::Fetched {
+                url: url.to_string(),
+                status: status.as_u16(),
+                verdict,
+                time_ms,
+            },
+        });
+
+        let broken = verdict == Verdict::Failure && self.opts.webhook_failures;
+
+        if let Some(webhook) = self.webhook.as_mut().filter(|_| broken) {
+            webhook.send(&notify::Payload::BrokenLink {
+                url,
+                status: status.as_u16(),
+                redirects: &response.redirects,
+                found_on: &response.referrers,
+            });
+        }
+    }
+
+    /// Queue a new URL.
+    ///
+    /// This method is smart enough to prevent duplication. A link is always pushed once.
+    pub(crate) fn queue(&mut self, url: &str, resource: Resource, referrer: Option<&str>) {
+        // The host is converted to punycode, spaces and non-ASCII characters percent-encoded.
+        let url = self.normalize(&self.format_url(url, referrer));
+
+        if let Err(e) = Url::parse(&url) {
+            self.invalid_url(&url, referrer, e);
+            return;
+        }
+
+        if self.visited.contains(&url_hash(&url)) {
+            self.found_again(&url, referrer);
+            return;
+        }
+
+        if self.is_excluded(&url) {
+            return;
+        }
+
+        let external = Url::parse(&url).is_ok_and(|x| {
+            !self.is_in_scope(&x) || self.static_dir.as_ref().is_some_and(|dir| !dir.serves(&x))
+        });
+
+        // When checking a build directory, external links are always checked (but never
+        // crawled), as are social card assets.
+        if external
+            && !self.opts.check_external
+            && self.static_dir.is_none()
+            && !self.social.contains_key(&url)
+        {
+            self.plan(&url, false, "out of scope");
+            return;
+        }
+
+        if self.opts.prime_cache && (resource != Resource::Page || is_static_resource(&url)) {
+            self.plan(&url, false, "not a page (--prime-cache)");
+            return;
+        }
+
+        if !external && self.is_over_budget(&url) {
+            self.plan(&url, false, "--max-per-pattern budget spent");
+            return;
+        }
+
+        let reply = self.plugins.dispatch(&Event::Queue { url: &url });
+
+        if reply.veto {
+            debug!("{} vetoed by a plugin", url);
+            self.plan(&url, false, "vetoed by a plugin");
+            return;
+        }
+
+        self.plan(&url, true, if external { "external" } else { "in scope" });
+
+        // Entrypoints are found on no page.
+        let mut response = Response::new(0, referrer.is_some() as u32);
+
+        response.annotations = reply.annotations;
+        response.resource = resource;
+        response.add_referrer(referrer.map(|x| self.intern(x)));
+        response.depth = referrer.and_then(|x| self.responses.get(x)).map_or(0, |x| x.depth + 1);
+
+        let url: Arc<str> = Arc::from(url);
+
+        self.visited.insert(url_hash(&url));
+        self.responses.insert(Arc::clone(&url), response);
+        self.events.emit(CrawlEvent::Queued {
+            url: url.to_string(),
+            referrer: referrer.map(str::to_string),
+        });
+
+        if external {
+            self.external.push_back(url);
+        } else {
+            let rank = self.rank(&url);
+
+            self.pending.push(url, rank);
+        }
+
+        reply.urls.iter().for_each(|x| self.queue(x, Resource::Page, None));
+    }
+
+    /// Count an internal URL against the budget of its template (--max-per-pattern), telling
+    /// whether the budget is already spent.
+    fn is_over_budget(&mut self, url: &str) -> bool {
+        let max = match self.opts.max_per_pattern {
+            Some(max) => max,
+            None => return false,
+        };
+        let template = match Url::parse(url).ok().as_ref().and_then(suggest::template) {
+            Some(template) => template,
+            None => return false,
+        };
+        let (queued, skipped) = self.patterns.entry(template).or_default();
+
+        if *queued < max {
+            *queued += 1;
+            return false;
+        }
+
+        if *skipped == 0 {
+            warn!("Crawl budget of {} hit, skipping the next URLs like {}", max, url);
+        }
+
+        *skipped += 1;
+        true
+    }
+
+    /// Normalize an URL before deduplication: lowercase scheme and host, no default port, no
+    /// empty query and no fragment. Trailing slashes, stripped query parameters and the query
+    /// parameters order are also normalized when asked to.
+    ///
+    /// The fragment is remembered to verify the target page contains the matching anchor (`#top`
+    /// and empty fragments are always valid).
+    pub(crate) fn normalize(&mut self, url: &str) -> String {
+        let mut parsed = match Url::parse(url) {
+            Ok(parsed) => parsed,
+            Err(_) => return url.to_string(),
+        };
+
+        let fragment = parsed
+            .fragment()
+            .map(|x| percent_decode_str(x).decode_utf8_lossy().into_owned());
+
+        parsed.set_fragment(None);
+
+        if self.opts.strip_trailing_slash && parsed.path().len() > 1 && parsed.path().ends_with('/')
+        {
+            let path = parsed.path().trim_end_matches('/').to_string();
+            parsed.set_path(&path);
+        }
+
+        if self.opts.strip_all_params {
+            parsed.set_query(None);
+        } else if !self.opts.strip_params.is_empty() && parsed.query().is_some() {
+            let pairs: Vec<(String, String)> = parsed
+                .query_pairs()
+                .into_owned()
+                .filter(|(k, _)| !self.opts.strip_params.contains(k))
+                .collect();
+
+            parsed.query_pairs_mut().clear().extend_pairs(pairs);
+        }
+
+        if self.opts.sort_query {
+            let mut pairs: Vec<(String, String)> = parsed.query_pairs().into_owned().collect();
+
+            pairs.sort();
+            parsed.query_pairs_mut().clear().extend_pairs(pairs);
+        }
+
+        if parsed.query() == Some("") {
+            parsed.set_query(None);
+        }
+
+        let normalized = parsed.to_string();
+
+        if let Some(fragment) = fragment.filter(|x| !x.is_empty() && !x.eq_ignore_ascii_case("top"))
+        {
+            self.fragments
+                .entry(normalized.clone())
+                .or_default()
+                .insert(fragment);
+        }
+
+        normalized
+    }
+
+    /// Format an URL.
+    ///
+    /// When an URL is relative (`/page`, `page`, `../page`, `//host/page`...), it is resolved
+    /// against the page it was found on (or our base). If the URL is absolute, simply return.
+    pub(crate) fn format_url(&self, url: &str, referrer: Option<&str>) -> String {
+        let base = referrer
+            .and_then(|x| Url::parse(x).ok())
+            .unwrap_or_else(|| self.bases[0].clone());
+
+        match Url::parse(url) {
+            Err(ParseError::RelativeUrlWithoutBase) => match base.join(url) {
+                Ok(full_url) => full_url.to_string(),
+                Err(_) => url.to_string(),
+            },
+            _ => url.to_string(),
+        }
+    }
+
+    /// Rank a pending URL, the lowest ranked being crawled first (--strategy).
+    ///
+    /// The priority strategy adds the depth of the page and the count of path segments (the query
+    /// being one more), pages of the same level being sorted by their count of referrers.
+    fn rank(&self, url: &str) -> u64 {
+        let response = self.responses.get(url);
+        let depth = response.map_or(0, |x| x.depth) as u64;
+
+        match self.opts.strategy.as_str() {
+            "priority" => {
+                let segments = Url::parse(url).map_or(0, |x| {
+                    x.path_segments().map_or(0, |x| x.filter(|x| !x.is_empty()).count())
+                        + x.query().is_some() as usize
+                });
+                let inbound = response.map_or(0, |x| x.referrers.len()).min(99) as u64;
+
+                (depth + segments as u64) * 100 + 99 - inbound
+            }
+            "dfs" => 0,
+            _ => depth,
+        }
+    }
+
+    /// Check if the crawl was interrupted (Ctrl-C or `SIGTERM`).
+    pub(crate) fn is_interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::Relaxed)
+    }
+
+    /// Take the next URL of a queue whose host is below --per-host-concurrency, if any.
+    pub(crate) fn next_url(&mut self, external: bool) -> Option<Arc<str>> {
+        let max = self.opts.per_host_concurrency.map(|x| x.max(1));
+        let in_flight = &self.in_flight;
+        let available =
+            |url: &str| max.is_none_or(|max| in_flight.get(&host(url)).is_none_or(|x| *x < max));
+
+        match external {
+            true => {
+                let position = self.external.iter().position(|x| available(x))?;
+
+                self.external.remove(position)
+            }
+            false => self.pending.pop_where(available),
+        }
+    }
+
+    /// Share the allocation of an URL already met, e.g. a page found again as a referrer.
+    fn intern(&self, url: &str) -> Arc<str> {
+        match self.responses.get_key_value(url) {
+            Some((key, _)) => Arc::clone(key),
+            None => Arc::from(url),
+        }
+    }
+
+    /// Leave out the record of an URL once requested, unless it failed, got a warning or has
+    /// been redirected (--failures-only): it is only remembered as visited.
+    pub(crate) fn forget(&mut self, url: &str) {
+        let success = self.responses.get(url).is_some_and(|x| {
+            x.redirects.is_empty()
+                && x.error.is_none()
+                && self.entry(url, x).verdict == Verdict::Success
+        });
+
+        if success {
+            self.responses.remove(url);
+        }
+    }
+
+    /// Check if the (successful) record of a visited URL has been left out, with --failures-only.
+    fn is_omitted(&self, url: &str) -> bool {
+        !self.responses.contains_key(url) && self.visited.contains(&url_hash(url))
+    }
+
+    /// Count the visited URLs whose record has been left out with --failures-only, but the
+    /// duplicates (collapsed into their canonical page, just as in the results).
+    pub(crate) fn omitted(&self) -> usize {
+        let duplicates = self.duplicates().iter().filter(|x| self.is_omitted(&x.url)).count();
+
+        self.visited.len().saturating_sub(self.responses.len() + duplicates)
+    }
+
+    /// Count a link to an URL already queued, found on another page (or again on the same one).
+    fn found_again(&mut self, url: &str, referrer: Option<&str>) {
+        let referrer = referrer.map(|x| self.intern(x));
+
+        if let Some(entry) = self.responses.get_mut(url) {
+            if referrer.is_some() {
+                entry.increment();
+            }
+
+            entry.add_referrer(referrer);
+        }
+
+        if self.opts.strategy == "priority" {
+            let rank = self.rank(url);
+
+            self.pending.promote(url, rank);
+        }
+    }
+
+    /// Check if an URL should be excluded (over the limit or on a denied domain).
+    fn is_excluded(&mut self, url: &str) -> bool {
+        if self.opts.limit > 0 && self.visited.len() >= self.opts.limit as usize {
+            self.plan(url, false, "--limit reached");
+            return true;
+        }
+
+        if let Some(pattern) = self.opts.exclude.iter().find(|x| x.is_match(url)) {
+            debug!("{} is excluded", url);
+
+            let reason = format!("matches --exclude {}", pattern);
+
+            self.plan(url, false, &reason);
+            return true;
+        }
+
+        let denied = Url::parse(url).is_ok_and(|x| self.is_denied(&x));
+
+        if denied {
+            self.plan(url, false, "denied domain");
+        }
+
+        denied
+    }
+
+    /// Report a link which is not a valid URL, once per page.
+    fn invalid_url(&mut self, href: &str, page: Option<&str>, error: ParseError) {
+        let page = page.map(str::to_string);
+
+        self.plan(href, false, "invalid URL");
+
+        if self.invalid_urls.iter().any(|x| x.href == href && x.page == page) {
+            return;
+        }
+
+        warn!("Invalid URL - {} ({})", href, error);
+        self.invalid_urls.push(report::InvalidUrl {
+            page,
+            href: href.to_string(),
+            reason: error.to_string(),
+        });
+    }
+
+    /// Note whether an URL would be requested, and why, with --dry-run (the first reason wins).
+    fn plan(&mut self, url: &str, requested: bool, reason: &str) {
+        if self.opts.dry_run {
+            self.plan
+                .entry(url.to_string())
+                .or_insert_with(|| (requested, reason.to_string()));
+        }
+    }
+
+    /// List the URLs found by a --dry-run, and whether they would be requested.
+    pub(crate) fn print_plan(&self) {
+        let requested = self.plan.values().filter(|(x, _)| *x).count();
+
+        println!(
+            "{} URLs would be requested, {} would not:",
+            requested,
+            self.plan.len() - requested
+        );
+
+        for (url, (requested, reason)) in &self.plan {
+            let action = match requested {
+                true => "request",
+                false => "skip",
+            };
+
+            println!("  {:<8} {} ({})", action, url, reason);
+        }
+    }
+
+    /// Check if an URL lives on a denied domain.
+    fn is_denied(&self, url: &Url) -> bool {
+        matches_any_domain(url, &self.opts.deny_domain)
+    }
+
+    /// Check if an URL should be crawled (allow list and domain restriction).
+    ///
+    /// Out-of-scope URLs are dropped, unless external checks are enabled.
+    pub(crate) fn is_in_scope(&self, url: &Url) -> bool {
+        let url = &self.rewrites.apply(url);
+
+        if !self.opts.allow_domain.is_empty() {
+            return self.is_same_domain(url) || matches_any_domain(url, &self.opts.allow_domain);
+        }
+
+        !self.opts.restrict_on_domain || self.is_same_domain(url)
+    }
+
+    /// Get the language section (--lang-prefixes) of a same-domain URL, as its prefix.
+    pub(crate) fn language(&self, url: &str) -> Option<&str> {
+        let url = Url::parse(url).ok().filter(|x| self.is_same_domain(x))?;
+        let path = url.path();
+
+        self.opts.lang_prefixes.iter().map(String::as_str).find(|prefix| {
+            path.strip_prefix(prefix)
+                .is_some_and(|x| x.is_empty() || x.starts_with('/'))
+        })
+    }
+
+    /// Check if an URL lives on the same domain as one of the base URLs.
+    ///
+    /// When subdomains are included, both domains are reduced to their registrable part using
+    /// the public suffix list, so `blog.example.co.uk` matches `www.example.co.uk` but not
+    /// `other.co.uk`.
+    pub(crate) fn is_same_domain(&self, url: &Url) -> bool {
+        self.bases.iter().any(|base| {
+            if !self.opts.include_subdomains {
+                return url.domain() == base.domain();
+            }
+
+            match (url.domain(), base.domain()) {
+                (Some(domain), Some(base)) => {
+                    match (psl::domain_str(domain), psl::domain_str(base)) {
+                        (Some(domain), Some(base)) => domain == base,
+                        _ => domain == base,
+                    }
+                }
+                (domain, base) => domain == base,
+            }
+        })
+    }
+
+    /// Add time spent waiting on a host to its budget.
+    pub(crate) fn spend(&mut self, url: &Url, elapsed: Duration) {
+        let max = match self.opts.max_time_per_host {
+            Some(max) => max,
+            None => return,
+        };
+
+        let host = url.host_str().unwrap_or_default();
+        let spent = self.host_times.entry(host.to_string()).or_default();
+
+        if *spent < max && *spent + elapsed >= max {
+            warn!("Time budget exhausted for {}, skipping its remaining URLs", host);
+        }
+
+        *spent += elapsed;
+    }
+
+    /// Check if the time budget of an URL host is exhausted.
+    pub(crate) fn is_exhausted(&self, url: &str) -> bool {
+        let max = match self.opts.max_time_per_host {
+            Some(max) => max,
+            None => return false,
+        };
+
+        let host = Url::parse(url).ok().and_then(|x| x.host_str().map(str::to_string));
+
+        host.and_then(|x| self.host_times.get(&x))
+            .is_some_and(|x| *x >= max)
+    }
+
+    /// Report an URL as skipped (never requested), for the given reason.
+    pub(crate) fn skip(&mut self, url: &str, reason: &str) {
+        debug!("Skipping {}, {}", url, reason);
+
+        if self.opts.dry_run {
+            self.plan.insert(url.to_string(), (false, reason.to_string()));
+        }
+
+        self.visited.insert(url_hash(url));
+
+        let response = self
+            .responses
+            .entry(Arc::from(url))
+            .or_insert_with(|| Response::new(0, 1));
+
+        response.skipped = true;
+        response.annotations.push(format!("skipped: {}", reason));
+    }
+
+    /// Remember how long the response headers of an URL took to arrive, its HTTP version, its
+    /// compression and its freshness headers.
+    pub(crate) fn answered(&mut self, url: &str, answer: &reqwest::Response, ttfb: Duration) {
+        let header = |name| {
+            answer
+                .headers()
+                .get(name)
+                .and_then(|x| x.to_str().ok())
+                .map(|x| x.trim().to_lowercase())
+                .filter(|x| !x.is_empty() && x != "identity")
+        };
+        let content_type = header(reqwest::header::CONTENT_TYPE).unwrap_or_default();
+        let raw = |name| {
+            answer
+                .headers()
+                .get(name)
+                .and_then(|x| x.to_str().ok())
+                .map(|x| x.trim().to_string())
+        };
+        let captured = self
+            .opts
+            .capture_headers
+            .iter()
+            .filter_map(|name| {
+                let values: Vec<&str> = answer
+                    .headers()
+                    .get_all(name.as_str())
+                    .iter()
+                    .filter_map(|x| x.to_str().ok())
+                    .collect();
+
+                match values.is_empty() {
+                    true => None,
+                    false => Some((name.to_lowercase(), values.join(", "))),
+                }
+            })
+            .collect();
+
+        tracing::Span::current().record("status", answer.status().as_u16());
+
+        if let Some(response) = self.responses.get_mut(url) {
+            response.ttfb = Some(ttfb);
+            response.download = None;
+            response.version = Some(format!("{:?}", answer.version()));
+            #[cfg(feature = "http3")]
+            {
+                response.http3_fallback =
+                    answer.extensions().get::<network::Http3Fallback>().map(|x| x.0.clone());
+            }
+            response.encoding = header(reqwest::header::CONTENT_ENCODING);
+            response.compressible = is_compressible(&content_type);
+            response.length = answer.content_length();
+            response.last_modified = raw(reqwest::header::LAST_MODIFIED);
+            response.age = raw(reqwest::header::AGE).and_then(|x| x.parse().ok());
+            response.expires = raw(reqwest::header::EXPIRES);
+            response.headers = captured;
+            response.cache_status = prime::cache_status(answer.headers());
+        }
+    }
+
+    /// Get the (first) address to connect to for an URL, given with --resolve or found with
+    /// --dns/--doh.
+    pub(crate) fn override_for(&self, url: &Url) -> Option<SocketAddr> {
+        let host = url.host_str()?.to_lowercase();
+        let port = url.port_or_known_default()?;
+
+        self.overrides.lock().unwrap().get(&(host, port))?.first().copied()
+    }
+
+    /// Get the proxy to use for an URL, if not the shared one (--proxy or the environment): the
+    /// resolution proxy for --resolve (and --dns/--doh) hosts, the host policy one (`direct`
+    /// meaning none) or, for out-of-scope URLs, the external proxy.
+    pub(crate) fn proxy_for(&self, url: &Url) -> Option<&str> {
+        if self.override_for(url).is_some() {
+            return self.resolver.as_ref().map(Url::as_str);
+        }
+
+        match self.policies.find(url).and_then(|x| x.proxy.as_deref()) {
+            Some(proxy) => Some(proxy),
+            None if !self.is_in_scope(url) => self.opts.external_proxy.as_deref(),
+            None => None,
+        }
+    }
+
+    /// Get the settings of the client to request an URL with, unless it is the shared one.
+    pub(crate) fn client_key(&self, url: &Url) -> Option<ClientKey> {
+        let proxy = self.proxy_for(url).map(str::to_string);
+        let policy = self.policies.find(url);
+        let identity = policy.and_then(|x| x.client_cert.clone().zip(x.client_key.clone()));
+        let insecure = policy.is_some_and(|x| x.insecure) && !self.opts.insecure;
+
+        match proxy.is_none() && identity.is_none() && !insecure {
+            true => None,
+            false => Some((proxy, identity, insecure)),
+        }
+    }
+
+    /// Get the client to send a request to the given URL with, building (once) a client for
+    /// each proxy, client certificate and certificate verification setting in use.
+    pub(crate) fn client_for(&mut self, url: &Url) -> Result<Client, Box<dyn Error>> {
+        let key = match self.client_key(url) {
+            Some(key) => key,
+            None => return Ok(self.client.clone()),
+        };
+
+        if let Some(client) = self.clients.get(&key) {
+            return Ok(client.clone());
+        }
+
+        let mut builder = client_builder(&self.opts, &self.usage)?;
+
+        match key.0.as_deref() {
+            Some("direct") => {
+                debug!("Bypassing proxies for {}", url);
+                builder = builder.no_proxy();
+            }
+            Some(proxy) => {
+                debug!("Using proxy {} for {}", proxy, url);
+                builder = builder.no_proxy().proxy(Proxy::all(proxy)?);
+            }
+            None => {}
+        }
+
+        if let Some((cert, private_key)) = &key.1 {
+            debug!("Using client certificate {} for {}", cert, url);
+            builder = builder.identity(tls::identity(cert, private_key)?);
+        }
+
+        if key.2 {
+            debug!("Accepting invalid certificates for {}", url);
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+
+        let client = builder.build()?;
+
+        self.clients.insert(key, client.clone());
+        Ok(client)
+    }
+
+    /// Remember the class of a failed request, its error being formatted afterwards.
+    pub(crate) fn set_error_class(&mut self, url: &str, class: Option<NetworkError>) {
+        if let Some(response) = self.responses.get_mut(url) {
+            response.error_class = class;
+        }
+    }
+
+    /// Count a check against --max-checks, skipping the URL once it is reached.
+    pub(crate) fn is_over_checks(&mut self, url: &str) -> bool {
+        if self.opts.max_checks.is_some_and(|max| self.checks >= max) {
+            self.skip(url, "--max-checks reached");
+            return true;
+        }
+
+        self.checks += 1;
+        false
+    }
+
+    /// Reject the assets of Open Graph and Twitter card tags not answering 200, or with an
+    /// unexpected content type (share images must be images).
+    pub(crate) fn check_social(&mut self, url: &str, status: StatusCode, headers: &HeaderMap) {
+        let property = match self.social.get(url) {
+            Some(property) => *property,
+            None => return,
+        };
+        let content_type = headers
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|x| x.to_str().ok())
+            .unwrap_or_default()
+            .to_lowercase();
+        let expected = match property {
+            "og:url" => content_type.starts_with("text/html")
+                || content_type.starts_with("application/xhtml+xml"),
+            _ => content_type.starts_with("image/"),
+        };
+        let reason = if status != StatusCode::OK {
+            format!("{} answers {}", property, status.as_u16())
+        } else if content_type.is_empty() {
+            format!("{} has no content type", property)
+        } else if !expected {
+            format!("{} is served as {}", property, content_type)
+        } else {
+            return;
+        };
+
+        warn!("Social asset - {} {}", url, reason);
+
+        if let Some(response) = self.responses.get_mut(url) {
+            response.invalid = true;
+            response.annotations.push(reason);
+        }
+    }
+
+    /// Describe why a request failed (its status or its error), if it did.
+    pub(crate) fn failure(
+        &self,
+        url: &str,
+        result: &Result<(reqwest::Response, Vec<String>), String>,
+    ) -> Option<String> {
+        match result {
+            Ok((response, redirects)) => {
+                let target = redirects.last().map_or(url, String::as_str);
+
+                match self.verdict(target, response.status().as_u16()) {
+                    Verdict::Failure => Some(response.status().to_string()),
+                    _ => None,
+                }
+            }
+            Err(e) => Some(e.clone()),
+        }
+    }
+}
+
+/// Hash an URL, remembered as visited. With 64 bits, the odds of two URLs of a crawl colliding
+/// stay below one in a million up to 6 million URLs.
+fn url_hash(url: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    url.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The size from which uncompressed text responses are reported by --require-compression.
+const MIN_COMPRESSIBLE_SIZE: u64 = 1024;
+
+/// Check if a content type is text, worth compressing.
+fn is_compressible(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+
+    mime.starts_with("text/")
+        || ["javascript", "json", "xml", "svg"].iter().any(|x| mime.contains(x))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a crawler of `http://example.com/` with these options.
+    fn crawler_with(args: &[&str]) -> Crawler {
+        let args = ["http-status-check", "http://example.com/"].iter().chain(args);
+
+        Crawler::new(Opts::parse_from(args)).unwrap()
+    }
+
+    #[test]
+    fn normalize() {
+        let mut crawler = crawler_with(&[]);
+
+        for (url, normalized) in [
+            ("HTTP://Example.COM:80/a/./b/../c", "http://example.com/a/c"),
+            ("https://example.com:443/", "https://example.com/"),
+            ("https://example.com:8443/", "https://example.com:8443/"),
+            ("http://example.com/a/?", "http://example.com/a/"),
+            ("http://example.com/?b=2&a=1", "http://example.com/?b=2&a=1"),
+            ("http://example.com/page#install", "http://example.com/page"),
+            ("not an url", "not an url"),
+        ] {
+            assert_eq!(crawler.normalize(url), normalized);
+        }
+
+        // Fragments are kept apart, to check the anchors (`#top` being always there).
+        crawler.normalize("http://example.com/page#Top");
+        crawler.normalize("http://example.com/page#caf%C3%A9");
+
+        let fragments = &crawler.fragments["http://example.com/page"];
+        let mut fragments: Vec<&str> = fragments.iter().map(String::as_str).collect();
+
+        fragments.sort_unstable();
+        assert_eq!(fragments, ["café", "install"]);
+    }
+
+    #[test]
+    fn social_assets() {
+        let mut crawler = crawler_with(&["--social"]);
+        let mut headers = HeaderMap::new();
+
+        headers.insert(reqwest::header::CONTENT_TYPE, "text/html".parse().unwrap());
+
+        for url in ["http://example.com/a.png", "http://example.com/b.png"] {
+            crawler.social.insert(url.to_string(), "og:image");
+            crawler.responses.insert(Arc::from(url), Response::new(0, 1));
+        }
+
+        crawler.check_social("http://example.com/a.png", StatusCode::NOT_FOUND, &headers);
+        crawler.check_social("http://example.com/b.png", StatusCode::OK, &headers);
+
+        for (url, reason) in [
+            ("http://example.com/a.png", "og:image answers 404"),
+            ("http://example.com/b.png", "og:image is served as text/html"),
+        ] {
+            let response = &crawler.responses[url];
+
+            assert!(response.invalid);
+            assert_eq!(response.annotations, [reason]);
+        }
+    }
+
+    #[test]
+    fn failures_only() {
+        let mut crawler = crawler_with(&["--failures-only"]);
+
+        for (url, status) in [
+            ("http://example.com/a", StatusCode::OK),
+            ("http://example.com/b", StatusCode::NOT_FOUND),
+        ] {
+            crawler.queue(url, Resource::Page, Some("http://example.com/"));
+            crawler.record(url, status, vec![]);
+            crawler.forget(url);
+        }
+
+        assert!(crawler.is_omitted("http://example.com/a"));
+        assert!(crawler.responses.contains_key("http://example.com/b"));
+        assert_eq!(crawler.omitted(), 1);
+
+        // Found again, a successful URL is not queued anew.
+        crawler.queue("http://example.com/a", Resource::Page, Some("http://example.com/b"));
+
+        assert!(crawler.is_omitted("http://example.com/a"));
+        assert_eq!(crawler.omitted(), 1);
+    }
+
+    #[test]
+    fn normalize_options() {
+        let mut crawler = crawler_with(&["--sort-query", "--strip-trailing-slash"]);
+
+        assert_eq!(crawler.normalize("http://example.com/?b=2&a=1"), "http://example.com/?a=1&b=2");
+        assert_eq!(crawler.normalize("http://example.com/a/"), "http://example.com/a");
+        assert_eq!(crawler.normalize("http://example.com/"), "http://example.com/");
+
+        let mut crawler = crawler_with(&["--strip-params", "utm_source,fbclid"]);
+        let url = "http://example.com/?utm_source=news&id=1&fbclid=x";
+
+        assert_eq!(crawler.normalize(url), "http://example.com/?id=1");
+        assert_eq!(crawler.normalize("http://example.com/?utm_source=news"), "http://example.com/");
+
+        let mut crawler = crawler_with(&["--strip-all-params"]);
+
+        assert_eq!(crawler.normalize("http://example.com/?id=1"), "http://example.com/");
+    }
+}
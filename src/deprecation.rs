@@ -0,0 +1,66 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use reqwest::{Request, Response};
+use serde::Serialize;
+
+use crate::middleware::Middleware;
+
+/// An endpoint announcing its deprecation (`Deprecation` header, RFC 9745) or its removal date
+/// (`Sunset` header, RFC 8594).
+#[derive(Clone, Debug, Serialize)]
+pub struct Deprecation {
+    pub url: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deprecation: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sunset: Option<String>,
+}
+
+/// Record the `Deprecation` and `Sunset` headers of every response.
+#[derive(Debug, Default)]
+pub struct Deprecations {
+    endpoints: Mutex<BTreeMap<String, Deprecation>>,
+}
+
+impl Deprecations {
+    /// List the deprecated endpoints, sorted by URL.
+    pub fn list(&self) -> Vec<Deprecation> {
+        self.endpoints.lock().unwrap().values().cloned().collect()
+    }
+}
+
+impl Middleware for Deprecations {
+    fn after(&self, request: &Request, response: &mut Response) {
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|x| x.to_str().ok())
+                .map(str::to_string)
+        };
+        let deprecation = header("deprecation");
+        let sunset = header("sunset");
+
+        if deprecation.is_none() && sunset.is_none() {
+            return;
+        }
+
+        warn!(
+            "{} is deprecated (sunset: {})",
+            request.url(),
+            sunset.as_deref().unwrap_or("unknown")
+        );
+
+        self.endpoints.lock().unwrap().insert(
+            request.url().to_string(),
+            Deprecation {
+                url: request.url().to_string(),
+                deprecation,
+                sunset,
+            },
+        );
+    }
+}
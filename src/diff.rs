@@ -0,0 +1,88 @@
+use std::error::Error;
+use std::fs;
+use std::process::Command;
+
+use serde::Deserialize;
+
+/// Map source files under `source` to URLs under `url`.
+///
+/// The source file extension is replaced by `extension` (dropped if not set), and index files
+/// (`index.*` and `_index.*`) are mapped to their directory URL.
+#[derive(Debug, Deserialize)]
+pub struct Rule {
+    pub source: String,
+    pub url: String,
+    pub extension: Option<String>,
+}
+
+impl Rule {
+    /// Map a source file to its URL, if the rule applies.
+    fn map(&self, file: &str) -> Option<String> {
+        let path = file.strip_prefix(&self.source)?;
+        let (dir, name) = match path.rsplit_once('/') {
+            Some((dir, name)) => (format!("{}/", dir), name),
+            None => (String::new(), path),
+        };
+
+        let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+        let url = self.url.trim_end_matches('/');
+
+        if stem == "index" || stem == "_index" {
+            return Some(format!("{}/{}", url, dir));
+        }
+
+        Some(format!(
+            "{}/{}{}{}",
+            url,
+            dir,
+            stem,
+            self.extension.as_deref().unwrap_or_default()
+        ))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct UrlMap {
+    #[serde(default)]
+    map: Vec<Rule>,
+}
+
+/// List the files changed in the given revision range (`git:<range>`).
+fn changed_files(spec: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let range = spec
+        .strip_prefix("git:")
+        .ok_or_else(|| format!("unsupported change source `{}`, expected `git:<range>`", spec))?;
+
+    let output = Command::new("git")
+        .args(["diff", "--name-only", "--diff-filter=d", range])
+        .output()?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string().into());
+    }
+
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+/// Get the URLs of the pages changed in the given revision range, using the URL map file.
+pub fn changed_urls(spec: &str, url_map: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let map: UrlMap = toml::from_str(&fs::read_to_string(url_map)?)?;
+    let mut urls = vec![];
+
+    for file in changed_files(spec)? {
+        match map.map.iter().find_map(|x| x.map(&file)) {
+            Some(url) => {
+                debug!("{} changed, mapped to {}", file, url);
+                urls.push(url);
+            }
+            None => debug!("{} changed, but is not mapped to any URL", file),
+        }
+    }
+
+    urls.sort();
+    urls.dedup();
+    Ok(urls)
+}
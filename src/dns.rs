@@ -0,0 +1,438 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use reqwest::Client;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use url::Url;
+
+/// How long to wait for a DNS answer.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The media type of DNS messages sent over HTTPS (RFC 8484).
+const DNS_MESSAGE: &str = "application/dns-message";
+
+/// A record type to look up.
+#[derive(Clone, Copy, Debug)]
+pub enum RecordType {
+    A,
+    Aaaa,
+}
+
+impl RecordType {
+    fn code(self) -> u16 {
+        match self {
+            Self::A => 1,
+            Self::Aaaa => 28,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::A => "A",
+            Self::Aaaa => "AAAA",
+        }
+    }
+}
+
+/// Where DNS queries are sent.
+#[derive(Debug)]
+pub enum Server {
+    /// A plain DNS server (UDP, TCP for truncated answers).
+    Udp(SocketAddr),
+
+    /// A DNS-over-HTTPS endpoint (RFC 8484).
+    Https(Url),
+}
+
+/// Parse a DNS server address, the port defaulting to 53.
+pub fn parse_server(value: &str) -> Result<SocketAddr, String> {
+    value
+        .parse::<SocketAddr>()
+        .or_else(|_| value.parse::<IpAddr>().map(|x| SocketAddr::new(x, 53)))
+        .map_err(|_| format!("invalid DNS server `{}`, expected an IP address", value))
+}
+
+/// Resolve hosts with a given DNS server, instead of the system resolver.
+#[derive(Debug)]
+pub struct Resolver {
+    server: Server,
+    record_types: Vec<RecordType>,
+    client: Client,
+
+    /// The outcome of every lookup, hosts being only resolved once.
+    lookups: Mutex<HashMap<String, Result<Vec<IpAddr>, String>>>,
+}
+
+impl Resolver {
+    pub fn new(server: Server, record_types: Vec<RecordType>) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            server,
+            record_types,
+            client: Client::builder().timeout(TIMEOUT).build()?,
+            lookups: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Get the addresses of a host, IPv4 ones first.
+    ///
+    /// The error tells why each record type failed (e.g. `A: NXDOMAIN, AAAA: timed out`).
+    pub async fn lookup(&self, host: &str) -> Result<Vec<IpAddr>, String> {
+        let host = host.trim_end_matches('.').to_lowercase();
+
+        if let Some(lookup) = self.lookups.lock().unwrap().get(&host) {
+            return lookup.clone();
+        }
+
+        let mut addresses = vec![];
+        let mut failures = vec![];
+
+        for record_type in &self.record_types {
+            match self.query(&host, *record_type).await {
+                Ok(found) if found.is_empty() => {
+                    failures.push(format!("{}: no record", record_type.name()))
+                }
+                Ok(found) => addresses.extend(found),
+                Err(e) => failures.push(format!("{}: {}", record_type.name(), e)),
+            }
+        }
+
+        let lookup = match addresses.is_empty() {
+            true => Err(format!("cannot resolve {} ({})", host, failures.join(", "))),
+            false => Ok(addresses),
+        };
+
+        debug!("DNS lookup of {}: {:?}", host, lookup);
+        self.lookups.lock().unwrap().insert(host, lookup.clone());
+        lookup
+    }
+
+    /// Get how many hosts have been looked up.
+    pub fn lookups(&self) -> usize {
+        self.lookups.lock().unwrap().len()
+    }
+
+    /// Send a query for a record type, and get the addresses of the answer.
+    async fn query(&self, host: &str, record_type: RecordType) -> Result<Vec<IpAddr>, String> {
+        // The ID of DoH queries should be 0, for HTTP caches. Over UDP, a random one makes
+        // spoofed answers harder to get accepted.
+        let id = match self.server {
+            Server::Udp(_) => random_id()?,
+            Server::Https(_) => 0,
+        };
+        let query = encode_query(id, host, record_type)?;
+        let answer = match &self.server {
+            Server::Udp(server) => {
+                let answer = timeout(send_udp(*server, &query)).await?;
+
+                match is_truncated(&answer) {
+                    true => {
+                        debug!("Truncated DNS answer for {}, retrying over TCP", host);
+                        timeout(send_tcp(*server, &query)).await?
+                    }
+                    false => answer,
+                }
+            }
+            Server::Https(url) => {
+                self.send_https(url, query.clone()).await.map_err(|e| e.to_string())?
+            }
+        };
+
+        decode_answer(&query, &answer)
+    }
+
+    async fn send_https(&self, url: &Url, query: Vec<u8>) -> Result<Vec<u8>, Box<dyn Error>> {
+        let response = self
+            .client
+            .post(url.clone())
+            .header(CONTENT_TYPE, DNS_MESSAGE)
+            .header(ACCEPT, DNS_MESSAGE)
+            .body(query)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(response.bytes().await?.to_vec())
+    }
+}
+
+/// Get a random query ID.
+fn random_id() -> Result<u16, String> {
+    let mut id = [0; 2];
+
+    openssl::rand::rand_bytes(&mut id).map_err(|e| e.to_string())?;
+    Ok(u16::from_be_bytes(id))
+}
+
+/// Wait for an answer, at most [`TIMEOUT`].
+async fn timeout(
+    answer: impl std::future::Future<Output = Result<Vec<u8>, Box<dyn Error>>>,
+) -> Result<Vec<u8>, String> {
+    tokio::time::timeout(TIMEOUT, answer)
+        .await
+        .map_err(|_| "timed out".to_string())?
+        .map_err(|e| e.to_string())
+}
+
+async fn send_udp(server: SocketAddr, query: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let local = match server {
+        SocketAddr::V4(_) => SocketAddr::from(([0, 0, 0, 0], 0)),
+        SocketAddr::V6(_) => SocketAddr::from(([0u16; 8], 0)),
+    };
+    let socket = UdpSocket::bind(local).await?;
+    let mut buffer = vec![0; 4096];
+
+    socket.connect(server).await?;
+    socket.send(query).await?;
+
+    let length = socket.recv(&mut buffer).await?;
+    buffer.truncate(length);
+
+    Ok(buffer)
+}
+
+/// Send a query over TCP, each message being prefixed with its length (RFC 1035 §4.2.2).
+async fn send_tcp(server: SocketAddr, query: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut stream = TcpStream::connect(server).await?;
+    let mut message = (query.len() as u16).to_be_bytes().to_vec();
+
+    message.extend_from_slice(query);
+    stream.write_all(&message).await?;
+
+    let mut length = [0; 2];
+
+    stream.read_exact(&mut length).await?;
+
+    let mut buffer = vec![0; u16::from_be_bytes(length) as usize];
+
+    stream.read_exact(&mut buffer).await?;
+    Ok(buffer)
+}
+
+/// Check if an answer has the TC (truncated) flag set, telling to retry over TCP.
+fn is_truncated(message: &[u8]) -> bool {
+    message.len() > 2 && message[2] & 0x02 != 0
+}
+
+/// Build a recursive query message for a host.
+fn encode_query(id: u16, host: &str, record_type: RecordType) -> Result<Vec<u8>, String> {
+    let mut message = vec![];
+
+    message.extend_from_slice(&id.to_be_bytes());
+    // Flags (recursion desired), one question, no answer, authority or additional record.
+    message.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+
+    for label in host.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(format!("invalid host name `{}`", host));
+        }
+
+        message.push(label.len() as u8);
+        message.extend_from_slice(label.as_bytes());
+    }
+
+    message.push(0);
+    message.extend_from_slice(&record_type.code().to_be_bytes());
+    message.extend_from_slice(&1u16.to_be_bytes());
+
+    Ok(message)
+}
+
+/// Skip a (possibly compressed) name, and get the position following it.
+fn skip_name(message: &[u8], mut position: usize) -> Option<usize> {
+    loop {
+        let length = *message.get(position)? as usize;
+
+        match length {
+            0 => return Some(position + 1),
+            x if x & 0xC0 == 0xC0 => return Some(position + 2),
+            x => position += x + 1,
+        }
+    }
+}
+
+/// Read the addresses of the answer to a query. CNAME chains are already followed by the
+/// server, so every A and AAAA record of the answer section is kept.
+///
+/// Answers whose ID or question doesn't match the query are rejected.
+fn decode_answer(query: &[u8], message: &[u8]) -> Result<Vec<IpAddr>, String> {
+    let invalid = || "invalid answer".to_string();
+    let read_u16 = |position: usize| -> Option<u16> {
+        Some(u16::from_be_bytes([*message.get(position)?, *message.get(position + 1)?]))
+    };
+
+    if message.len() < 12 || message[..2] != query[..2] || message[2] & 0x80 == 0 {
+        return Err(invalid());
+    }
+
+    match message[3] & 0x0F {
+        0 => {}
+        2 => return Err("SERVFAIL".to_string()),
+        3 => return Err("NXDOMAIN".to_string()),
+        5 => return Err("REFUSED".to_string()),
+        code => return Err(format!("RCODE {}", code)),
+    }
+
+    // The question is echoed (names compared case-insensitively).
+    let question = &query[12..];
+    let echoed = message.get(12..12 + question.len());
+
+    if read_u16(4) != Some(1) || !echoed.is_some_and(|x| x.eq_ignore_ascii_case(question)) {
+        return Err("answer to another question".to_string());
+    }
+
+    let answers = read_u16(6).ok_or_else(invalid)?;
+    let mut position = 12 + question.len();
+
+    let mut addresses = vec![];
+
+    for _ in 0..answers {
+        position = skip_name(message, position).ok_or_else(invalid)?;
+
+        let record_type = read_u16(position).ok_or_else(invalid)?;
+        let length = read_u16(position + 8).ok_or_else(invalid)? as usize;
+        let data = message
+            .get(position + 10..position + 10 + length)
+            .ok_or_else(invalid)?;
+
+        match (record_type, data.len()) {
+            (1, 4) => addresses.push(IpAddr::from(Ipv4Addr::new(
+                data[0], data[1], data[2], data[3],
+            ))),
+            (28, 16) => {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(data);
+                addresses.push(IpAddr::from(Ipv6Addr::from(octets)));
+            }
+            _ => {}
+        }
+
+        position += 10 + length;
+    }
+
+    Ok(addresses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build the answer to a query, with the given flags (second byte) and records (type, data).
+    fn answer(query: &[u8], flags: u8, records: &[(u16, &[u8])]) -> Vec<u8> {
+        let mut message = query.to_vec();
+
+        message[2] |= 0x80;
+        message[3] = flags;
+        message[7] = records.len() as u8;
+
+        for (record_type, data) in records {
+            // A pointer to the name of the question, then the type, class, TTL and data.
+            message.extend_from_slice(&[0xC0, 12]);
+            message.extend_from_slice(&record_type.to_be_bytes());
+            message.extend_from_slice(&[0, 1, 0, 0, 0, 60]);
+            message.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            message.extend_from_slice(data);
+        }
+
+        message
+    }
+
+    #[test]
+    fn encode() {
+        let query = encode_query(0x1234, "www.example.com", RecordType::Aaaa).unwrap();
+
+        assert_eq!(&query[..12], &[0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&query[12..], b"\x03www\x07example\x03com\x00\x00\x1c\x00\x01");
+        assert!(encode_query(0, "a..b", RecordType::A).is_err());
+        assert!(encode_query(0, &"a".repeat(64), RecordType::A).is_err());
+    }
+
+    #[test]
+    fn decode() {
+        let query = encode_query(7, "example.com", RecordType::A).unwrap();
+        let cname: &[u8] = b"\x03foo\xC0\x0C";
+        let message = answer(&query, 0, &[(5, cname), (1, &[1, 2, 3, 4]), (1, &[5, 6, 7, 8])]);
+
+        assert_eq!(
+            decode_answer(&query, &message),
+            Ok(vec![IpAddr::from([1, 2, 3, 4]), IpAddr::from([5, 6, 7, 8])])
+        );
+
+        let query = encode_query(7, "example.com", RecordType::Aaaa).unwrap();
+        let message = answer(&query, 0, &[(28, &Ipv6Addr::LOCALHOST.octets())]);
+
+        assert_eq!(decode_answer(&query, &message), Ok(vec![IpAddr::from(Ipv6Addr::LOCALHOST)]));
+    }
+
+    #[test]
+    fn decode_errors() {
+        let query = encode_query(7, "example.com", RecordType::A).unwrap();
+
+        assert_eq!(decode_answer(&query, &answer(&query, 3, &[])), Err("NXDOMAIN".to_string()));
+        assert_eq!(decode_answer(&query, &answer(&query, 2, &[])), Err("SERVFAIL".to_string()));
+        assert_eq!(decode_answer(&query, &query), Err("invalid answer".to_string()));
+        assert_eq!(decode_answer(&query, &[0; 4]), Err("invalid answer".to_string()));
+
+        let mut message = answer(&query, 0, &[(1, &[1, 2, 3, 4])]);
+
+        message.truncate(message.len() - 2);
+        assert_eq!(decode_answer(&query, &message), Err("invalid answer".to_string()));
+    }
+
+    #[test]
+    fn decode_rejects_other_queries() {
+        let query = encode_query(7, "example.com", RecordType::A).unwrap();
+        let other_id = encode_query(8, "example.com", RecordType::A).unwrap();
+        let other_host = encode_query(7, "example.org", RecordType::A).unwrap();
+        let other_type = encode_query(7, "example.com", RecordType::Aaaa).unwrap();
+
+        for other in [other_id, other_host, other_type] {
+            assert!(decode_answer(&query, &answer(&other, 0, &[(1, &[1, 2, 3, 4])])).is_err());
+        }
+
+        let upper = encode_query(7, "EXAMPLE.com", RecordType::A).unwrap();
+
+        assert!(decode_answer(&query, &answer(&upper, 0, &[(1, &[1, 2, 3, 4])])).is_ok());
+    }
+
+    #[tokio::test]
+    async fn truncated_answers_are_retried_over_tcp() {
+        use std::io::{Read, Write};
+
+        let udp = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server = udp.local_addr().unwrap();
+        let tcp = std::net::TcpListener::bind(server).unwrap();
+
+        std::thread::spawn(move || {
+            let mut buffer = [0; 512];
+            let (length, client) = udp.recv_from(&mut buffer).unwrap();
+            let mut message = answer(&buffer[..length], 0, &[]);
+
+            message[2] |= 0x02;
+            udp.send_to(&message, client).unwrap();
+        });
+        std::thread::spawn(move || {
+            let (mut stream, _) = tcp.accept().unwrap();
+            let mut length = [0; 2];
+
+            stream.read_exact(&mut length).unwrap();
+
+            let mut query = vec![0; u16::from_be_bytes(length) as usize];
+
+            stream.read_exact(&mut query).unwrap();
+
+            let message = answer(&query, 0, &[(1, &[1, 2, 3, 4])]);
+
+            stream.write_all(&(message.len() as u16).to_be_bytes()).unwrap();
+            stream.write_all(&message).unwrap();
+        });
+
+        let resolver = Resolver::new(Server::Udp(server), vec![RecordType::A]).unwrap();
+
+        assert_eq!(resolver.lookup("example.com").await, Ok(vec![IpAddr::from([1, 2, 3, 4])]));
+    }
+}
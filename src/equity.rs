@@ -0,0 +1,44 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// An internal link, from a crawled page to another page of the site.
+#[derive(Debug)]
+pub struct Edge {
+    pub source: String,
+    pub target: String,
+    pub text: String,
+    pub rel: String,
+    pub nofollow: bool,
+}
+
+/// Quote a CSV field when needed (RFC 4180).
+pub fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Write the link edges as CSV into the given file, for SEO tools to import.
+pub fn write_csv(edges: &[Edge], path: &str) -> Result<(), Box<dyn Error>> {
+    let mut file = BufWriter::new(File::create(path)?);
+
+    writeln!(file, "source,target,anchor_text,rel,nofollow")?;
+
+    for edge in edges {
+        writeln!(
+            file,
+            "{},{},{},{},{}",
+            field(&edge.source),
+            field(&edge.target),
+            field(&edge.text),
+            field(&edge.rel),
+            edge.nofollow
+        )?;
+    }
+
+    file.flush()?;
+    Ok(())
+}
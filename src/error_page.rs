@@ -0,0 +1,70 @@
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::hash::{BuildHasher, Hash, Hasher};
+
+use serde::Serialize;
+use url::Url;
+
+/// The fingerprint of the site's error page, as served for a path that cannot exist.
+#[derive(Debug, Serialize)]
+pub struct ErrorPage {
+    pub url: String,
+    pub status: u16,
+    pub length: usize,
+
+    /// Hash of the body, with the probed path removed (error pages often echo it).
+    pub hash: String,
+
+    /// Issues of the error page itself (soft 404, server error, empty body...).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub problems: Vec<String>,
+}
+
+/// Build a random URL on the given site, deliberately missing.
+pub fn probe_url(base: &Url) -> Url {
+    let token = RandomState::new().build_hasher().finish();
+    let mut url = base.clone();
+
+    url.set_path(&format!("/http-status-check-{:016x}", token));
+    url.set_query(None);
+    url
+}
+
+/// Hash an error page body, ignoring the probed path.
+pub fn hash(body: &str, url: &Url) -> String {
+    let mut hasher = DefaultHasher::new();
+
+    body.replace(url.path(), "").hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+impl ErrorPage {
+    /// Fingerprint the response of a probed URL, and check it looks like a proper error page.
+    pub fn new(url: &Url, status: u16, redirects: &[String], body: &str) -> Self {
+        let mut problems = vec![];
+
+        if let Some(target) = redirects.last() {
+            problems.push(format!("missing pages are redirected to {}", target));
+        }
+
+        match status {
+            200..=299 => problems.push(format!(
+                "missing pages are served with {} (soft 404)",
+                status
+            )),
+            500..=599 => problems.push(format!("the error page fails with {}", status)),
+            _ => {}
+        }
+
+        if body.trim().is_empty() {
+            problems.push("the error page is empty".to_string());
+        }
+
+        Self {
+            url: url.to_string(),
+            status,
+            length: body.len(),
+            hash: hash(body, url),
+            problems,
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use serde::Serialize;
+
+use crate::report::Verdict;
+
+/// The progress of a crawl, streamed by [`Crawler::events`](crate::Crawler::events).
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum CrawlEvent {
+    /// An URL has been found, and will be requested.
+    Queued {
+        url: String,
+        referrer: Option<String>,
+    },
+
+    /// An URL has been requested, successfully or with a warning.
+    Fetched {
+        url: String,
+        status: u16,
+        verdict: Verdict,
+
+        /// How long the response took, the body included.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        time_ms: Option<u64>,
+    },
+
+    /// An URL failed: an error status, or no response at all (`error` tells why).
+    Failed {
+        url: String,
+        status: u16,
+        error: Option<String>,
+
+        #[serde(skip_serializing_if = "Option::is_none")]
+        time_ms: Option<u64>,
+    },
+
+    /// The crawl is finished, its results are ready. This is the last event.
+    Finished,
+}
+
+/// Send the crawl events to every subscriber.
+#[derive(Debug)]
+pub struct Events {
+    subscribers: Vec<UnboundedSender<CrawlEvent>>,
+
+    /// The events sent before the crawl is started (the entrypoints being queued), replayed to
+    /// the subscribers.
+    backlog: Option<Vec<CrawlEvent>>,
+}
+
+impl Default for Events {
+    fn default() -> Self {
+        Self {
+            subscribers: vec![],
+            backlog: Some(vec![]),
+        }
+    }
+}
+
+impl Events {
+    pub fn subscribe(&mut self) -> UnboundedReceiver<CrawlEvent> {
+        let (sender, receiver) = mpsc::unbounded();
+
+        for event in self.backlog.iter().flatten() {
+            let _ = sender.unbounded_send(event.clone());
+        }
+
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// Stop keeping the events for later subscribers, the crawl being started.
+    pub fn start(&mut self) {
+        self.backlog = None;
+    }
+
+    /// Send an event, forgetting the subscribers which went away.
+    pub fn emit(&mut self, event: CrawlEvent) {
+        if let Some(backlog) = &mut self.backlog {
+            backlog.push(event.clone());
+        }
+
+        self.subscribers.retain(|x| x.unbounded_send(event.clone()).is_ok());
+    }
+
+    /// Send the last event, and end the streams.
+    pub fn finish(&mut self) {
+        self.emit(CrawlEvent::Finished);
+        self.subscribers.clear();
+    }
+}
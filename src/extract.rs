@@ -0,0 +1,686 @@
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+use percent_encoding::percent_decode_str;
+use regex::Regex;
+use select::document::Document;
+use select::node::Node;
+use select::predicate::{Attr, Name};
+use serde::{Deserialize, Serialize};
+
+use crate::pdf;
+
+/// The kind of resource a link points to, guessed from the tag it has been found in.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Resource {
+    Page,
+    Image,
+    Script,
+    Stylesheet,
+    Media,
+    Font,
+    Embed,
+    Manifest,
+    Link,
+}
+
+/// The format of a document links can be extracted from.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    Html,
+    Css,
+
+    /// A web app manifest (`<link rel="manifest">`), its icons being checked.
+    Manifest,
+
+    /// An RSS or Atom feed.
+    Feed,
+
+    /// Another XML document: a sitemap (or sitemap index), or a feed served as XML.
+    Xml,
+
+    /// A PDF document (with --parse-pdf), its URI annotations being checked.
+    Pdf,
+}
+
+impl Format {
+    /// Extract every link of a document in this format.
+    pub fn links(self, body: &str) -> Vec<Link> {
+        match self {
+            Format::Html => links(&Document::from(body)),
+            Format::Css => css_links(body),
+            Format::Manifest => manifest_links(body),
+            Format::Feed => feed_links(body),
+            Format::Xml => xml_links(body),
+            Format::Pdf => pdf::links(body),
+        }
+    }
+}
+
+/// A link extracted from a document.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Link {
+    pub href: String,
+    pub resource: Resource,
+
+    /// Whether the link is marked `rel="nofollow"`, or found on a `nofollow` page.
+    pub nofollow: bool,
+
+    /// The text of the tag, whitespace collapsed (the anchor text of `<a>` tags).
+    pub text: String,
+
+    /// The `rel` attribute of the tag, as written.
+    pub rel: String,
+}
+
+/// Guess the resource of a `<link>` tag from its `rel` attribute.
+fn link_resource(node: &Node) -> Resource {
+    let rel = node.attr("rel").unwrap_or_default().to_lowercase();
+
+    if rel.split_whitespace().any(|x| x == "stylesheet") {
+        Resource::Stylesheet
+    } else if rel.split_whitespace().any(|x| x == "icon" || x == "apple-touch-icon") {
+        Resource::Image
+    } else if rel.split_whitespace().any(|x| x == "manifest") {
+        Resource::Manifest
+    } else if rel.split_whitespace().any(|x| x == "canonical")
+        // Alternate versions of the page, in other languages.
+        || rel.split_whitespace().any(|x| x == "alternate") && node.attr("hreflang").is_some()
+    {
+        Resource::Page
+    } else {
+        Resource::Link
+    }
+}
+
+/// Guess the resource of a `<source>` tag from its parent (`<picture>`, `<video>` or `<audio>`).
+fn source_resource(node: &Node) -> Resource {
+    match node.parent().and_then(|x| x.name()) {
+        Some("picture") => Resource::Image,
+        _ => Resource::Media,
+    }
+}
+
+/// Guess the resource of a link from the tag it has been found in.
+type Guess = fn(&Node) -> Resource;
+
+/// Tags (and their attribute) containing links.
+const TAGS: &[(&str, &str, Guess)] = &[
+    ("a", "href", |_| Resource::Page),
+    ("img", "src", |_| Resource::Image),
+    ("script", "src", |_| Resource::Script),
+    ("link", "href", link_resource),
+    ("source", "src", source_resource),
+    ("video", "src", |_| Resource::Media),
+    ("audio", "src", |_| Resource::Media),
+    ("iframe", "src", |_| Resource::Embed),
+    ("embed", "src", |_| Resource::Embed),
+    ("object", "data", |_| Resource::Embed),
+];
+
+/// Check if a tag is marked `rel="nofollow"`.
+fn is_nofollow(node: &Node) -> bool {
+    node.attr("rel")
+        .unwrap_or_default()
+        .split_whitespace()
+        .any(|x| x.eq_ignore_ascii_case("nofollow"))
+}
+
+/// Get the directives of the `<meta name="robots">` tags of a document, lowercased (`none`
+/// is expanded to `noindex` and `nofollow`).
+pub fn robots(document: &Document) -> Vec<String> {
+    let mut directives = vec![];
+
+    for node in document.find(Name("meta")) {
+        if !node.attr("name").is_some_and(|x| x.eq_ignore_ascii_case("robots")) {
+            continue;
+        }
+
+        for directive in node.attr("content").unwrap_or_default().split(',') {
+            match directive.trim().to_lowercase().as_str() {
+                "none" => directives.extend(["noindex".to_string(), "nofollow".to_string()]),
+                directive => directives.push(directive.to_string()),
+            }
+        }
+    }
+
+    directives
+}
+
+/// Extract every link (anchors and assets) from an HTML document.
+pub fn links(document: &Document) -> Vec<Link> {
+    let mut links = vec![];
+    let nofollow = robots(document).iter().any(|x| x == "nofollow");
+
+    for (tag, attr, resource) in TAGS {
+        for node in document.find(Name(*tag)) {
+            if let Some(href) = node.attr(attr) {
+                links.push(Link {
+                    href: href.to_string(),
+                    resource: resource(&node),
+                    nofollow: nofollow || is_nofollow(&node),
+                    text: node.text().split_whitespace().collect::<Vec<_>>().join(" "),
+                    rel: node.attr("rel").unwrap_or_default().to_string(),
+                });
+            }
+        }
+    }
+
+    for tag in &["img", "source"] {
+        for node in document.find(Name(*tag)) {
+            let resource = if *tag == "img" {
+                Resource::Image
+            } else {
+                source_resource(&node)
+            };
+
+            for attr in LAZY_ATTRIBUTES {
+                if let Some(href) = node.attr(attr) {
+                    links.push(Link {
+                        href: href.to_string(),
+                        resource,
+                        nofollow,
+                        text: String::new(),
+                        rel: String::new(),
+                    });
+                }
+            }
+
+            for attr in SRCSET_ATTRIBUTES {
+                if let Some(srcset) = node.attr(attr) {
+                    links.extend(parse_srcset(srcset).into_iter().map(|href| Link {
+                        href,
+                        resource,
+                        nofollow,
+                        text: String::new(),
+                        rel: String::new(),
+                    }));
+                }
+            }
+        }
+    }
+
+    links
+}
+
+/// Collect every fragment target of an HTML document: element IDs and named anchors
+/// (`<a name="...">`).
+pub fn anchors(document: &Document) -> HashSet<String> {
+    let ids = document.find(Attr("id", ())).filter_map(|x| x.attr("id"));
+    let names = document.find(Name("a")).filter_map(|x| x.attr("name"));
+
+    ids.chain(names).map(str::to_string).collect()
+}
+
+/// Get the canonical URL of an HTML document (`<link rel="canonical">`), as written.
+pub fn canonical(document: &Document) -> Option<String> {
+    document
+        .find(Name("link"))
+        .find(|x| {
+            x.attr("rel")
+                .unwrap_or_default()
+                .split_whitespace()
+                .any(|x| x.eq_ignore_ascii_case("canonical"))
+        })
+        .and_then(|x| x.attr("href"))
+        .map(str::to_string)
+}
+
+/// An alternate version of a page, in another language (`<link rel="alternate" hreflang>`).
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Alternate {
+    pub hreflang: String,
+    pub href: String,
+}
+
+/// Get the alternate versions of an HTML document, as written.
+pub fn alternates(document: &Document) -> Vec<Alternate> {
+    document
+        .find(Name("link"))
+        .filter(|x| {
+            x.attr("rel")
+                .unwrap_or_default()
+                .split_whitespace()
+                .any(|x| x.eq_ignore_ascii_case("alternate"))
+        })
+        .filter_map(|x| {
+            Some(Alternate {
+                hreflang: x.attr("hreflang")?.to_string(),
+                href: x.attr("href")?.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The Open Graph and Twitter card properties pointing to an asset, and its resource.
+const SOCIAL_PROPERTIES: &[(&str, Resource)] = &[
+    ("og:image", Resource::Image),
+    ("og:url", Resource::Page),
+    ("twitter:image", Resource::Image),
+];
+
+/// Extract the assets of the Open Graph and Twitter card `<meta>` tags of an HTML document,
+/// with their property (e.g. `og:image`).
+pub fn social(document: &Document) -> Vec<(&'static str, Link)> {
+    let mut links = vec![];
+
+    for node in document.find(Name("meta")) {
+        let property = node.attr("property").or_else(|| node.attr("name"));
+        let social = SOCIAL_PROPERTIES
+            .iter()
+            .find(|(name, _)| property.is_some_and(|x| x.eq_ignore_ascii_case(name)));
+
+        if let (Some((property, resource)), Some(href)) = (social, node.attr("content")) {
+            links.push((
+                *property,
+                Link {
+                    href: href.trim().to_string(),
+                    resource: *resource,
+                    nofollow: false,
+                    text: String::new(),
+                    rel: String::new(),
+                },
+            ));
+        }
+    }
+
+    links
+}
+
+/// Attributes used by lazy-loading libraries to hold the image URL.
+const LAZY_ATTRIBUTES: &[&str] = &["data-src", "data-lazy-src"];
+
+/// Attributes holding responsive image candidates.
+const SRCSET_ATTRIBUTES: &[&str] = &["srcset", "data-srcset", "data-lazy-srcset"];
+
+/// Extract URLs from a `srcset` attribute (e.g. `a.png 1x, b.png 2x`).
+///
+/// This follows the HTML parsing rules, so URLs containing commas (`w_100,h_100/a.png 100w`)
+/// are kept whole: a comma only separates candidates when it ends an URL or follows descriptors.
+pub fn parse_srcset(srcset: &str) -> Vec<String> {
+    let mut urls = vec![];
+    let mut chars = srcset.chars().peekable();
+
+    loop {
+        while chars.peek().is_some_and(|x| x.is_whitespace() || *x == ',') {
+            chars.next();
+        }
+
+        let mut url = String::new();
+
+        while let Some(x) = chars.peek().filter(|x| !x.is_whitespace()) {
+            url.push(*x);
+            chars.next();
+        }
+
+        if url.is_empty() {
+            return urls;
+        }
+
+        if url.ends_with(',') {
+            urls.push(url.trim_end_matches(',').to_string());
+            continue;
+        }
+
+        urls.push(url);
+
+        // Skip descriptors (`2x`, `100w`), up to the next comma outside of parentheses.
+        let mut depth = 0;
+
+        for x in chars.by_ref() {
+            match x {
+                '(' => depth += 1,
+                ')' if depth > 0 => depth -= 1,
+                ',' if depth == 0 => break,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Extract the icons listed by a web app manifest (`icons[].src`), if it is valid JSON.
+pub fn manifest_links(body: &str) -> Vec<Link> {
+    let manifest: serde_json::Value = match serde_json::from_str(body) {
+        Ok(manifest) => manifest,
+        Err(_) => return vec![],
+    };
+
+    manifest["icons"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|x| x["src"].as_str())
+        .map(|href| Link {
+            href: href.to_string(),
+            resource: Resource::Image,
+            nofollow: false,
+            text: String::new(),
+            rel: String::new(),
+        })
+        .collect()
+}
+
+/// Guess the resource of an URL referenced by a stylesheet, from its extension.
+fn css_resource(url: &str) -> Resource {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let extension = path.rsplit_once('.').map(|(_, x)| x.to_lowercase());
+
+    match extension.as_deref() {
+        Some("woff" | "woff2" | "ttf" | "otf" | "eot") => Resource::Font,
+        Some("png" | "jpg" | "jpeg" | "gif" | "svg" | "webp" | "avif" | "ico" | "bmp") => {
+            Resource::Image
+        }
+        Some("css") => Resource::Stylesheet,
+        _ => Resource::Link,
+    }
+}
+
+/// Get the scheme of a link which is not a web page (`mailto:`, `tel:`, `javascript:`,
+/// `data:`...), lowercased.
+pub fn other_scheme(href: &str) -> Option<String> {
+    let (scheme, _) = href.trim_start().split_once(':')?;
+    let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+    let scheme = scheme.to_lowercase();
+
+    (valid && scheme != "http" && scheme != "https").then_some(scheme)
+}
+
+/// Tell why the addresses of a `mailto:` link are invalid, if they are.
+pub fn mailto_error(href: &str) -> Option<String> {
+    static ADDRESS: OnceLock<Regex> = OnceLock::new();
+
+    let address = ADDRESS.get_or_init(|| Regex::new(r"^[^@\s]+@[^@\s]+\.[^@\s.]+$").unwrap());
+    let (_, addresses) = href.split_once(':')?;
+    let (to, query) = addresses.split_once('?').unwrap_or((addresses, ""));
+    let to = percent_decode_str(to).decode_utf8_lossy();
+
+    // The addresses can be given as a `to` header only.
+    if to.trim().is_empty() {
+        return match query.split('&').any(|x| x.to_lowercase().starts_with("to=")) {
+            true => None,
+            false => Some("no address".to_string()),
+        };
+    }
+
+    to.split(',')
+        .map(str::trim)
+        .find(|x| !address.is_match(x))
+        .map(|x| format!("invalid address `{}`", x))
+}
+
+/// Extract every `url(...)` and `@import` reference from a stylesheet.
+///
+/// Inline `data:` URIs and SVG fragment references (`url(#filter)`) are skipped.
+pub fn css_links(body: &str) -> Vec<Link> {
+    static URL: OnceLock<Regex> = OnceLock::new();
+    static IMPORT: OnceLock<Regex> = OnceLock::new();
+
+    let url = URL.get_or_init(|| {
+        Regex::new(r#"(?i)(@import\s+)?url\(\s*(?:"([^"]*)"|'([^']*)'|([^)\s]*))\s*\)"#).unwrap()
+    });
+    let import =
+        IMPORT.get_or_init(|| Regex::new(r#"(?i)@import\s+(?:"([^"]*)"|'([^']*)')"#).unwrap());
+
+    let mut links = vec![];
+
+    for captures in url.captures_iter(body) {
+        let href = captures
+            .get(2)
+            .or_else(|| captures.get(3))
+            .or_else(|| captures.get(4))
+            .map_or("", |x| x.as_str().trim());
+
+        let resource = if captures.get(1).is_some() {
+            Resource::Stylesheet
+        } else {
+            css_resource(href)
+        };
+
+        links.push(Link {
+            href: href.to_string(),
+            resource,
+            nofollow: false,
+            text: String::new(),
+            rel: String::new(),
+        });
+    }
+
+    for captures in import.captures_iter(body) {
+        let href = captures.get(1).or_else(|| captures.get(2)).map_or("", |x| x.as_str());
+
+        links.push(Link {
+            href: href.trim().to_string(),
+            resource: Resource::Stylesheet,
+            nofollow: false,
+            text: String::new(),
+            rel: String::new(),
+        });
+    }
+
+    links.retain(|x| {
+        !x.href.is_empty() && !x.href.starts_with('#') && !x.href.to_lowercase().starts_with("data:")
+    });
+    links
+}
+
+/// Decode the predefined XML entities of a value.
+fn xml_unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Get the value of an attribute in the attributes of an XML tag.
+fn xml_attribute(attributes: &str, name: &str) -> Option<String> {
+    let pattern = format!(r#"(?i)\b{}\s*=\s*(?:"([^"]*)"|'([^']*)')"#, name);
+    let captures = Regex::new(&pattern).ok()?.captures(attributes)?;
+
+    captures.get(1).or_else(|| captures.get(2)).map(|x| xml_unescape(x.as_str().trim()))
+}
+
+/// Extract the links of an RSS or Atom feed: the channel and item links (RSS `<link>`, Atom
+/// `<link href>`) and the enclosures (podcasts, images...).
+pub fn feed_links(body: &str) -> Vec<Link> {
+    static RSS_LINK: OnceLock<Regex> = OnceLock::new();
+    static TAG: OnceLock<Regex> = OnceLock::new();
+
+    let rss_link = RSS_LINK.get_or_init(|| {
+        Regex::new(r"(?is)<link>\s*(?:<!\[CDATA\[)?\s*([^<\]\s]+)\s*(?:\]\]>)?\s*</link>").unwrap()
+    });
+    let tag = TAG.get_or_init(|| Regex::new(r"(?is)<(link|enclosure)\b([^>]*)>").unwrap());
+    let link = |href: String, resource| Link {
+        href,
+        resource,
+        nofollow: false,
+        text: String::new(),
+        rel: String::new(),
+    };
+    // Enclosures are media, unless their extension tells otherwise.
+    let enclosure = |href: String| match css_resource(&href) {
+        Resource::Link => link(href, Resource::Media),
+        resource => link(href, resource),
+    };
+
+    let mut links: Vec<Link> = rss_link
+        .captures_iter(body)
+        .map(|x| link(xml_unescape(&x[1]), Resource::Page))
+        .collect();
+
+    for captures in tag.captures_iter(body) {
+        let attributes = &captures[2];
+
+        if captures[1].eq_ignore_ascii_case("enclosure") {
+            links.extend(xml_attribute(attributes, "url").map(enclosure));
+            continue;
+        }
+
+        let href = match xml_attribute(attributes, "href") {
+            Some(href) => href,
+            None => continue,
+        };
+
+        match xml_attribute(attributes, "rel").unwrap_or_default().as_str() {
+            // The feed itself.
+            "self" => {}
+            "enclosure" => links.push(enclosure(href)),
+            _ => links.push(link(href, Resource::Page)),
+        }
+    }
+
+    links.retain(|x| !x.href.is_empty());
+    links
+}
+
+/// Extract the links of an XML document: the `<loc>` URLs of a sitemap (pages, or the sitemaps
+/// of an index), then its feed links.
+pub fn xml_links(body: &str) -> Vec<Link> {
+    static LOC: OnceLock<Regex> = OnceLock::new();
+
+    let loc = LOC.get_or_init(|| {
+        Regex::new(r"(?is)<loc>\s*(?:<!\[CDATA\[)?\s*([^<\]\s]+)\s*(?:\]\]>)?\s*</loc>").unwrap()
+    });
+    let mut links: Vec<Link> = loc
+        .captures_iter(body)
+        .map(|x| Link {
+            href: xml_unescape(&x[1]),
+            resource: Resource::Page,
+            nofollow: false,
+            text: String::new(),
+            rel: String::new(),
+        })
+        .collect();
+
+    links.extend(feed_links(body));
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hrefs(links: &[Link]) -> Vec<(&str, Resource)> {
+        links.iter().map(|x| (x.href.as_str(), x.resource)).collect()
+    }
+
+    #[test]
+    fn srcset() {
+        let cases: &[(&str, &[&str])] = &[
+            ("a.png", &["a.png"]),
+            ("a.png 1x, b.png 2x", &["a.png", "b.png"]),
+            ("a.png 100w,b.png 200w", &["a.png", "b.png"]),
+            ("a.png, b.png 2x", &["a.png", "b.png"]),
+            // Commas within an URL are kept, only trailing ones separate candidates.
+            ("a.png,b.png 2x", &["a.png,b.png"]),
+            (" a.png  1.5x ,  b.png ", &["a.png", "b.png"]),
+            ("/w_100,h_100/a.png 100w, /w_200,h_200/a.png 200w", &[
+                "/w_100,h_100/a.png",
+                "/w_200,h_200/a.png",
+            ]),
+            ("a.png calc(1px, 2px), b.png 2x", &["a.png", "b.png"]),
+            ("", &[]),
+            (", ,", &[]),
+        ];
+
+        for (srcset, urls) in cases {
+            assert_eq!(parse_srcset(srcset), *urls, "{}", srcset);
+        }
+    }
+
+    #[test]
+    fn css() {
+        let body = r#"
+            @import url("reset.css");
+            @import url(print.css) print;
+            @IMPORT 'theme.css' screen;
+            @import "fonts.css";
+            body { background: url( bg.png ) }
+            @font-face { src: url('/fonts/a.woff2?v=1') format("woff2"), url("a.ttf") }
+            .icon { background: url(data:image/png;base64,AAAA) }
+            .mask { mask: url(#mask) }
+            .empty { background: url("") }
+            .other { cursor: url(/cursor) }
+        "#;
+
+        assert_eq!(
+            hrefs(&css_links(body)),
+            [
+                ("reset.css", Resource::Stylesheet),
+                ("print.css", Resource::Stylesheet),
+                ("bg.png", Resource::Image),
+                ("/fonts/a.woff2?v=1", Resource::Font),
+                ("a.ttf", Resource::Font),
+                ("/cursor", Resource::Link),
+                ("theme.css", Resource::Stylesheet),
+                ("fonts.css", Resource::Stylesheet),
+            ]
+        );
+    }
+
+    #[test]
+    fn feed() {
+        let rss = r#"<rss><channel>
+            <link>https://example.com/</link>
+            <atom:link href="https://example.com/feed.xml" rel="self"/>
+            <item>
+                <link><![CDATA[ /posts/first?a=1&amp;b=2 ]]></link>
+                <enclosure url="episodes/1.mp3" type="audio/mpeg"/>
+                <enclosure url='/covers/1.jpg'/>
+            </item>
+        </channel></rss>"#;
+
+        assert_eq!(
+            hrefs(&feed_links(rss)),
+            [
+                ("https://example.com/", Resource::Page),
+                ("/posts/first?a=1&b=2", Resource::Page),
+                ("episodes/1.mp3", Resource::Media),
+                ("/covers/1.jpg", Resource::Image),
+            ]
+        );
+
+        let atom = r#"<feed>
+            <link href="/" />
+            <link rel="self" href="/atom.xml"/>
+            <entry>
+                <link href="posts/second" rel="alternate"/>
+                <link rel="enclosure" href="../media/2.ogg"/>
+                <link href=""/>
+            </entry>
+        </feed>"#;
+
+        assert_eq!(
+            hrefs(&feed_links(atom)),
+            [
+                ("/", Resource::Page),
+                ("posts/second", Resource::Page),
+                ("../media/2.ogg", Resource::Media),
+            ]
+        );
+    }
+
+    #[test]
+    fn xml() {
+        let sitemap = r#"<urlset>
+            <url><loc>https://example.com/a</loc></url>
+            <url><loc>
+                /relative/b?x=1&amp;y=2
+            </loc></url>
+            <url><loc><![CDATA[c.html]]></loc></url>
+        </urlset>"#;
+
+        assert_eq!(
+            hrefs(&xml_links(sitemap)),
+            [
+                ("https://example.com/a", Resource::Page),
+                ("/relative/b?x=1&y=2", Resource::Page),
+                ("c.html", Resource::Page),
+            ]
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use std::error::Error;
+use std::fs;
+
+use serde::de::value::{Error as ValueError, StrDeserializer};
+use serde::Deserialize;
+
+use crate::extract::Format;
+
+/// The built-in extractors, used after the configured ones: the documents of any other content
+/// type only get their status checked.
+const DEFAULTS: &[(&str, Option<Format>)] = &[
+    ("text/html", Some(Format::Html)),
+    ("application/xhtml+xml", Some(Format::Html)),
+    ("text/css", Some(Format::Css)),
+    ("application/rss+xml", Some(Format::Feed)),
+    ("application/atom+xml", Some(Format::Feed)),
+    ("application/xml", Some(Format::Xml)),
+    ("text/xml", Some(Format::Xml)),
+    ("application/pdf", Some(Format::Pdf)),
+];
+
+#[derive(Debug, Deserialize)]
+struct ExtractorFile {
+    #[serde(default)]
+    extract: Vec<RawExtractor>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawExtractor {
+    content_type: String,
+    extractor: String,
+}
+
+/// How the documents of a content type are parsed: a MIME type (`application/xml`), every
+/// subtype of a type (`text/*`) or anything (`*/*`), `None` checking their status only.
+#[derive(Debug)]
+struct Extractor {
+    content_type: String,
+    format: Option<Format>,
+}
+
+impl Extractor {
+    fn matches(&self, mime: &str) -> bool {
+        match self.content_type.strip_suffix("/*") {
+            Some("*") => true,
+            Some(kind) => mime.split('/').next() == Some(kind),
+            None => self.content_type == mime,
+        }
+    }
+}
+
+/// The extractor of every content type: the configured ones first (in order), then the
+/// built-in ones.
+#[derive(Debug)]
+pub struct Extractors {
+    extractors: Vec<Extractor>,
+}
+
+impl Extractors {
+    /// Load the `[[extract]]` tables of the given TOML files, mapping a `content_type` to an
+    /// `extractor`: `html`, `css`, `xml` (sitemaps and feeds), `feed`, `manifest`, `pdf` or
+    /// `none`.
+    pub fn load(paths: &[String]) -> Result<Self, Box<dyn Error>> {
+        let mut extractors = vec![];
+
+        for path in paths {
+            let file: ExtractorFile = toml::from_str(&fs::read_to_string(path)?)?;
+
+            for extractor in file.extract {
+                let format = match extractor.extractor.as_str() {
+                    "none" => None,
+                    name => Some(
+                        Format::deserialize(StrDeserializer::<ValueError>::new(name)).map_err(
+                            |_| format!("unknown extractor `{}` in {}", extractor.extractor, path),
+                        )?,
+                    ),
+                };
+
+                extractors.push(Extractor {
+                    content_type: extractor.content_type.trim().to_lowercase(),
+                    format,
+                });
+            }
+        }
+
+        extractors.extend(DEFAULTS.iter().map(|(content_type, format)| Extractor {
+            content_type: content_type.to_string(),
+            format: *format,
+        }));
+
+        Ok(Self { extractors })
+    }
+
+    /// Get the format a `Content-Type` header is parsed as, if any.
+    pub fn format(&self, content_type: &str) -> Option<Format> {
+        let mime = content_type.split(';').next()?.trim().to_lowercase();
+
+        self.extractors
+            .iter()
+            .find(|x| x.matches(&mime))
+            .and_then(|x| x.format)
+    }
+}
@@ -0,0 +1,88 @@
+use std::error::Error;
+use std::fs::File;
+
+use serde::Serialize;
+use url::Url;
+
+use crate::redirects::Rewrite;
+
+/// A broken internal link, with the page it probably meant to point to.
+#[derive(Debug, Serialize)]
+pub struct BrokenLink {
+    pub url: String,
+    pub status: u16,
+    pub found_on: Vec<String>,
+    pub suggestion: Option<String>,
+}
+
+/// An `http://` resource linked from `https://` pages, whose `https://` equivalent exists.
+#[derive(Debug, Serialize)]
+pub struct MixedContent {
+    pub url: String,
+    pub https: String,
+    pub found_on: Vec<String>,
+}
+
+/// Actionable items of a crawl, to be applied in bulk by another tool (or a CMS import).
+#[derive(Debug, Default, Serialize)]
+pub struct Fixes {
+    pub redirects: Vec<Rewrite>,
+    pub broken_links: Vec<BrokenLink>,
+    pub mixed_content: Vec<MixedContent>,
+}
+
+impl Fixes {
+    /// Write the fixes as JSON into the given file.
+    pub fn write_json(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        serde_json::to_writer_pretty(File::create(path)?, self)?;
+        Ok(())
+    }
+}
+
+/// Compute the edit distance between two strings.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, x) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+
+        for (j, y) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = (previous + usize::from(x != *y)).min(row[j] + 1).min(current + 1);
+            previous = current;
+        }
+    }
+
+    row[b.len()]
+}
+
+/// Suggest the page a broken URL probably meant to point to: a page with the same last path
+/// segment (moved page), or else the page with the closest path (typo).
+pub fn suggest_target(url: &Url, pages: &[Url]) -> Option<String> {
+    let slug = |x: &Url| {
+        x.path()
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    };
+    let candidates = pages.iter().filter(|x| x.origin() == url.origin());
+
+    if let Some(page) = candidates
+        .clone()
+        .find(|x| !slug(url).is_empty() && slug(x) == slug(url))
+    {
+        return Some(page.to_string());
+    }
+
+    let max = (url.path().len() / 3).max(3);
+
+    candidates
+        .map(|x| (distance(x.path(), url.path()), x))
+        .filter(|(distance, _)| *distance <= max)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, x)| x.to_string())
+}
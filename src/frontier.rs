@@ -0,0 +1,363 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+/// A file of pending URLs (one `rank url` per line), spilled out of memory.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Segment {
+    path: PathBuf,
+    len: usize,
+
+    /// Where the first pending line starts, its head having been taken by [`Frontier::pop_where`].
+    #[serde(default)]
+    offset: u64,
+}
+
+/// The pending URLs, the lowest ranked first (the first queued among them, or the last one if
+/// `lifo`): at most `limit` of them are kept in memory (--spill-queue), the next ones being
+/// appended to segment files until the memory is empty again.
+///
+/// The ranks only order the URLs in memory: the spilled ones are loaded back in their order.
+#[derive(Debug)]
+pub struct Frontier {
+    memory: BinaryHeap<Reverse<(u64, u64, Arc<str>)>>,
+
+    /// The rank of every URL in memory: promoting an URL queues it again, the outranked entry
+    /// being skipped once popped.
+    ranks: HashMap<Arc<str>, u64>,
+    lifo: bool,
+    queued: u64,
+    segments: VecDeque<Segment>,
+    limit: Option<usize>,
+    dir: PathBuf,
+
+    /// The writer of the last segment, while it is not full.
+    writer: Option<BufWriter<File>>,
+    next: u64,
+
+    /// The segments loaded back, removed once a checkpoint doesn't need them anymore.
+    consumed: Vec<PathBuf>,
+    persistent: bool,
+}
+
+impl Frontier {
+    /// Spill the URLs into `dir` once there are more than `limit` of them, the segments being
+    /// kept for the next checkpoint if `persistent` (--state), or removed once loaded.
+    ///
+    /// Among the URLs of a rank, the last queued one comes first if `lifo` (depth-first crawls).
+    pub fn new(limit: Option<usize>, dir: PathBuf, persistent: bool, lifo: bool) -> Self {
+        Self {
+            memory: BinaryHeap::new(),
+            ranks: HashMap::new(),
+            lifo,
+            queued: 0,
+            segments: VecDeque::new(),
+            limit: limit.map(|x| x.max(1)),
+            dir,
+            writer: None,
+            next: 0,
+            consumed: vec![],
+            persistent,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ranks.len() + self.segments.iter().map(|x| x.len).sum::<usize>()
+    }
+
+    pub fn push(&mut self, url: Arc<str>, rank: u64) {
+        match self.limit {
+            Some(limit) if !self.segments.is_empty() || self.ranks.len() >= limit => {
+                if let Err(e) = self.spill(&url, rank, limit) {
+                    error!("Cannot spill the queue into {}: {}", self.dir.display(), e);
+                    self.insert(url, rank);
+                }
+            }
+            _ => self.insert(url, rank),
+        }
+    }
+
+    /// Give a better rank to an URL still in memory, e.g. found on another page.
+    pub fn promote(&mut self, url: &str, rank: u64) {
+        if let Some((url, _)) = self.ranks.get_key_value(url).filter(|(_, x)| **x > rank) {
+            let url = Arc::clone(url);
+
+            self.insert(url, rank);
+        }
+    }
+
+    fn insert(&mut self, url: Arc<str>, rank: u64) {
+        let order = match self.lifo {
+            true => u64::MAX - self.queued,
+            false => self.queued,
+        };
+
+        self.queued += 1;
+        self.ranks.insert(Arc::clone(&url), rank);
+        self.memory.push(Reverse((rank, order, url)));
+    }
+
+    /// Pop the first accepted URL (e.g. whose host isn't busy), the rejected ones staying queued.
+    ///
+    /// Once every URL in memory is rejected, only the first URL of each segment is considered:
+    /// the segments are not loaded back before the memory is empty.
+    pub fn pop_where(&mut self, accept: impl Fn(&str) -> bool) -> Option<Arc<str>> {
+        if self.ranks.is_empty() {
+            if let Some(segment) = self.segments.pop_front() {
+                if let Err(e) = self.load(&segment) {
+                    error!("Cannot read the queued URLs of {}: {}", segment.path.display(), e);
+                }
+            }
+        }
+
+        let mut rejected = vec![];
+        let found = loop {
+            match self.pop_entry() {
+                Some(entry) if accept(&entry.2) => break Some(entry.2),
+                Some(entry) => rejected.push(entry),
+                None => break None,
+            }
+        };
+
+        for (rank, order, url) in rejected {
+            self.ranks.insert(Arc::clone(&url), rank);
+            self.memory.push(Reverse((rank, order, url)));
+        }
+
+        match found {
+            Some(url) => Some(url),
+            None => self.pop_head_where(accept).unwrap_or_else(|e| {
+                error!("Cannot read the queued URLs of {}: {}", self.dir.display(), e);
+                None
+            }),
+        }
+    }
+
+    /// Pop the first URL of a segment, if accepted.
+    fn pop_head_where(&mut self, accept: impl Fn(&str) -> bool) -> io::Result<Option<Arc<str>>> {
+        if let Some(writer) = self.writer.as_mut() {
+            writer.flush()?;
+        }
+
+        for index in 0..self.segments.len() {
+            let segment = &mut self.segments[index];
+            let mut file = BufReader::new(File::open(&segment.path)?);
+            let mut line = String::new();
+
+            file.seek(SeekFrom::Start(segment.offset))?;
+
+            let read = file.read_line(&mut line)?;
+            let (_, url) = line.trim_end().split_once(' ').unwrap_or(("0", line.trim_end()));
+
+            if read == 0 || !accept(url) {
+                continue;
+            }
+
+            let url = Arc::from(url);
+
+            segment.offset += read as u64;
+            segment.len -= 1;
+
+            if segment.len == 0 {
+                if let Some(segment) = self.segments.remove(index) {
+                    self.consume(&segment)?;
+                }
+
+                // Spilling again starts a new segment.
+                if index == self.segments.len() {
+                    self.writer = None;
+                }
+            }
+
+            return Ok(Some(url));
+        }
+
+        Ok(None)
+    }
+
+    fn pop_entry(&mut self) -> Option<(u64, u64, Arc<str>)> {
+        while let Some(Reverse((rank, order, url))) = self.memory.pop() {
+            if self.ranks.get(&url) == Some(&rank) {
+                self.ranks.remove(&url);
+                return Some((rank, order, url));
+            }
+        }
+
+        None
+    }
+
+    /// Append an URL to the last segment, starting a new one when it is full.
+    fn spill(&mut self, url: &str, rank: u64, limit: usize) -> io::Result<()> {
+        if self.writer.is_none() || self.segments.back().is_none_or(|x| x.len >= limit) {
+            fs::create_dir_all(&self.dir)?;
+
+            let path = self.dir.join(format!("{}.urls", self.next));
+
+            self.writer = Some(BufWriter::new(File::create(&path)?));
+            self.segments.push_back(Segment { path, len: 0, offset: 0 });
+            self.next += 1;
+        }
+
+        if let (Some(writer), Some(segment)) = (self.writer.as_mut(), self.segments.back_mut()) {
+            writeln!(writer, "{} {}", rank, url)?;
+            segment.len += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Move the URLs of a segment back into memory.
+    fn load(&mut self, segment: &Segment) -> io::Result<()> {
+        // The segment being written is the last one.
+        if self.segments.is_empty() {
+            if let Some(mut writer) = self.writer.take() {
+                writer.flush()?;
+            }
+        }
+
+        let mut file = File::open(&segment.path)?;
+
+        file.seek(SeekFrom::Start(segment.offset))?;
+
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            let (rank, url) = line.split_once(' ').unwrap_or(("0", &line));
+
+            self.insert(Arc::from(url), rank.parse().unwrap_or_default());
+        }
+
+        debug!("{} queued URLs loaded from {}", segment.len, segment.path.display());
+
+        self.consume(segment)
+    }
+
+    /// Remove a segment out of the queue, or once the next checkpoint doesn't need it anymore.
+    fn consume(&mut self, segment: &Segment) -> io::Result<()> {
+        match self.persistent {
+            true => self.consumed.push(segment.path.clone()),
+            false => fs::remove_file(&segment.path)?,
+        }
+
+        Ok(())
+    }
+
+    /// Get the URLs kept in memory (in order), and the segments holding the next ones (written
+    /// down).
+    pub fn save(&mut self) -> io::Result<(VecDeque<Arc<str>>, Vec<Segment>)> {
+        if let Some(writer) = self.writer.as_mut() {
+            writer.flush()?;
+        }
+
+        let mut memory: Vec<&(u64, u64, Arc<str>)> = self
+            .memory
+            .iter()
+            .map(|Reverse(x)| x)
+            .filter(|(rank, _, url)| self.ranks.get(url) == Some(rank))
+            .collect();
+
+        memory.sort();
+
+        Ok((
+            memory.into_iter().map(|(_, _, url)| Arc::clone(url)).collect(),
+            self.segments.iter().cloned().collect(),
+        ))
+    }
+
+    /// Remove the segments loaded before the last checkpoint.
+    pub fn saved(&mut self) {
+        for path in self.consumed.drain(..) {
+            if let Err(e) = fs::remove_file(&path) {
+                warn!("Cannot remove {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// Resume from a checkpoint (the URLs saved in memory, ranked again, then the segments), new
+    /// segments being written after the saved ones.
+    pub fn restore(
+        &mut self,
+        memory: impl IntoIterator<Item = (Arc<str>, u64)>,
+        segments: Vec<Segment>,
+    ) {
+        memory.into_iter().for_each(|(url, rank)| self.insert(url, rank));
+        self.next = segments
+            .iter()
+            .filter_map(|x| x.path.file_stem()?.to_str()?.parse::<u64>().ok())
+            .max()
+            .map_or(0, |x| x + 1);
+        self.segments = segments.into();
+        self.writer = None;
+    }
+}
+
+impl Drop for Frontier {
+    fn drop(&mut self) {
+        if !self.persistent && self.limit.is_some() {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frontier(name: &str) -> Frontier {
+        let dir = std::env::temp_dir().join(format!("frontier-{}-{}", name, std::process::id()));
+
+        Frontier::new(Some(1), dir, false, false)
+    }
+
+    #[test]
+    fn pop_where_peeks_segment_heads() {
+        let mut frontier = frontier("heads");
+
+        for url in ["https://busy.test/", "https://a.test/1", "https://a.test/2"] {
+            frontier.push(Arc::from(url), 0);
+        }
+
+        assert_eq!(frontier.segments.len(), 2);
+
+        let url = frontier.pop_where(|x| !x.contains("busy"));
+
+        assert_eq!(url.as_deref(), Some("https://a.test/1"));
+        assert_eq!(frontier.ranks.len(), 1);
+        assert_eq!(frontier.segments.len(), 1);
+
+        let url = frontier.pop_where(|x| !x.contains("busy"));
+
+        assert_eq!(url.as_deref(), Some("https://a.test/2"));
+        assert!(frontier.segments.is_empty());
+        assert_eq!(frontier.pop_where(|x| !x.contains("busy")), None);
+        assert_eq!(frontier.pop_where(|_| true).as_deref(), Some("https://busy.test/"));
+        assert_eq!(frontier.len(), 0);
+    }
+
+    #[test]
+    fn pop_where_skips_taken_heads() {
+        let mut frontier = frontier("offset");
+        let urls = ["https://busy.test/1", "https://busy.test/2", "https://a.test/1"];
+
+        frontier.limit = Some(2);
+
+        for url in urls.iter().chain(&["https://a.test/2", "https://b.test/"]) {
+            frontier.push(Arc::from(*url), 0);
+        }
+
+        let url = frontier.pop_where(|x| !x.contains("busy"));
+
+        assert_eq!(url.as_deref(), Some("https://a.test/1"));
+
+        let urls: Vec<_> = std::iter::from_fn(|| frontier.pop_where(|_| true)).collect();
+
+        assert_eq!(
+            urls.iter().map(|x| &**x).collect::<Vec<_>>(),
+            ["https://busy.test/1", "https://busy.test/2", "https://a.test/2", "https://b.test/"]
+        );
+    }
+}
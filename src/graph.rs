@@ -0,0 +1,102 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::equity::Edge;
+use crate::history::name;
+use crate::report::{Entry, Verdict};
+
+/// Escape a DOT string, once quoted.
+fn dot_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Escape an XML attribute or text.
+pub fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Write the page → page link graph into the given file, the nodes being annotated with their
+/// status and verdict: as GraphML (Gephi...) for `.graphml` files, as DOT (Graphviz) otherwise.
+///
+/// A page linking several times to another one gives a single edge.
+pub fn write(edges: &[Edge], results: &[Entry], path: &str) -> Result<(), Box<dyn Error>> {
+    let links: BTreeSet<(&str, &str)> = edges
+        .iter()
+        .map(|x| (x.source.as_str(), x.target.as_str()))
+        .collect();
+    let entries: BTreeMap<&str, &Entry> = results.iter().map(|x| (x.url.as_str(), x)).collect();
+    let nodes: BTreeSet<&str> = links.iter().flat_map(|(a, b)| [*a, *b]).collect();
+    let mut file = BufWriter::new(File::create(path)?);
+
+    if path.ends_with(".graphml") {
+        writeln!(file, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(file, "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">")?;
+        writeln!(
+            file,
+            "  <key id=\"status\" for=\"node\" attr.name=\"status\" attr.type=\"int\"/>"
+        )?;
+        writeln!(
+            file,
+            "  <key id=\"verdict\" for=\"node\" attr.name=\"verdict\" attr.type=\"string\"/>"
+        )?;
+        writeln!(file, "  <graph id=\"links\" edgedefault=\"directed\">")?;
+
+        for node in &nodes {
+            write!(file, "    <node id=\"{}\">", xml_escape(node))?;
+
+            if let Some(entry) = entries.get(node) {
+                write!(file, "<data key=\"status\">{}</data>", entry.status)?;
+                write!(file, "<data key=\"verdict\">{}</data>", name(&entry.verdict))?;
+            }
+
+            writeln!(file, "</node>")?;
+        }
+
+        for (source, target) in &links {
+            writeln!(
+                file,
+                "    <edge source=\"{}\" target=\"{}\"/>",
+                xml_escape(source),
+                xml_escape(target)
+            )?;
+        }
+
+        writeln!(file, "  </graph>")?;
+        writeln!(file, "</graphml>")?;
+    } else {
+        writeln!(file, "digraph links {{")?;
+        writeln!(file, "  node [shape=box];")?;
+
+        for node in &nodes {
+            let id = dot_escape(node);
+            let (label, color) = match entries.get(node) {
+                Some(entry) => (
+                    format!("{}\\n{}", id, entry.status),
+                    match entry.verdict {
+                        Verdict::Success => "green",
+                        Verdict::Warning => "orange",
+                        Verdict::Failure => "red",
+                    },
+                ),
+                None => (id.clone(), "gray"),
+            };
+
+            writeln!(file, "  \"{}\" [label=\"{}\", color={}];", id, label, color)?;
+        }
+
+        for (source, target) in &links {
+            writeln!(file, "  \"{}\" -> \"{}\";", dot_escape(source), dot_escape(target))?;
+        }
+
+        writeln!(file, "}}")?;
+    }
+
+    file.flush()?;
+    Ok(())
+}
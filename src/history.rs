@@ -0,0 +1,128 @@
+use std::error::Error;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use crate::baseline::Previous;
+use crate::report::Entry;
+
+/// The tables of the results database: one row per run, per URL, and per URL of each run.
+/// Timestamps are UNIX timestamps.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    entrypoint TEXT NOT NULL,
+    started_at INTEGER NOT NULL,
+    finished_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS urls (
+    id INTEGER PRIMARY KEY,
+    url TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS results (
+    run_id INTEGER NOT NULL REFERENCES runs (id),
+    url_id INTEGER NOT NULL REFERENCES urls (id),
+    status INTEGER NOT NULL,
+    verdict TEXT NOT NULL,
+    resource TEXT NOT NULL,
+    count INTEGER NOT NULL,
+    redirects TEXT NOT NULL,
+    annotations TEXT NOT NULL,
+    found_on TEXT NOT NULL,
+    PRIMARY KEY (run_id, url_id)
+);
+
+CREATE INDEX IF NOT EXISTS results_url ON results (url_id, run_id);
+";
+
+/// Get a UNIX timestamp.
+fn timestamp(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |x| x.as_secs() as i64)
+}
+
+/// Get the name of a unit enum variant, as serialized (e.g. `failure`).
+pub fn name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|x| x.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Store the results of a run into a SQLite database (created if needed), and get its ID.
+///
+/// Lists (redirects, annotations and referrers) are stored as JSON arrays.
+pub fn record(
+    path: &str,
+    entrypoint: &str,
+    started_at: SystemTime,
+    entries: &[Entry],
+) -> Result<i64, Box<dyn Error>> {
+    let mut connection = Connection::open(path)?;
+
+    connection.execute_batch(SCHEMA)?;
+
+    let transaction = connection.transaction()?;
+
+    transaction.execute(
+        "INSERT INTO runs (entrypoint, started_at, finished_at) VALUES (?1, ?2, ?3)",
+        params![entrypoint, timestamp(started_at), timestamp(SystemTime::now())],
+    )?;
+
+    let run = transaction.last_insert_rowid();
+
+    {
+        let mut url = transaction.prepare(
+            "INSERT INTO urls (url) VALUES (?1) ON CONFLICT (url) DO UPDATE SET url = url \
+             RETURNING id",
+        )?;
+        let mut result = transaction.prepare(
+            "INSERT INTO results (run_id, url_id, status, verdict, resource, count, redirects, \
+             annotations, found_on) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        )?;
+
+        for entry in entries {
+            let url_id: i64 = url.query_row(params![entry.url], |x| x.get(0))?;
+
+            result.execute(params![
+                run,
+                url_id,
+                entry.status,
+                name(&entry.verdict),
+                name(&entry.resource),
+                entry.count,
+                serde_json::to_string(&entry.redirects)?,
+                serde_json::to_string(&entry.annotations)?,
+                serde_json::to_string(&entry.found_on)?,
+            ])?;
+        }
+    }
+
+    transaction.commit()?;
+    Ok(run)
+}
+
+/// Get the results of the last run stored into a SQLite database.
+pub fn last_results(path: &str) -> Result<Vec<Previous>, Box<dyn Error>> {
+    let connection = Connection::open(path)?;
+    let mut statement = connection.prepare(
+        "SELECT url, status, verdict FROM results JOIN urls ON urls.id = url_id \
+         WHERE run_id = (SELECT MAX(id) FROM runs)",
+    )?;
+    let results = statement
+        .query_map([], |x| Ok((x.get(0)?, x.get(1)?, x.get::<_, String>(2)?)))?
+        .map(|x| {
+            let (url, status, verdict) = x?;
+
+            Ok(Previous {
+                url,
+                status,
+                verdict: serde_json::from_value(serde_json::Value::String(verdict))?,
+            })
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+    Ok(results)
+}
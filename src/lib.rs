@@ -0,0 +1,758 @@
+//! Crawl websites and check the status of every link.
+//!
+//! The command line is a thin wrapper around [`cli`]. Other projects can embed the checker with
+//! a [`CrawlerBuilder`], the options defaulting to the ones of the command line:
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let results = http_status_check::CrawlerBuilder::new("https://example.com")
+//!     .concurrency(8)
+//!     .check_external(true)
+//!     .build()?
+//!     .run()
+//!     .await;
+//!
+//! for entry in results {
+//!     println!("{} {}", entry.status, entry.url);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+#[macro_use]
+extern crate tracing;
+
+mod audit;
+mod baseline;
+mod certs;
+mod codeclimate;
+mod completions;
+mod config;
+mod content;
+mod cookies;
+mod crawler;
+mod deprecation;
+mod diff;
+mod dns;
+mod email;
+mod equity;
+mod error_page;
+mod events;
+mod extract;
+mod extractors;
+mod fixes;
+mod frontier;
+mod graph;
+mod history;
+mod logging;
+mod middleware;
+mod network;
+mod notify;
+mod opts;
+mod parse;
+mod pdf;
+mod plugin;
+mod policy;
+mod prime;
+mod progress;
+mod redirects;
+mod render;
+mod report;
+mod resolve;
+mod revalidate;
+mod robots;
+mod schedule;
+mod seo;
+mod serve;
+mod sink;
+mod sitemap;
+mod static_dir;
+mod suggest;
+mod tls;
+mod tui;
+mod usage;
+mod validate;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod wayback;
+mod worker;
+
+use std::error::Error;
+use std::io::{self, IsTerminal};
+use std::net::IpAddr;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use clap::{Clap, IntoApp};
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::{redirect, Client, ClientBuilder, Method, Proxy};
+use url::Url;
+
+use crawler::Response;
+use opts::{Command, Opts};
+use report::{Report, Stage};
+use sink::OutputSink;
+use usage::Usage;
+use worker::Worker;
+
+pub use crawler::{Crawler, CrawlerBuilder};
+pub use events::CrawlEvent;
+pub use extract::Resource;
+pub use report::{Entry, Verdict};
+pub use validate::{CheckedResponse, Validator};
+
+/// The encodings bodies can be decompressed from, advertised with `Accept-Encoding`.
+const ACCEPT_ENCODING: &str = "gzip, deflate, br";
+
+/// The product of the default `User-Agent`, identifying the crawler.
+const USER_AGENT: &str = concat!("http-status-check/", env!("CARGO_PKG_VERSION"));
+
+/// The contact URL of the default `User-Agent`.
+const CONTACT_URL: &str = "https://github.com/cmizzi/http-status-check";
+
+/// The minimal delay between two requests to a host, in polite mode.
+const POLITE_DELAY: Duration = Duration::from_secs(1);
+
+/// Get the `User-Agent` of the requests: --user-agent or the default one, with the contact URL
+/// and the disclosed crawl rate as comments (`http-status-check/1.0 (+https://...; 5 concurrent
+/// requests)`).
+fn user_agent(opts: &Opts) -> String {
+    let mut comments = vec![];
+
+    match (&opts.contact_url, &opts.user_agent) {
+        (Some(url), _) => comments.push(format!("+{}", url)),
+        (None, None) => comments.push(format!("+{}", CONTACT_URL)),
+        (None, Some(_)) => {}
+    }
+
+    if opts.disclose_rate {
+        comments.push(match (opts.polite, opts.per_host_concurrency) {
+            (true, _) => format!("1 request at a time, {:?} apart per host", POLITE_DELAY),
+            (false, Some(max)) => {
+                format!("{} concurrent requests, {} per host", opts.concurrency, max)
+            }
+            (false, None) => format!("{} concurrent requests", opts.concurrency),
+        });
+    }
+
+    let product = opts.user_agent.as_deref().unwrap_or(USER_AGENT);
+
+    match comments.is_empty() {
+        true => product.to_string(),
+        false => format!("{} ({})", product, comments.join("; ")),
+    }
+}
+
+/// Build an HTTP client with the crawler settings. Redirects are followed manually, hop by hop.
+///
+/// Hosts are resolved, and connections opened, through the usage counters.
+fn client_builder(opts: &Opts, usage: &Arc<Usage>) -> Result<ClientBuilder, Box<dyn Error>> {
+    let mut builder = Client::builder()
+        .redirect(redirect::Policy::none())
+        .danger_accept_invalid_certs(opts.insecure)
+        .dns_resolver(Arc::clone(usage))
+        .connector_layer(usage::Connections(Arc::clone(usage)))
+        .tls_info(true);
+
+    builder = builder.user_agent(user_agent(opts));
+
+    if let Some(proxy) = &opts.proxy {
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
+
+    // Binding to an address of a family only connects to addresses of this family.
+    if opts.ipv4 {
+        builder = builder.local_address(IpAddr::from([0, 0, 0, 0]));
+    } else if opts.ipv6 {
+        builder = builder.local_address(IpAddr::from([0u16; 8]));
+    }
+
+    // HTTP/1.1 is spoken by default, HTTP/2 not being negotiated over TLS.
+    if opts.http2_prior_knowledge && !opts.http1 {
+        builder = builder.http2_prior_knowledge();
+    }
+
+    let mut headers: HeaderMap = opts.header.iter().cloned().collect();
+
+    if let Some(from) = &opts.from {
+        headers.insert(reqwest::header::FROM, from.clone());
+    }
+
+    // Compressed responses are decompressed, so --require-compression can tell them apart.
+    if !headers.contains_key(reqwest::header::ACCEPT_ENCODING) {
+        headers.insert(
+            reqwest::header::ACCEPT_ENCODING,
+            HeaderValue::from_static(ACCEPT_ENCODING),
+        );
+    }
+
+    builder = builder.default_headers(headers);
+
+    for cert in opts.ca_cert.iter().flat_map(|x| &x.0) {
+        builder = builder.add_root_certificate(cert.clone());
+    }
+
+    if let (Some(cert), Some(key)) = (&opts.client_cert, &opts.client_key) {
+        builder = builder.identity(tls::identity(cert, key)?);
+    }
+
+    Ok(builder)
+}
+
+/// Read a list of URLs, one per line (`-` for stdin). Empty lines and `#` comments are skipped.
+fn read_urls(path: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let content = match path {
+        "-" => {
+            let mut content = String::new();
+            std::io::Read::read_to_string(&mut std::io::stdin(), &mut content)?;
+            content
+        }
+        path => std::fs::read_to_string(path)?,
+    };
+    let urls: Vec<String> = content
+        .lines()
+        .map(str::trim)
+        .filter(|x| !x.is_empty() && !x.starts_with('#'))
+        .map(str::to_string)
+        .collect();
+
+    if urls.is_empty() {
+        return Err(format!("no URL found in {}", path).into());
+    }
+
+    Ok(urls)
+}
+
+/// Check if an URL obviously targets a non-HTML resource (archive, image, media, font…), based on
+/// its extension.
+fn is_static_resource(url: &str) -> bool {
+    const EXTENSIONS: &[&str] = &[
+        "7z", "avi", "bmp", "bz2", "dmg", "doc", "docx", "eot", "exe", "gif", "gz", "ico", "iso",
+        "jpeg", "jpg", "mov", "mp3", "mp4", "ogg", "otf", "pdf", "png", "ppt", "pptx", "rar", "svg",
+        "tar", "tgz", "ttf", "wav", "webm", "webp", "woff", "woff2", "xls", "xlsx", "xz", "zip",
+    ];
+
+    let extension = Url::parse(url).ok().and_then(|url| {
+        url.path_segments()
+            .and_then(|mut x| x.next_back().map(str::to_string))
+            .and_then(|x| x.rsplit_once('.').map(|(_, ext)| ext.to_lowercase()))
+    });
+
+    extension.is_some_and(|x| EXTENSIONS.contains(&x.as_str()))
+}
+
+/// Check if the URL domain matches one of the given domains (or their subdomains).
+fn matches_any_domain(url: &Url, domains: &[String]) -> bool {
+    url.domain()
+        .is_some_and(|domain| domains.iter().any(|x| is_domain_or_subdomain(domain, x)))
+}
+
+/// Check if `domain` is `parent` itself or one of its subdomains.
+fn is_domain_or_subdomain(domain: &str, parent: &str) -> bool {
+    let domain = domain.trim_end_matches('.').to_lowercase();
+    let parent = parent.trim_end_matches('.').to_lowercase();
+
+    domain == parent || domain.ends_with(&format!(".{}", parent))
+}
+
+/// Check the certificate of every host contacted over TLS.
+async fn certificates(usage: &Usage, window: Duration) -> Vec<certs::Certificate> {
+    let mut certificates = vec![];
+
+    for (host, port) in usage.tls_hosts() {
+        let task = tokio::task::spawn_blocking(move || {
+            certs::fetch(&host, port, window).map_err(|e| format!("{}: {}", host, e))
+        });
+
+        match task.await {
+            Ok(Ok(cert)) => {
+                if cert.expiring {
+                    warn!("The certificate of {} expires in {} days", cert.host, cert.days_left);
+                }
+
+                certificates.push(cert);
+            }
+            Ok(Err(e)) => warn!("Cannot check the certificate of {}", e),
+            Err(e) => warn!("Cannot check a certificate: {}", e),
+        }
+    }
+
+    certificates
+}
+
+/// The host of an URL, or an empty string.
+fn host(url: &str) -> String {
+    Url::parse(url)
+        .ok()
+        .and_then(|x| x.host_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Interrupt the crawl on Ctrl-C (or `SIGTERM`), a second signal exiting right away.
+fn trap_signals(interrupted: &Arc<AtomicBool>) -> io::Result<Vec<signal_hook::SigId>> {
+    let mut ids = vec![];
+
+    for signal in &[signal_hook::consts::SIGINT, signal_hook::consts::SIGTERM] {
+        ids.push(signal_hook::flag::register_conditional_shutdown(
+            *signal,
+            130,
+            Arc::clone(interrupted),
+        )?);
+        ids.push(signal_hook::flag::register(*signal, Arc::clone(interrupted))?);
+    }
+
+    Ok(ids)
+}
+
+/// How long to wait between two --watch passes, by default.
+const WATCH_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Run the command line: parse the options (and the configuration file), crawl, then report.
+pub async fn cli() -> Result<(), Box<dyn Error>> {
+    let mut opts = Opts::parse_from(config::args(Opts::into_app(), std::env::args_os().collect())?);
+
+    if opts.generate_man {
+        print!("{}", completions::man(&Opts::into_app()));
+        return Ok(());
+    }
+
+    if let Some(Command::Completions(shell)) = &opts.command {
+        print!("{}", completions::generate(&Opts::into_app(), &shell.shell));
+        return Ok(());
+    }
+
+    let _logging =
+        logging::init(opts.verbose, opts.quiet, &opts.log_format, opts.otlp_endpoint.as_deref())?;
+
+    let mut started_at = SystemTime::now();
+
+    // Loaded before crawling, as the baseline may be the results database of this run.
+    let baseline = opts.baseline.as_deref().map(baseline::load).transpose()?;
+
+    if let Some(Command::Check(check)) = opts.command.clone() {
+        if let Some(url) = check.url {
+            opts.entrypoint = vec![url.clone()];
+            return check_url(opts, &url, &check.show_header).await;
+        }
+
+        opts.entrypoint = check.base_url.into_iter().collect();
+    }
+
+    if opts.prime_cache {
+        opts.restrict_on_domain = true;
+    }
+
+    if let Some(lang) = opts.lang.as_ref().filter(|x| !opts.lang_prefixes.contains(x)) {
+        clap::Error::with_description(
+            format!("--lang {} is not one of the --lang-prefixes\n", lang),
+            clap::ErrorKind::InvalidValue,
+        )?
+        .exit();
+    }
+
+    if let Some(dir) = &opts.serve_dir {
+        let rules = serve::Rules::load(std::path::Path::new(dir))?;
+
+        opts.entrypoint = vec![serve::spawn(dir, vec![Box::new(rules)])?.to_string()];
+    }
+
+    // Schedules can give their own entrypoints.
+    let scheduled =
+        !opts.schedule.is_empty() && opts.schedule.iter().all(|x| !x.entrypoints.is_empty());
+
+    if opts.entrypoint.is_empty() && opts.input.is_none() && !scheduled {
+        clap::Error::with_description(
+            "The following required arguments were not provided:\n    <entrypoint>\n",
+            clap::ErrorKind::MissingRequiredArgument,
+        )?
+        .exit();
+    }
+
+    if !opts.schedule.is_empty() {
+        return run_schedules(opts, baseline).await;
+    }
+
+    let mut previous = baseline;
+
+    loop {
+        let results = crawl(opts.clone(), previous.take(), started_at).await?;
+
+        if !opts.watch {
+            let slow = results
+                .iter()
+                .filter(|x| x.annotations.iter().any(|x| x.starts_with("slow (")))
+                .count();
+
+            if opts.fail_on_slow && slow > 0 {
+                return Err(format!("{} pages slower than their threshold", slow).into());
+            }
+
+            return Ok(());
+        }
+
+        // The next passes are compared with this one, and do not resume it.
+        previous = Some(results.iter().map(baseline::Previous::from).collect());
+        opts.diff = true;
+        opts.resume = false;
+
+        let interval = opts.interval.unwrap_or(WATCH_INTERVAL);
+
+        info!("Crawling again in {}s", interval.as_secs());
+        tokio::time::sleep(interval).await;
+        started_at = SystemTime::now();
+    }
+}
+
+/// Request a single URL (`check <url>`) through the client, host policies and middlewares of a
+/// crawl, and print its status, response time, redirects and headers. Fails unless the final
+/// response is successful.
+async fn check_url(opts: Opts, url: &str, headers: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut crawler = Worker::new(Crawler::new(opts)?).await;
+    let started = Instant::now();
+
+    // The response times are remembered for known URLs only.
+    crawler.responses.insert(Arc::from(url), Response::new(0, 0));
+
+    let mut class = None;
+    let result = crawler.fetch(Method::GET, url).await.map_err(|e| {
+        class = network::classify(&*e);
+        network::describe(&*e)
+    });
+    let (response, redirects) = match result {
+        Ok(answer) => answer,
+        Err(e) => {
+            let class = class.map(|x| format!(" ({})", history::name(&x))).unwrap_or_default();
+
+            return Err(format!("{} is unreachable{}: {}", url, class, e).into());
+        }
+    };
+    let status = response.status();
+    let target = redirects.last().map_or(url, String::as_str).to_string();
+    let mut values: Vec<(String, String)> = vec![];
+    let names = match headers {
+        [] => vec!["content-type".to_string()],
+        names => names.to_vec(),
+    };
+
+    for name in &names {
+        for value in response.headers().get_all(name.as_str()) {
+            values.push((name.to_lowercase(), value.to_str().unwrap_or_default().to_string()));
+        }
+    }
+
+    let length = response.bytes().await?.len();
+    let ttfb = crawler.responses.get(url).and_then(|x| x.ttfb).unwrap_or_default();
+
+    println!("{} {}", status, url);
+
+    for redirect in &redirects {
+        println!("  -> {}", redirect);
+    }
+
+    println!(
+        "  time: {}ms (first byte after {}ms), {} bytes",
+        started.elapsed().as_millis(),
+        ttfb.as_millis(),
+        length
+    );
+
+    for (name, value) in values {
+        println!("  {}: {}", name, value);
+    }
+
+    match crawler.verdict(&target, status.as_u16()) {
+        Verdict::Failure => Err(format!("{} answered {}", target, status).into()),
+        _ => Ok(()),
+    }
+}
+
+/// Crawl on the --schedule expressions, each pass being compared with the previous one of the
+/// same schedule.
+async fn run_schedules(
+    opts: Opts,
+    baseline: Option<Vec<baseline::Previous>>,
+) -> Result<(), Box<dyn Error>> {
+    let schedules = opts.schedule.clone();
+    let mut previous: Vec<_> = schedules.iter().map(|_| baseline.clone()).collect();
+    let mut crawled = vec![false; schedules.len()];
+
+    loop {
+        let now = SystemTime::now();
+        let (index, at) = schedules
+            .iter()
+            .enumerate()
+            .filter_map(|(i, x)| Some((i, x.next(now)?)))
+            .min_by_key(|x| x.1)
+            .ok_or("No schedule runs anymore")?;
+        let wait = at.duration_since(now).unwrap_or_default();
+
+        info!("Next crawl ({}) in {}s", schedules[index].expression, wait.as_secs());
+        tokio::time::sleep(wait).await;
+
+        let mut pass = opts.clone();
+
+        if !schedules[index].entrypoints.is_empty() {
+            pass.entrypoint = schedules[index].entrypoints.clone();
+        }
+
+        if crawled[index] {
+            pass.diff = true;
+            pass.resume = false;
+        }
+
+        let results = crawl(pass, previous[index].take(), SystemTime::now()).await?;
+
+        previous[index] = Some(results.iter().map(baseline::Previous::from).collect());
+        crawled[index] = true;
+    }
+}
+
+/// Get the outputs of a crawl: what is printed on stdout, then the report files.
+fn outputs(opts: &Opts) -> Result<Vec<Box<dyn OutputSink>>, Box<dyn Error>> {
+    let mut sinks: Vec<Box<dyn OutputSink>> = vec![];
+    let file = |format: &str, path: &String| sink::Output {
+        format: format.to_string(),
+        path: path.clone(),
+    };
+
+    match (&opts.format, opts.diff) {
+        (Some(format), _) => sinks.push(Box::new(sink::Console::Results(format.clone()))),
+        (None, _) if opts.quiet => {}
+        (None, true) => sinks.push(Box::new(sink::Console::Changes)),
+        (None, false) => sinks.push(Box::new(sink::Console::Summary)),
+    }
+
+    let files = opts.report.iter().map(|x| file("json", x));
+    let files = files.chain(opts.html_report.iter().map(|x| file("html", x)));
+
+    for output in files.chain(opts.output.iter().cloned()) {
+        sinks.push(Box::new(sink::FileSink::new(output)?));
+    }
+
+    Ok(sinks)
+}
+
+/// Get the notifications sent once the crawl is done (the webhook, chats and email), and the
+/// results database.
+fn notifiers(
+    opts: &Opts,
+    webhook: Option<notify::Webhook>,
+    started_at: SystemTime,
+) -> Result<Vec<Box<dyn OutputSink>>, Box<dyn Error>> {
+    let mut sinks: Vec<Box<dyn OutputSink>> = vec![];
+
+    if let Some(webhook) = webhook {
+        sinks.push(Box::new(webhook));
+    }
+
+    for chat in &opts.notify {
+        sinks.push(Box::new(sink::Notification {
+            chat: chat.clone(),
+            report_url: opts.report_url.clone(),
+        }));
+    }
+
+    if !opts.email_to.is_empty() {
+        sinks.push(Box::new(sink::Email {
+            from: match &opts.email_from {
+                Some(from) => from.clone(),
+                None => email::DEFAULT_FROM.parse()?,
+            },
+            to: opts.email_to.clone(),
+            smtp: opts.smtp.as_deref().unwrap_or(email::DEFAULT_SMTP).to_string(),
+            attachment: opts.html_report.clone(),
+        }));
+    }
+
+    if let Some(path) = &opts.db {
+        sinks.push(Box::new(sink::Database {
+            path: path.clone(),
+            started_at,
+        }));
+    }
+
+    Ok(sinks)
+}
+
+/// Crawl the entrypoints, report the results and compare them with the previous ones, if any.
+async fn crawl(
+    opts: Opts,
+    baseline: Option<Vec<baseline::Previous>>,
+    started_at: SystemTime,
+) -> Result<Vec<Entry>, Box<dyn Error>> {
+    let mut crawler = Crawler::new(opts)?;
+    let signals = trap_signals(&crawler.interrupted)?;
+
+    let terminal = io::stdout().is_terminal() && io::stderr().is_terminal();
+
+    if crawler.opts.tui && !terminal {
+        warn!("The dashboard needs a terminal, showing the logs instead");
+    }
+
+    let dashboard = match crawler.opts.tui && terminal {
+        true => Some(tokio::spawn(tui::show(crawler.events(), Arc::clone(&crawler.interrupted)))),
+        false => None,
+    };
+    let progress = match crawler.opts.no_progress || crawler.opts.quiet || dashboard.is_some() {
+        false if terminal => Some(tokio::spawn(progress::show(crawler.events()))),
+        _ => None,
+    };
+    let outputs = tokio::spawn(sink::forward(crawler.events(), outputs(&crawler.opts)?));
+    let mut crawler = crawler.crawl().await;
+
+    if let Some(dashboard) = dashboard {
+        if let Err(e) = dashboard.await? {
+            warn!("Cannot show the dashboard: {}", e);
+        }
+    }
+
+    if let Some(progress) = progress {
+        progress.await?;
+    }
+
+    for id in signals {
+        signal_hook::low_level::unregister(id);
+    }
+
+    if crawler.opts.dry_run {
+        crawler.print_plan();
+        return Ok(vec![]);
+    }
+
+    let interrupted = crawler.is_interrupted();
+
+    if interrupted {
+        warn!("Crawl interrupted, reporting the {} URLs collected so far", crawler.responses.len());
+    }
+
+    crawler.stages.enter(Stage::Verification);
+    let mut results = crawler.entries();
+
+    if crawler.opts.wayback && !interrupted {
+        crawler.archived(&mut results).await;
+    }
+
+    let broken_anchors = crawler.broken_anchors();
+    let duplicates = crawler.duplicates();
+    let duplicate_content = crawler.duplicate_content();
+    let redirect_groups = crawler.redirect_groups();
+    let hreflang_errors = crawler.hreflang_errors();
+    let budgets = crawler.budgets();
+    let insecure_links = crawler.insecure_links();
+    let off_domain_redirects = crawler.off_domain_redirects();
+    let suggested_exclusions = suggest::suggestions(&results);
+    let certificates = match crawler.opts.cert_expiry_warn {
+        Some(window) if !interrupted => certificates(&crawler.usage, window).await,
+        _ => vec![],
+    };
+
+    crawler.stages.enter(Stage::Reporting);
+    crawler.plugins.finish();
+
+    // Mixed content fixes are verified, so the plan is built before the usage is reported.
+    if let Some(path) = crawler.opts.fixes.clone() {
+        let mut worker = Worker::new(crawler).await;
+
+        worker.fixes(&results).await.write_json(&path)?;
+        crawler = worker.into_inner();
+    }
+
+    crawler.stages.finish();
+
+    crawler.header_audits.sort_by(|a, b| a.url.cmp(&b.url));
+    crawler.content_failures.sort_by(|a, b| a.url.cmp(&b.url));
+    crawler.seo_audits.sort_by(|a, b| a.url.cmp(&b.url));
+    seo::flag_duplicate_titles(&mut crawler.seo_audits);
+
+    let pages = crawler
+        .opts
+        .group_by
+        .as_ref()
+        .map(|_| report::group_by_page(&results));
+
+    let changes = baseline.map(|x| baseline::changes(&x, &results));
+    let hosts = report::host_health(&results, &certificates);
+    let languages = report::languages(&results, &crawler.opts.lang_prefixes);
+    let webhook = crawler.webhook.take();
+    let mut usage = crawler.usage.summary();
+
+    // The hosts resolved with --dns or --doh are looked up by the crawler itself.
+    usage.dns_lookups += crawler.dns.as_ref().map_or(0, |x| x.lookups()) as u64;
+
+    let report = Report {
+        entrypoint: crawler.bases[0].as_str(),
+        entrypoints: crawler.bases.iter().map(Url::as_str).collect(),
+        captured_headers: &crawler.opts.capture_headers,
+        cache: crawler.opts.prime_cache.then(|| prime::Summary::new(&results)),
+        interrupted,
+        stages: crawler.stages.timings(),
+        results,
+        omitted: crawler.opts.failures_only.then(|| crawler.omitted()),
+        broken_anchors,
+        duplicates,
+        duplicate_content,
+        redirect_groups,
+        hreflang_errors,
+        budgets,
+        content_failures: &crawler.content_failures,
+        mixed_content: &crawler.mixed_content,
+        insecure_links,
+        off_domain_redirects,
+        other_schemes: &crawler.other_schemes,
+        single_scheme_hosts: crawler.single_scheme_hosts.values().collect(),
+        invalid_mailto: &crawler.invalid_mailto,
+        invalid_urls: &crawler.invalid_urls,
+        header_audits: &crawler.header_audits,
+        seo: &crawler.seo_audits,
+        certificates,
+        hosts,
+        languages,
+        deprecations: crawler.deprecations.list(),
+        suggested_exclusions,
+        pages,
+        usage,
+        error_page: crawler
+            .error_pages
+            .get(&crawler.bases[0].origin().ascii_serialization()),
+        changes,
+    };
+
+    let mut sinks = outputs.await?;
+
+    sinks.extend(notifiers(&crawler.opts, webhook, started_at)?);
+
+    for sink in &mut sinks {
+        sink.finish(&report).await?;
+    }
+
+    if let Some(path) = &crawler.opts.redirect_rewrites {
+        redirects::write_json(&redirects::rewrites(&crawler.redirects), path)?;
+    }
+
+    if let Some(path) = &crawler.opts.link_equity {
+        equity::write_csv(&crawler.edges, path)?;
+    }
+
+    if let Some(path) = &crawler.opts.graph {
+        graph::write(&crawler.edges, &report.results, path)?;
+    }
+
+    if let Some(path) = &crawler.opts.sitemap {
+        sitemap::write(&crawler.indexable, &report.results, path)?;
+    }
+
+    if let Some(path) = &crawler.opts.save_cookies {
+        crawler.cookies.save(path)?;
+    }
+
+    if let Some(revalidation) = &crawler.revalidation {
+        revalidation.save(crawler.opts.revalidate_cache.as_deref().unwrap_or_default())?;
+    }
+
+    if interrupted {
+        return Err("The crawl has been interrupted, its results are partial".into());
+    }
+
+    Ok(report.results)
+}
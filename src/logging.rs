@@ -0,0 +1,170 @@
+use std::error::Error;
+use std::fmt;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::{filter_fn, EnvFilter};
+use tracing_subscriber::fmt::format::{self, FormatEvent, FormatFields, Writer};
+use tracing_subscriber::fmt::time::{FormatTime, SystemTime};
+use tracing_subscriber::fmt::FmtContext;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+use crate::progress;
+
+/// Whether the log records are hidden, e.g. while the dashboard is shown.
+static MUTED: AtomicBool = AtomicBool::new(false);
+
+/// Hide (or show again) the log records.
+pub fn mute(muted: bool) {
+    MUTED.store(muted, Ordering::Relaxed);
+}
+
+/// Keep the exporter of the spans running, flushing them when dropped.
+#[derive(Debug, Default)]
+pub struct Guard {
+    #[cfg(feature = "otlp")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otlp")]
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Cannot export the crawl spans: {}", e);
+            }
+        }
+    }
+}
+
+/// Write the records as `[timestamp LEVEL]: message`, the level being colored on terminals.
+struct Text;
+
+impl<S, N> FormatEvent<S, N> for Text
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let level = *event.metadata().level();
+        let color = match level {
+            Level::ERROR => "31",
+            Level::WARN => "33",
+            Level::INFO => "32",
+            Level::DEBUG => "34",
+            Level::TRACE => "36",
+        };
+
+        if progress::SHOWN.load(Ordering::Relaxed) {
+            write!(writer, "\r\x1b[K")?;
+        }
+
+        write!(writer, "[")?;
+        SystemTime.format_time(&mut writer)?;
+
+        match writer.has_ansi_escapes() {
+            true => write!(writer, " \x1b[1;{}m{:>5}\x1b[0m]: ", color, level)?,
+            false => write!(writer, " {:>5}]: ", level)?,
+        }
+
+        ctx.field_format().format_fields(writer.by_ref(), event)?;
+        writeln!(writer)
+    }
+}
+
+/// Initialize the logs, written on stderr as text or JSON objects (`format`), and the export
+/// of the crawl spans to an OpenTelemetry collector, if any.
+///
+/// The verbosity is overridden by `RUST_LOG`, e.g. `RUST_LOG=http_status_check=debug`, unless
+/// the logs are disabled (`quiet`).
+pub fn init(
+    verbose: i32,
+    quiet: bool,
+    format: &str,
+    otlp_endpoint: Option<&str>,
+) -> Result<Guard, Box<dyn Error>> {
+    let level = match verbose {
+        0 => "error",
+        1 => "info",
+        2 => "debug",
+        _ => "trace",
+    };
+    let filter = match quiet {
+        true => EnvFilter::new("off"),
+        false => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(level)),
+    };
+    let unmuted = filter_fn(|_| !MUTED.load(Ordering::Relaxed));
+    let logs = match format {
+        "json" => tracing_subscriber::fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_current_span(false)
+            .with_span_list(false)
+            .with_target(false)
+            .with_writer(std::io::stderr)
+            .with_filter(filter)
+            .with_filter(unmuted)
+            .boxed(),
+        // Only the message is written, the fields of the records being for the JSON format.
+        _ => tracing_subscriber::fmt::layer()
+            .event_format(Text)
+            .with_ansi(std::io::stderr().is_terminal())
+            .fmt_fields(format::debug_fn(|writer, field, value| {
+                match field.name() {
+                    "message" => write!(writer, "{:?}", value),
+                    _ => Ok(()),
+                }
+            }))
+            .with_writer(std::io::stderr)
+            .with_filter(filter)
+            .with_filter(unmuted)
+            .boxed(),
+    };
+    let registry = tracing_subscriber::registry().with(logs);
+
+    #[cfg(feature = "otlp")]
+    if let Some(endpoint) = otlp_endpoint {
+        use opentelemetry::trace::TracerProvider;
+        use opentelemetry_otlp::WithExportConfig;
+
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_endpoint(endpoint)
+            .build()?;
+        let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(
+                opentelemetry_sdk::Resource::builder()
+                    .with_service_name(env!("CARGO_PKG_NAME"))
+                    .build(),
+            )
+            .build();
+        let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+        let spans = tracing_opentelemetry::layer()
+            .with_tracer(tracer)
+            .with_filter(EnvFilter::new("http_status_check=info"));
+
+        registry.with(spans).try_init()?;
+
+        return Ok(Guard {
+            provider: Some(provider),
+        });
+    }
+
+    #[cfg(not(feature = "otlp"))]
+    if otlp_endpoint.is_some() {
+        return Err("--otlp-endpoint needs the `otlp` feature".into());
+    }
+
+    registry.try_init()?;
+    Ok(Guard::default())
+}
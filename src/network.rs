@@ -0,0 +1,110 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// The class of a request failure, telling infrastructure problems from content ones.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkError {
+    Dns,
+    ConnectionRefused,
+    Tls,
+    Timeout,
+
+    /// The response headers not arriving within --ttfb-timeout, the request being sent.
+    Stalled,
+    Protocol,
+}
+
+/// A request aborted by --ttfb-timeout: the server accepted it, but has not answered in time.
+#[derive(Debug)]
+pub struct Stalled {
+    pub url: String,
+    pub limit: Duration,
+}
+
+impl fmt::Display for Stalled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "stalled: no response for url ({}) within {:?}", self.url, self.limit)
+    }
+}
+
+impl Error for Stalled {}
+
+/// Why HTTP/3 failed on a host, attached to the responses then requested over TCP (--http3).
+#[cfg(feature = "http3")]
+#[derive(Clone, Debug)]
+pub struct Http3Fallback(pub String);
+
+/// Describe a request failure with its chain of causes, which the errors of the HTTP client
+/// leave out of their message (`error sending request for url (...): client error (Connect):
+/// tcp connect error: Connection refused`).
+pub fn describe(error: &(dyn Error + 'static)) -> String {
+    let mut description = error.to_string();
+    let mut cause = error.source();
+
+    while let Some(error) = cause {
+        let message = error.to_string();
+
+        // Some errors already mention their cause.
+        if !description.contains(&message) {
+            description.push_str(": ");
+            description.push_str(&message);
+        }
+
+        cause = error.source();
+    }
+
+    description
+}
+
+/// Classify a request failure from its chain of causes, or `None` if it is not a network error
+/// (e.g. an unreadable file).
+pub fn classify(error: &(dyn Error + 'static)) -> Option<NetworkError> {
+    let mut found = None;
+    let mut cause = Some(error);
+
+    while let Some(error) = cause {
+        let message = error.to_string().to_lowercase();
+        let class = if message.contains("dns error")
+            || message.contains("failed to lookup address")
+            || message.starts_with("cannot resolve")
+        {
+            Some(NetworkError::Dns)
+        } else if error.is::<openssl::ssl::Error>()
+            || error.is::<openssl::error::ErrorStack>()
+            || message.contains("certificate")
+            || message.contains("handshake")
+        {
+            Some(NetworkError::Tls)
+        } else if error.is::<Stalled>() {
+            Some(NetworkError::Stalled)
+        } else if let Some(error) = error.downcast_ref::<io::Error>() {
+            match error.kind() {
+                io::ErrorKind::ConnectionRefused => Some(NetworkError::ConnectionRefused),
+                io::ErrorKind::TimedOut => Some(NetworkError::Timeout),
+                _ => None,
+            }
+        } else if error.downcast_ref::<reqwest::Error>().is_some_and(reqwest::Error::is_timeout)
+            || error.is::<tokio::time::error::Elapsed>()
+        {
+            Some(NetworkError::Timeout)
+        } else if error.is::<reqwest::Error>() || error.is::<hyper::Error>() {
+            Some(NetworkError::Protocol)
+        } else {
+            None
+        };
+
+        // The deepest cause is the most precise, the protocol errors wrapping the other ones.
+        found = match (found, class) {
+            (Some(NetworkError::Protocol), Some(class)) | (None, Some(class)) => Some(class),
+            (found, _) => found,
+        };
+        cause = error.source();
+    }
+
+    found
+}
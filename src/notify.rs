@@ -0,0 +1,236 @@
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Client, RequestBuilder};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::task::JoinHandle;
+use url::Url;
+
+use crate::baseline::{Change, ChangeKind};
+use crate::report::{Entry, Report, Verdict};
+
+/// How long to wait for a notification to be accepted.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many failing URLs are listed in chat messages, the most linked ones first.
+const TOP_OFFENDERS: usize = 5;
+
+/// The color of Discord embeds (red).
+const DISCORD_COLOR: u32 = 0xE7_4C_3C;
+
+/// A notification, posted as JSON.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Payload<'a> {
+    /// A link is broken, sent as soon as it is found.
+    BrokenLink {
+        url: &'a str,
+        status: u16,
+        redirects: &'a [String],
+        found_on: &'a [Arc<str>],
+    },
+
+    /// The crawl is finished: its summary, the failures, and the URLs broken since the
+    /// --baseline run.
+    Finished {
+        entrypoints: &'a [&'a str],
+        checked: usize,
+        failed: usize,
+        warnings: usize,
+        failures: Vec<&'a Entry>,
+        regressions: Vec<&'a Change>,
+    },
+}
+
+impl<'a> Payload<'a> {
+    pub fn finished(report: &'a Report) -> Self {
+        let count = |verdict| report.results.iter().filter(|x| x.verdict == verdict).count();
+
+        Self::Finished {
+            entrypoints: &report.entrypoints,
+            checked: report.checked(),
+            failed: count(Verdict::Failure),
+            warnings: count(Verdict::Warning),
+            failures: report
+                .results
+                .iter()
+                .filter(|x| x.verdict == Verdict::Failure)
+                .collect(),
+            regressions: report
+                .changes
+                .iter()
+                .flatten()
+                .filter(|x| x.kind == ChangeKind::Broken)
+                .collect(),
+        }
+    }
+}
+
+/// POST notifications to a webhook.
+#[derive(Debug)]
+pub struct Webhook {
+    url: Url,
+    client: Client,
+
+    /// The notifications sent during the crawl, awaited before the summary.
+    pending: Vec<JoinHandle<()>>,
+}
+
+impl Webhook {
+    pub fn new(url: Url) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            url,
+            client: Client::builder().timeout(TIMEOUT).build()?,
+            pending: vec![],
+        })
+    }
+
+    fn request(&self, payload: &Payload) -> RequestBuilder {
+        self.client
+            .post(self.url.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .body(serde_json::to_vec(payload).unwrap_or_default())
+    }
+
+    /// Send a notification in the background, without slowing down the crawl.
+    pub fn send(&mut self, payload: &Payload) {
+        let request = self.request(payload);
+
+        self.pending.push(tokio::spawn(async move {
+            if let Err(e) = request.send().await.and_then(|x| x.error_for_status()) {
+                warn!("Cannot send the webhook notification: {}", e);
+            }
+        }));
+    }
+
+    /// Wait for the pending notifications, then send the summary of the crawl.
+    pub async fn finish(&mut self, report: &Report<'_>) -> Result<(), Box<dyn Error>> {
+        for pending in self.pending.drain(..) {
+            pending.await?;
+        }
+
+        self.request(&Payload::finished(report))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+/// A chat service notified with a readable message, when failures or regressions are found.
+#[derive(Clone, Debug)]
+pub enum Chat {
+    /// A Slack incoming webhook, the message using Block Kit.
+    Slack(Url),
+
+    /// A Discord webhook, the message using an embed.
+    Discord(Url),
+}
+
+/// Parse a chat webhook: `slack://hooks.slack.com/services/...` or
+/// `discord://discord.com/api/webhooks/...`, sent over HTTPS.
+pub fn parse_chat(value: &str) -> Result<Chat, String> {
+    let invalid = || {
+        format!("invalid notification URL `{}`, expected slack://... or discord://...", value)
+    };
+    let (scheme, rest) = value.split_once("://").ok_or_else(invalid)?;
+    let url = Url::parse(&format!("https://{}", rest)).map_err(|_| invalid())?;
+
+    match scheme {
+        "slack" => Ok(Chat::Slack(url)),
+        "discord" => Ok(Chat::Discord(url)),
+        _ => Err(invalid()),
+    }
+}
+
+impl Chat {
+    /// Build the message of a crawl, if anything failed.
+    fn message(&self, report: &Report, report_url: Option<&Url>) -> Option<Value> {
+        let failures = report.most_linked_failures();
+        let regressions = report
+            .changes
+            .iter()
+            .flatten()
+            .filter(|x| x.kind == ChangeKind::Broken)
+            .count();
+
+        if failures.is_empty() && regressions == 0 {
+            return None;
+        }
+
+        let title = format!("Broken links on {}", report.sites());
+        let mut summary = format!("{} of {} URLs failed", failures.len(), report.checked());
+
+        if report.changes.is_some() {
+            summary.push_str(&format!(", {} newly broken since the baseline", regressions));
+        }
+
+        summary.push('.');
+
+        let offender = |x: &&Entry| match self {
+            Self::Slack(_) => format!("• `{}` <{}> (found {} times)", x.status, x.url, x.count),
+            Self::Discord(_) => format!("`{}` {} (found {} times)", x.status, x.url, x.count),
+        };
+        let offenders: Vec<String> = failures.iter().take(TOP_OFFENDERS).map(offender).collect();
+        let text = format!("{}\n\n{}", summary, offenders.join("\n"));
+
+        Some(match self {
+            Self::Slack(_) => {
+                let mut blocks = vec![
+                    json!({"type": "header", "text": {"type": "plain_text", "text": title}}),
+                    json!({"type": "section", "text": {"type": "mrkdwn", "text": text}}),
+                ];
+
+                if let Some(url) = report_url {
+                    let link = format!("<{}|Open the report>", url);
+
+                    blocks.push(json!({
+                        "type": "section",
+                        "text": {"type": "mrkdwn", "text": link},
+                    }));
+                }
+
+                json!({"text": format!("{}: {}", title, summary), "blocks": blocks})
+            }
+            Self::Discord(_) => json!({
+                "embeds": [{
+                    "title": title,
+                    "url": report_url.map(Url::as_str),
+                    "description": text,
+                    "color": DISCORD_COLOR,
+                }],
+            }),
+        })
+    }
+
+    /// Post the message of a crawl, unless nothing failed.
+    pub async fn send(
+        &self,
+        report: &Report<'_>,
+        report_url: Option<&Url>,
+    ) -> Result<(), Box<dyn Error>> {
+        let message = match self.message(report, report_url) {
+            Some(message) => message,
+            None => return Ok(()),
+        };
+        let url = match self {
+            Self::Slack(url) | Self::Discord(url) => url,
+        };
+
+        Client::builder()
+            .timeout(TIMEOUT)
+            .build()?
+            .post(url.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .body(serde_json::to_vec(&message)?)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+use std::time::Duration;
+
+use reqwest::header::{HeaderName, HeaderValue};
+
+
+/// A set of statuses, as ranges.
+#[derive(Clone, Debug)]
+pub(crate) struct Statuses(Vec<(u16, u16)>);
+
+impl Statuses {
+    pub(crate) fn contains(&self, status: u16) -> bool {
+        self.0.iter().any(|(min, max)| (*min..=*max).contains(&status))
+    }
+}
+
+/// Parse a list of statuses, status ranges and classes (`200-299,401,403`, `2xx,3xx`).
+pub(crate) fn parse_statuses(value: &str) -> Result<Statuses, String> {
+    let parse = |x: &str| {
+        x.trim()
+            .parse::<u16>()
+            .map_err(|_| format!("invalid status `{}`", x.trim()))
+    };
+    let mut ranges = vec![];
+
+    for part in value.split(',').map(str::trim).filter(|x| !x.is_empty()) {
+        let class = part
+            .strip_suffix("xx")
+            .or_else(|| part.strip_suffix("XX"))
+            .filter(|x| x.len() == 1);
+        let (min, max) = match (class, part.split_once('-')) {
+            (Some(class), _) => (parse(class)? * 100, parse(class)? * 100 + 99),
+            (None, Some((min, max))) => (parse(min)?, parse(max)?),
+            (None, None) => (parse(part)?, parse(part)?),
+        };
+
+        if min > max || !(100..=599).contains(&min) || !(100..=599).contains(&max) {
+            return Err(format!("invalid status range `{}`", part));
+        }
+
+        ranges.push((min, max));
+    }
+
+    Ok(Statuses(ranges))
+}
+
+/// Parse a request header (`Name: value`).
+pub(crate) fn parse_header(value: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let invalid = || format!("invalid header `{}`, expected `Name: value`", value);
+    let (name, header) = value.split_once(':').ok_or_else(invalid)?;
+
+    Ok((
+        HeaderName::from_bytes(name.trim().as_bytes()).map_err(|_| invalid())?,
+        HeaderValue::from_str(header.trim()).map_err(|_| invalid())?,
+    ))
+}
+
+/// Parse the path prefix of a language section (`fr`, `/fr` or `/fr/` being `/fr`).
+pub(crate) fn parse_lang_prefix(value: &str) -> Result<String, String> {
+    match value.trim().trim_matches('/') {
+        "" => Err(format!("invalid language prefix `{}`, expected e.g. `/fr`", value)),
+        prefix => Ok(format!("/{}", prefix)),
+    }
+}
+
+/// Parse the `From` header (an email address).
+pub(crate) fn parse_from(value: &str) -> Result<HeaderValue, String> {
+    match value.trim() {
+        from if from.contains('@') => {
+            HeaderValue::from_str(from).map_err(|_| format!("invalid email address `{}`", value))
+        }
+        _ => Err(format!("invalid email address `{}`", value)),
+    }
+}
+
+/// Parse a rewrite rule (`<from>=><to>`).
+pub(crate) fn parse_rewrite(value: &str) -> Result<(String, String), String> {
+    match value.split_once("=>") {
+        Some((from, to)) if !from.is_empty() && !to.is_empty() => {
+            Ok((from.to_string(), to.to_string()))
+        }
+        _ => Err(format!("invalid rewrite `{}`, expected `<from>=><to>`", value)),
+    }
+}
+
+/// Parse a size with an optional unit suffix (K, M or G, powers of 1024).
+pub(crate) fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let (number, multiplier) = match value.chars().last().map(|x| x.to_ascii_uppercase()) {
+        Some('K') => (&value[..value.len() - 1], 1024),
+        Some('M') => (&value[..value.len() - 1], 1024 * 1024),
+        Some('G') => (&value[..value.len() - 1], 1024 * 1024 * 1024),
+        _ => (value, 1),
+    };
+
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|x| x.checked_mul(multiplier))
+        .ok_or_else(|| format!("invalid size `{}`", value))
+}
+
+/// Parse a duration with a unit suffix (`ms`, `s`, `m`, `h` or `d`), e.g. `30s` or `5m`.
+pub(crate) fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value.find(|x: char| !x.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number = number
+        .parse::<u64>()
+        .map_err(|_| format!("invalid duration `{}`", value))?;
+    let seconds = |multiplier: u64| {
+        number
+            .checked_mul(multiplier)
+            .map(Duration::from_secs)
+            .ok_or_else(|| format!("invalid duration `{}`, too long", value))
+    };
+
+    match unit.trim() {
+        "ms" => Ok(Duration::from_millis(number)),
+        "s" => seconds(1),
+        "m" => seconds(60),
+        "h" => seconds(3600),
+        "d" => seconds(86400),
+        "y" => seconds(365 * 86400),
+        _ => Err(format!("invalid duration `{}`, expected e.g. `30s` or `5m`", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("500"), Ok(500));
+        assert_eq!(parse_size("500K"), Ok(500 * 1024));
+        assert_eq!(parse_size(" 10m "), Ok(10 * 1024 * 1024));
+        assert_eq!(parse_size("2G"), Ok(2 * 1024 * 1024 * 1024));
+        assert!(parse_size("10T").is_err());
+        assert!(parse_size("-1K").is_err());
+        assert!(parse_size("99999999999999G").is_err());
+    }
+
+    #[test]
+    fn statuses() {
+        let statuses = parse_statuses("200-299, 401,403").unwrap();
+
+        assert!(statuses.contains(200) && statuses.contains(299));
+        assert!(statuses.contains(401) && statuses.contains(403));
+        assert!(!statuses.contains(300) && !statuses.contains(402));
+
+        let classes = parse_statuses("2xx,5XX").unwrap();
+
+        assert_eq!(classes.0, [(200, 299), (500, 599)]);
+        assert_eq!(parse_statuses("500-599").unwrap().0, [(500, 599)]);
+        assert_eq!(parse_statuses("404,").unwrap().0, [(404, 404)]);
+
+        for invalid in ["abc", "4x", "40xx", "299-200", "200-", "600", "0-99"] {
+            assert!(parse_statuses(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("14d"), Ok(Duration::from_secs(14 * 86400)));
+        assert_eq!(parse_duration("2y"), Ok(Duration::from_secs(2 * 365 * 86400)));
+        assert!(parse_duration("5").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("5w").is_err());
+        assert!(parse_duration("99999999999999999y").is_err());
+        assert!(parse_duration("99999999999999999999s").is_err());
+    }
+}
@@ -0,0 +1,126 @@
+use std::io::Read;
+use std::sync::OnceLock;
+
+use flate2::read::ZlibDecoder;
+use regex::bytes::Regex;
+
+use crate::extract::{Link, Resource};
+
+/// The maximum size of a decompressed stream, so a small document cannot exhaust the memory.
+const MAX_STREAM_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Extract the links of the URI annotations (`/URI (https://...)`) of a PDF document, kept as
+/// Latin-1 text (one char per byte), including those of compressed (object) streams.
+pub fn links(body: &str) -> Vec<Link> {
+    static STREAM: OnceLock<Regex> = OnceLock::new();
+
+    let stream = STREAM.get_or_init(|| Regex::new(r"(?s-u)stream\r?\n(.*?)endstream").unwrap());
+    let bytes: Vec<u8> = body.chars().map(|x| x as u32 as u8).collect();
+    let mut found = uris(&bytes);
+
+    for captures in stream.captures_iter(&bytes) {
+        let mut decoded = vec![];
+
+        // Streams that are not compressed (or not with zlib) do not decode: they have already
+        // been scanned as they are.
+        let _ = ZlibDecoder::new(&captures[1])
+            .take(MAX_STREAM_SIZE)
+            .read_to_end(&mut decoded);
+
+        found.extend(uris(&decoded));
+    }
+
+    found.sort();
+    found.dedup();
+    found
+        .into_iter()
+        .map(|href| Link {
+            href,
+            resource: Resource::Page,
+            nofollow: false,
+            text: String::new(),
+            rel: String::new(),
+        })
+        .collect()
+}
+
+/// Get the URIs of the URI actions found in some (decompressed) PDF content.
+fn uris(content: &[u8]) -> Vec<String> {
+    static URI: OnceLock<Regex> = OnceLock::new();
+
+    let uri = URI.get_or_init(|| Regex::new(r"(?-u)/URI\s*([(<])").unwrap());
+
+    uri.captures_iter(content)
+        .filter_map(|x| {
+            let start = x.get(1)?.end();
+
+            match &x[1] {
+                b"(" => literal_string(&content[start..]),
+                _ => hex_string(&content[start..]),
+            }
+        })
+        .map(|x| String::from_utf8_lossy(&x).trim().to_string())
+        .filter(|x| !x.is_empty())
+        .collect()
+}
+
+/// Decode a literal string (after its opening parenthesis): escapes and balanced parentheses.
+fn literal_string(content: &[u8]) -> Option<Vec<u8>> {
+    let mut value = vec![];
+    let mut depth = 0;
+    let mut bytes = content.iter().copied();
+
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'\\' => match bytes.next()? {
+                b'n' => value.push(b'\n'),
+                b'r' => value.push(b'\r'),
+                b't' => value.push(b'\t'),
+                // An escaped end of line continues the string.
+                b'\r' | b'\n' => {}
+                x @ b'0'..=b'7' => {
+                    let mut code = (x - b'0') as u32;
+                    let mut digits = bytes.clone();
+
+                    for _ in 0..2 {
+                        match digits.next() {
+                            Some(x @ b'0'..=b'7') => {
+                                code = code * 8 + (x - b'0') as u32;
+                                bytes.next();
+                            }
+                            _ => break,
+                        }
+                    }
+
+                    value.push(code as u8);
+                }
+                x => value.push(x),
+            },
+            b'(' => {
+                depth += 1;
+                value.push(byte);
+            }
+            b')' if depth == 0 => return Some(value),
+            b')' => {
+                depth -= 1;
+                value.push(byte);
+            }
+            _ => value.push(byte),
+        }
+    }
+
+    None
+}
+
+/// Decode a hexadecimal string (after its opening `<`), whitespace ignored.
+fn hex_string(content: &[u8]) -> Option<Vec<u8>> {
+    let end = content.iter().position(|x| *x == b'>')?;
+    let digits: Vec<u8> = content[..end]
+        .iter()
+        .filter(|x| !x.is_ascii_whitespace())
+        .filter_map(|x| (*x as char).to_digit(16).map(|x| x as u8))
+        .collect();
+
+    // A missing final digit is a zero.
+    Some(digits.chunks(2).map(|x| x[0] << 4 | x.get(1).copied().unwrap_or(0)).collect())
+}
@@ -0,0 +1,242 @@
+use std::error::Error;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// An event sent to plugins, as a single line of JSON.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    /// An URL is about to be queued. Plugins can veto it or add other URLs.
+    Queue { url: &'a str },
+
+    /// A response has been received. Plugins can annotate the URL in the report.
+    Response {
+        url: &'a str,
+        status: u16,
+        redirects: &'a [String],
+    },
+
+    /// The crawl is finished. No reply is expected.
+    Finished,
+}
+
+/// A plugin reply to an event, as a single line of JSON. Every field is optional, so `{}` is a
+/// valid reply.
+#[derive(Debug, Default, Deserialize)]
+pub struct Reply {
+    /// Do not queue the URL.
+    #[serde(default)]
+    pub veto: bool,
+
+    /// Additional URLs to queue.
+    #[serde(default)]
+    pub urls: Vec<String>,
+
+    /// Notes to attach to the URL in the report.
+    #[serde(default)]
+    pub annotations: Vec<String>,
+}
+
+impl Reply {
+    /// Merge another reply into this one.
+    fn merge(&mut self, other: Reply) {
+        self.veto |= other.veto;
+        self.urls.extend(other.urls);
+        self.annotations.extend(other.annotations);
+    }
+}
+
+/// An external command receiving events on stdin and replying on stdout.
+///
+/// Its replies are read by a thread of their own, so a plugin not replying in time can be
+/// killed instead of blocking the crawl.
+#[derive(Debug)]
+struct Plugin {
+    command: String,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    timeout: Duration,
+}
+
+impl Plugin {
+    /// Start the given command through the shell.
+    fn spawn(command: &str, timeout: Duration) -> Result<Self, Box<dyn Error>> {
+        let mut child = Command::new("sh");
+
+        child.arg("-c").arg(command).stdin(Stdio::piped()).stdout(Stdio::piped());
+
+        // In a process group of its own, the processes the shell starts being killed with it.
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut child, 0);
+
+        let mut child = child.spawn()?;
+
+        let stdin = child.stdin.take().ok_or("cannot open plugin stdin")?;
+        let stdout = child.stdout.take().ok_or("cannot open plugin stdout")?;
+        let (sender, lines) = mpsc::channel();
+
+        // The thread ends with the plugin stdout, the reader going away first or not.
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            command: command.to_string(),
+            child,
+            stdin,
+            lines,
+            timeout,
+        })
+    }
+
+    /// Send an event and wait for the reply, at most for the plugin timeout.
+    fn send(&mut self, event: &Event) -> Result<Reply, Box<dyn Error>> {
+        writeln!(self.stdin, "{}", serde_json::to_string(event)?)?;
+        self.stdin.flush()?;
+
+        match self.lines.recv_timeout(self.timeout) {
+            Ok(line) => Ok(serde_json::from_str(&line)?),
+            Err(RecvTimeoutError::Timeout) => {
+                Err(format!("no reply within {:?}", self.timeout).into())
+            }
+            Err(RecvTimeoutError::Disconnected) => Err("plugin closed its stdout".into()),
+        }
+    }
+
+    /// Send the final event and wait for the plugin to exit, killing it after the plugin
+    /// timeout.
+    fn finish(mut self) -> Result<(), Box<dyn Error>> {
+        writeln!(self.stdin, "{}", serde_json::to_string(&Event::Finished)?)?;
+        drop(self.stdin);
+
+        let started = Instant::now();
+
+        while self.child.try_wait()?.is_none() {
+            if started.elapsed() >= self.timeout {
+                kill(&mut self.child)?;
+                self.child.wait()?;
+                return Err(format!("still running after {:?}, killed", self.timeout).into());
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        Ok(())
+    }
+
+    /// Stop a failing plugin for good.
+    fn kill(mut self) {
+        let _ = kill(&mut self.child);
+        let _ = self.child.wait();
+    }
+}
+
+/// Kill a plugin and the processes it started.
+#[cfg(unix)]
+fn kill(child: &mut Child) -> std::io::Result<()> {
+    match unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) } {
+        0 => Ok(()),
+        _ => Err(std::io::Error::last_os_error()),
+    }
+}
+
+#[cfg(not(unix))]
+fn kill(child: &mut Child) -> std::io::Result<()> {
+    child.kill()
+}
+
+/// Every running plugin.
+#[derive(Debug, Default)]
+pub struct Plugins {
+    plugins: Vec<Plugin>,
+}
+
+impl Plugins {
+    /// Start every given command, each one having to reply to an event within the timeout.
+    pub fn spawn(commands: &[String], timeout: Duration) -> Result<Self, Box<dyn Error>> {
+        let plugins = commands
+            .iter()
+            .map(|x| Plugin::spawn(x, timeout))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { plugins })
+    }
+
+    /// Send an event to every plugin, merging their replies. An invalid reply is logged and
+    /// ignored, while a plugin not replying in time (or exiting) is logged and stopped, the
+    /// crawl going on without it.
+    pub fn dispatch(&mut self, event: &Event) -> Reply {
+        let mut reply = Reply::default();
+        let mut index = 0;
+
+        while index < self.plugins.len() {
+            let plugin = &mut self.plugins[index];
+
+            match plugin.send(event) {
+                Ok(x) => reply.merge(x),
+                Err(e) if e.is::<serde_json::Error>() => {
+                    error!("Plugin `{}` failed: {}", plugin.command, e)
+                }
+                Err(e) => {
+                    error!("Plugin `{}` failed: {}, disabling it", plugin.command, e);
+                    self.plugins.remove(index).kill();
+                    continue;
+                }
+            }
+
+            index += 1;
+        }
+
+        reply
+    }
+
+    /// Notify every plugin that the crawl is finished.
+    pub fn finish(&mut self) {
+        for plugin in self.plugins.drain(..) {
+            let command = plugin.command.clone();
+
+            if let Err(e) = plugin.finish() {
+                error!("Plugin `{}` failed: {}", command, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dispatch() {
+        let command = r#"while read line; do echo '{"annotations": ["seen"]}'; done"#;
+        let mut plugins = Plugins::spawn(&[command.to_string()], Duration::from_secs(5)).unwrap();
+        let reply = plugins.dispatch(&Event::Queue { url: "http://example.com/" });
+
+        assert_eq!(reply.annotations, ["seen"]);
+        plugins.finish();
+    }
+
+    #[test]
+    fn slow_plugins_are_stopped() {
+        let commands = ["sleep 10".to_string(), "while read line; do echo '{}'; done".to_string()];
+        let mut plugins = Plugins::spawn(&commands, Duration::from_millis(200)).unwrap();
+        let started = Instant::now();
+
+        plugins.dispatch(&Event::Queue { url: "http://example.com/" });
+        assert_eq!(plugins.plugins.len(), 1);
+
+        plugins.dispatch(&Event::Queue { url: "http://example.com/" });
+        plugins.finish();
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::time::Duration;
+
+use reqwest::header::{HeaderName, HeaderValue, AUTHORIZATION};
+use reqwest::Request;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Built-in policies, shipped with the binary.
+const BUILTIN: &str = include_str!("hosts.toml");
+
+/// How to behave with a given host (and its subdomains).
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HostPolicy {
+    pub domain: String,
+
+    /// Minimal delay between two requests to this host, in milliseconds.
+    #[serde(default)]
+    pub delay_ms: u64,
+
+    /// Statuses to report as warnings instead of failures.
+    #[serde(default)]
+    pub warn: Vec<u16>,
+
+    /// Proxy to send requests to this host through (`http://`, `https://` or `socks5://`), or
+    /// `direct` to bypass any other proxy.
+    pub proxy: Option<String>,
+
+    /// Client certificate (PEM) to present to this host (mTLS), with its `client_key`.
+    pub client_cert: Option<String>,
+
+    /// Private key (PEM) of the client certificate.
+    pub client_key: Option<String>,
+
+    /// Accept invalid TLS certificates from this host (e.g. a self-signed staging).
+    #[serde(default)]
+    pub insecure: bool,
+
+    /// Headers to send to this host (e.g. a staging access token).
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+
+    /// Basic authentication credentials (`user:pass`) to send to this host.
+    pub auth: Option<String>,
+
+    /// Bearer token to send to this host.
+    pub bearer: Option<String>,
+}
+
+impl HostPolicy {
+    /// Get the minimal delay between two requests.
+    pub fn delay(&self) -> Duration {
+        Duration::from_millis(self.delay_ms)
+    }
+
+    /// Add the headers (and credentials) of this host to a request.
+    pub fn apply(&self, request: &mut Request) -> Result<(), Box<dyn Error>> {
+        for (name, value) in &self.headers {
+            request
+                .headers_mut()
+                .insert(HeaderName::from_bytes(name.as_bytes())?, HeaderValue::from_str(value)?);
+        }
+
+        let authorization = match (&self.auth, &self.bearer) {
+            (Some(credentials), _) => format!("Basic {}", base64::encode(credentials)),
+            (_, Some(token)) => format!("Bearer {}", token),
+            _ => return Ok(()),
+        };
+
+        request
+            .headers_mut()
+            .insert(AUTHORIZATION, HeaderValue::from_str(&authorization)?);
+
+        Ok(())
+    }
+}
+
+/// Policies are read as tables first, to merge them with the ones of the same domain.
+#[derive(Debug, Deserialize)]
+struct PolicyFile {
+    #[serde(default)]
+    host: Vec<toml::Table>,
+}
+
+/// The list of known host policies.
+#[derive(Debug, Default)]
+pub struct HostPolicies {
+    policies: Vec<HostPolicy>,
+}
+
+impl HostPolicies {
+    /// Load the built-in policies, extended (or overridden) by the given files.
+    pub fn load(paths: &[String]) -> Result<Self, Box<dyn Error>> {
+        let mut policies = Self::default();
+
+        policies.extend(BUILTIN)?;
+
+        for path in paths {
+            policies.extend(&fs::read_to_string(path)?)?;
+        }
+
+        Ok(policies)
+    }
+
+    /// Parse a TOML policy list. A policy for an already known domain is merged into the previous
+    /// one: the fields it sets are replaced (headers one by one), the others kept.
+    fn extend(&mut self, content: &str) -> Result<(), Box<dyn Error>> {
+        let file: PolicyFile = toml::from_str(content)?;
+
+        for table in file.host {
+            let policy: HostPolicy = table.clone().try_into()?;
+            let domain = &policy.domain;
+
+            match self.policies.iter().position(|x| x.domain.eq_ignore_ascii_case(domain)) {
+                Some(position) => {
+                    let mut merged = toml::Table::try_from(&self.policies[position])?;
+
+                    merge(&mut merged, table);
+                    self.policies[position] = merged.try_into()?;
+                }
+                None => self.policies.push(policy),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Find the policy of the given URL host, preferring the most specific domain.
+    pub fn find(&self, url: &Url) -> Option<&HostPolicy> {
+        let domain = url.domain()?;
+
+        self.policies
+            .iter()
+            .filter(|x| crate::is_domain_or_subdomain(domain, &x.domain))
+            .max_by_key(|x| x.domain.len())
+    }
+}
+
+/// Override the fields of a policy with the ones of another, merging their headers.
+fn merge(policy: &mut toml::Table, other: toml::Table) {
+    for (key, value) in other {
+        match (policy.get_mut(&key), value) {
+            (Some(toml::Value::Table(headers)), toml::Value::Table(other)) => headers.extend(other),
+            (_, value) => {
+                policy.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_known_domain() {
+        let mut policies = HostPolicies::default();
+
+        policies.extend(BUILTIN).unwrap();
+        policies
+            .extend("[[host]]\ndomain = 'GitHub.com'\nheaders = { X-Token = 'secret' }")
+            .unwrap();
+
+        let policy = policies.find(&Url::parse("https://github.com/").unwrap()).unwrap();
+
+        assert_eq!(policy.delay_ms, 1000);
+        assert_eq!(policy.warn, vec![429]);
+        assert_eq!(policy.headers["X-Token"], "secret");
+        assert_eq!(policies.policies.iter().filter(|x| x.domain == "GitHub.com").count(), 1);
+    }
+
+    #[test]
+    fn merge_headers_one_by_one() {
+        let mut policies = HostPolicies::default();
+
+        let first = "[[host]]\ndomain = 'a.test'\ndelay_ms = 500\nheaders = { A = '1', B = '2' }";
+        let second = "[[host]]\ndomain = 'a.test'\ndelay_ms = 0\nheaders = { B = '3' }";
+
+        policies.extend(first).unwrap();
+        policies.extend(second).unwrap();
+
+        let policy = policies.find(&Url::parse("https://a.test/").unwrap()).unwrap();
+
+        assert_eq!(policy.delay_ms, 0);
+        assert_eq!(policy.headers["A"], "1");
+        assert_eq!(policy.headers["B"], "3");
+    }
+}
@@ -0,0 +1,75 @@
+use reqwest::header::{HeaderMap, AGE};
+use serde::{Deserialize, Serialize};
+
+use crate::report::Entry;
+
+/// The headers telling whether a response has been served by a cache (Varnish, Fastly,
+/// CloudFront, Cloudflare, nginx...).
+const STATUS_HEADERS: &[&str] = &["cf-cache-status", "x-cache-status", "x-cache", "x-proxy-cache"];
+
+/// Whether a response has been served by a cache, according to its headers.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheStatus {
+    Hit,
+    Miss,
+
+    /// Not cacheable, or the cache has been bypassed (e.g. because of a cookie).
+    Bypass,
+}
+
+/// Read the cache status of a response: the first cache header given, or a positive `Age`.
+pub fn cache_status(headers: &HeaderMap) -> Option<CacheStatus> {
+    for name in STATUS_HEADERS {
+        let value = match headers.get(*name).and_then(|x| x.to_str().ok()) {
+            Some(value) => value.to_uppercase(),
+            None => continue,
+        };
+
+        // `X-Cache: HIT, MISS` lists every layer, the edge first.
+        return match value.split(',').next().unwrap_or_default().trim() {
+            x if x.contains("HIT") => Some(CacheStatus::Hit),
+            x if x.contains("MISS") || x == "EXPIRED" || x == "STALE" => Some(CacheStatus::Miss),
+            _ => Some(CacheStatus::Bypass),
+        };
+    }
+
+    let age = headers.get(AGE).and_then(|x| x.to_str().ok()?.trim().parse::<u64>().ok());
+
+    match age {
+        Some(0) => Some(CacheStatus::Miss),
+        Some(_) => Some(CacheStatus::Hit),
+        None => None,
+    }
+}
+
+/// The cache hits and misses of a --prime-cache crawl, over its pages.
+#[derive(Debug, Default, Serialize)]
+pub struct Summary {
+    pub pages: usize,
+    pub hits: usize,
+    pub misses: usize,
+    pub bypassed: usize,
+
+    /// The pages answering without any cache header.
+    pub unknown: usize,
+}
+
+impl Summary {
+    pub fn new(results: &[Entry]) -> Self {
+        let mut summary = Self::default();
+
+        for entry in results.iter().filter(|x| x.status != 0) {
+            summary.pages += 1;
+
+            match entry.cache_status {
+                Some(CacheStatus::Hit) => summary.hits += 1,
+                Some(CacheStatus::Miss) => summary.misses += 1,
+                Some(CacheStatus::Bypass) => summary.bypassed += 1,
+                None => summary.unknown += 1,
+            }
+        }
+
+        summary
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use futures::{Stream, StreamExt};
+
+use crate::events::CrawlEvent;
+
+/// How often the progress line is redrawn, at most.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How far back the queue drain rate is measured, for the ETA.
+const DRAIN_WINDOW: Duration = Duration::from_secs(10);
+
+/// Whether the progress line is drawn, log records clearing it first.
+pub static SHOWN: AtomicBool = AtomicBool::new(false);
+
+/// The progress of a crawl, drawn on a single line of stderr.
+#[derive(Debug)]
+struct Progress {
+    started: Instant,
+    drawn_at: Option<Instant>,
+    queued: usize,
+    crawled: usize,
+    errors: usize,
+
+    /// The number of remaining URLs over the drain window, oldest first.
+    samples: VecDeque<(Instant, usize)>,
+}
+
+impl Progress {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            drawn_at: None,
+            queued: 0,
+            crawled: 0,
+            errors: 0,
+            samples: VecDeque::new(),
+        }
+    }
+
+    fn update(&mut self, event: &CrawlEvent) {
+        match event {
+            CrawlEvent::Queued { .. } => self.queued += 1,
+            CrawlEvent::Fetched { .. } => self.crawled += 1,
+            CrawlEvent::Failed { .. } => {
+                self.crawled += 1;
+                self.errors += 1;
+            }
+            CrawlEvent::Finished => {}
+        }
+    }
+
+    /// Estimate the remaining time, from how fast the queue drained lately.
+    fn eta(&mut self, now: Instant) -> Option<Duration> {
+        let remaining = self.queued.saturating_sub(self.crawled);
+
+        self.samples.push_back((now, remaining));
+
+        while self.samples.front().is_some_and(|(x, _)| now - *x > DRAIN_WINDOW) {
+            self.samples.pop_front();
+        }
+
+        let (since, before) = *self.samples.front()?;
+        let elapsed = (now - since).as_secs_f64();
+        let drained = before.saturating_sub(remaining) as f64;
+
+        if elapsed < 1.0 || drained == 0.0 {
+            return None;
+        }
+
+        Some(Duration::from_secs_f64(remaining as f64 * elapsed / drained))
+    }
+
+    fn draw(&mut self) {
+        let now = Instant::now();
+
+        if self.drawn_at.is_some_and(|x| now - x < REDRAW_INTERVAL) {
+            return;
+        }
+
+        let rate = self.crawled as f64 / (now - self.started).as_secs_f64().max(0.001);
+        let eta = match self.eta(now) {
+            Some(eta) => format!("{}s", eta.as_secs()),
+            None => "--".to_string(),
+        };
+
+        eprint!(
+            "\r\x1b[K{} crawled, {} queued, {} errors, {:.1} req/s, ETA {}",
+            self.crawled,
+            self.queued.saturating_sub(self.crawled),
+            self.errors,
+            rate,
+            eta
+        );
+        let _ = std::io::stderr().flush();
+        self.drawn_at = Some(now);
+    }
+}
+
+/// Draw the progress of a crawl until it is finished, then clear it.
+pub async fn show(events: impl Stream<Item = CrawlEvent>) {
+    let mut progress = Progress::new();
+
+    SHOWN.store(true, Ordering::Relaxed);
+    futures::pin_mut!(events);
+
+    while let Some(event) = events.next().await {
+        progress.update(&event);
+
+        if let CrawlEvent::Finished = event {
+            break;
+        }
+
+        progress.draw();
+    }
+
+    SHOWN.store(false, Ordering::Relaxed);
+    eprint!("\r\x1b[K");
+}
@@ -0,0 +1,91 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs::File;
+
+use serde::Serialize;
+
+/// Maximum number of redirects followed for a single URL.
+pub const MAX_REDIRECTS: usize = 10;
+
+/// A suggested rewrite: links to `from` should directly point to `to`.
+#[derive(Debug, Serialize)]
+pub struct Rewrite {
+    pub from: String,
+    pub to: String,
+    pub hops: usize,
+}
+
+/// Follow the redirect graph from the given URL, returning the final destination and the number
+/// of hops. Loops stop on the last URL seen before looping back.
+fn resolve<'a>(edges: &'a HashMap<String, String>, url: &'a str) -> (&'a str, usize) {
+    let mut current = url;
+    let mut hops = 0;
+
+    while let Some(next) = edges.get(current) {
+        if next == url || hops > edges.len() {
+            break;
+        }
+
+        current = next;
+        hops += 1;
+    }
+
+    (current, hops)
+}
+
+/// Group redirecting URLs by final destination.
+fn clusters(edges: &HashMap<String, String>) -> BTreeMap<&str, Vec<(&str, usize)>> {
+    let mut clusters: BTreeMap<&str, Vec<(&str, usize)>> = BTreeMap::new();
+
+    for from in edges.keys() {
+        let (to, hops) = resolve(edges, from);
+
+        if to != from {
+            clusters.entry(to).or_default().push((from, hops));
+        }
+    }
+
+    clusters
+}
+
+/// Build the rewrites of the given clusters: every URL to its final destination.
+fn cluster_rewrites<'a>(
+    clusters: impl Iterator<Item = (&'a str, Vec<(&'a str, usize)>)>,
+) -> Vec<Rewrite> {
+    let mut rewrites = vec![];
+
+    for (to, mut sources) in clusters {
+        sources.sort();
+        rewrites.extend(sources.into_iter().map(|(from, hops)| Rewrite {
+            from: from.to_string(),
+            to: to.to_string(),
+            hops,
+        }));
+    }
+
+    rewrites
+}
+
+/// Find clusters of URLs funneling into the same destination through redirects.
+///
+/// Every URL of a cluster with at least two members produces a rewrite to the final destination,
+/// so all links pointing to them can be updated at once.
+pub fn rewrites(edges: &HashMap<String, String>) -> Vec<Rewrite> {
+    cluster_rewrites(clusters(edges).into_iter().filter(|(_, x)| x.len() > 1))
+}
+
+/// List every redirecting URL with its final destination, so links can skip the redirects.
+/// Redirect loops (destinations redirecting themselves) have no shortcut.
+pub fn shortcuts(edges: &HashMap<String, String>) -> Vec<Rewrite> {
+    cluster_rewrites(
+        clusters(edges)
+            .into_iter()
+            .filter(|(to, _)| !edges.contains_key(*to)),
+    )
+}
+
+/// Write the rewrites as JSON into the given file.
+pub fn write_json(rewrites: &[Rewrite], path: &str) -> Result<(), Box<dyn Error>> {
+    serde_json::to_writer_pretty(File::create(path)?, rewrites)?;
+    Ok(())
+}
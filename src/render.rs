@@ -0,0 +1,902 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::Semaphore;
+use url::Url;
+
+use crate::cookies::Cookie;
+
+/// How long pages can run their scripts once loaded, before their DOM is taken.
+const BUDGET: Duration = Duration::from_secs(5);
+
+/// How long a loaded page can stay quiet (no event from the browser) before being taken.
+const IDLE: Duration = Duration::from_millis(500);
+
+/// How long the browser can take to answer a command, or to load a page.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Get the performance timings of the page, in milliseconds since the navigation started, and
+/// the bytes transferred for the page and its resources.
+const TIMINGS: &str = r#"(() => {
+    const navigation = performance.getEntriesByType("navigation")[0];
+    const paint = performance.getEntriesByName("first-contentful-paint")[0];
+    const resources = performance.getEntriesByType("resource");
+
+    return {
+        fcp: paint && paint.startTime,
+        dom_content_loaded: navigation && navigation.domContentLoadedEventEnd,
+        transfer_size: navigation
+            && resources.reduce((x, y) => x + y.transferSize, navigation.transferSize),
+    };
+})()"#;
+
+/// The callers waiting for an answer (by command ID), or for the events of a page (by session ID).
+type Waiting<K> = Arc<Mutex<HashMap<K, Sender<Value>>>>;
+
+/// How a page has to be requested by the browser, the crawler requesting it the same way.
+#[derive(Clone, Debug, Default)]
+pub struct Navigation {
+    /// The headers sent with every request of the page (`-H`, `From`).
+    pub headers: Vec<(String, String)>,
+
+    /// The headers only sent with the requests to the origin of the page (the ones of its host
+    /// policy, credentials), so they don't leak to third parties.
+    pub origin_headers: Vec<(String, String)>,
+
+    /// The cookies of the crawl, the browser sending each one where it belongs.
+    pub cookies: Vec<Cookie>,
+}
+
+impl Navigation {
+    /// Get the cookies, as `Network.setCookies` parameters. Host-only cookies are given by URL
+    /// rather than by domain, for the browser to keep them from the subdomains.
+    fn cookie_params(&self) -> Vec<Value> {
+        self.cookies
+            .iter()
+            .map(|x| {
+                let mut cookie = json!({
+                    "name": x.name,
+                    "value": x.value,
+                    "path": x.path,
+                    "secure": x.secure,
+                });
+
+                let scheme = if x.secure { "https" } else { "http" };
+
+                match x.include_subdomains {
+                    true => cookie["domain"] = json!(format!(".{}", x.domain)),
+                    false => cookie["url"] = json!(format!("{}://{}{}", scheme, x.domain, x.path)),
+                }
+
+                if x.expires > 0 {
+                    cookie["expires"] = json!(x.expires);
+                }
+
+                cookie
+            })
+            .collect()
+    }
+
+    /// Get the headers of a request paused by the browser, completed with the origin ones, as
+    /// `Fetch.continueRequest` parameters.
+    fn continued_headers(&self, request: &Value) -> Vec<Value> {
+        let overridden = |name: &str| {
+            self.origin_headers.iter().any(|(x, _)| x.eq_ignore_ascii_case(name))
+        };
+        let headers = request["headers"].as_object().into_iter().flatten();
+        let origin_headers = self.origin_headers.iter().map(|(name, value)| (name, json!(value)));
+
+        headers
+            .filter(|(name, _)| !overridden(name))
+            .map(|(name, value)| (name, value.clone()))
+            .chain(origin_headers)
+            .map(|(name, value)| json!({ "name": name, "value": value }))
+            .collect()
+    }
+}
+
+/// A page rendered by the browser.
+#[derive(Debug, Default)]
+pub struct Rendered {
+    /// The DOM of the page, once its scripts have run.
+    pub html: String,
+
+    /// The errors logged to the console by the page, its uncaught exceptions included.
+    pub console_errors: Vec<String>,
+
+    /// The XHR and fetch requests of the page which failed (network error or error status).
+    pub failed_requests: Vec<String>,
+
+    /// The performance timings of the page, measured by the browser.
+    pub timings: Timings,
+
+    /// A screenshot of the page (PNG), if asked for.
+    pub screenshot: Option<Vec<u8>>,
+
+    /// The XHR and fetch requests sent by the page (`GET <url>`), by request ID.
+    requests: HashMap<String, String>,
+}
+
+/// The performance timings of a rendered page.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Timings {
+    /// When the first text or image has been painted (first contentful paint), in milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fcp_ms: Option<u64>,
+
+    /// When the `DOMContentLoaded` event handlers have run, in milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dom_content_loaded_ms: Option<u64>,
+
+    /// The bytes transferred for the page and its resources, headers included (cached
+    /// resources transferring nothing).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transfer_size: Option<u64>,
+}
+
+impl Timings {
+    /// Read the timings from the result of the `TIMINGS` script.
+    fn from_value(value: &Value) -> Self {
+        let number = |x: &Value| x.as_f64().filter(|x| *x > 0.0).map(|x| x.round() as u64);
+
+        Self {
+            fcp_ms: number(&value["fcp"]),
+            dom_content_loaded_ms: number(&value["dom_content_loaded"]),
+            transfer_size: number(&value["transfer_size"]),
+        }
+    }
+}
+
+impl Rendered {
+    /// Collect the console errors and the failed requests from an event of the page.
+    fn on_event(&mut self, event: &Value) {
+        let params = &event["params"];
+        let text = |x: &Value| x.as_str().unwrap_or_default().to_string();
+
+        match event["method"].as_str().unwrap_or_default() {
+            "Runtime.exceptionThrown" => {
+                let details = &params["exceptionDetails"];
+                let description = details["exception"]["description"].as_str();
+                let description = description.or_else(|| details["text"].as_str());
+
+                // The stack trace is left out.
+                let message = description.and_then(|x| x.lines().next());
+                self.console_errors.push(message.unwrap_or("Uncaught exception").to_string());
+            }
+            "Runtime.consoleAPICalled" if params["type"] == "error" => {
+                let args: Vec<String> = params["args"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|x| match &x["value"] {
+                        Value::String(value) => value.clone(),
+                        Value::Null => text(&x["description"]),
+                        value => value.to_string(),
+                    })
+                    .collect();
+
+                self.console_errors.push(args.join(" "));
+            }
+            // Resources failing to load are logged too, but only XHR and fetch matter here.
+            "Log.entryAdded"
+                if params["entry"]["level"] == "error" && params["entry"]["source"] != "network" =>
+            {
+                self.console_errors.push(text(&params["entry"]["text"]));
+            }
+            "Network.requestWillBeSent" if params["type"] == "XHR" || params["type"] == "Fetch" => {
+                let request = &params["request"];
+
+                self.requests.insert(
+                    text(&params["requestId"]),
+                    format!("{} {}", text(&request["method"]), text(&request["url"])),
+                );
+            }
+            "Network.responseReceived" => {
+                let status = params["response"]["status"].as_u64().unwrap_or_default();
+
+                if status >= 400 {
+                    if let Some(request) = self.requests.remove(&text(&params["requestId"])) {
+                        self.failed_requests.push(format!("{} ({})", request, status));
+                    }
+                }
+            }
+            "Network.loadingFailed" if params["canceled"] != true => {
+                if let Some(request) = self.requests.remove(&text(&params["requestId"])) {
+                    let error = text(&params["errorText"]);
+
+                    self.failed_requests.push(format!("{} ({})", request, error));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A headless Chromium driven over the DevTools protocol, through the pipes of
+/// `--remote-debugging-pipe`: JSON messages separated by NUL bytes.
+#[derive(Debug)]
+struct Browser {
+    child: Child,
+    commands: Mutex<File>,
+    ids: AtomicU64,
+    answers: Waiting<u64>,
+    sessions: Waiting<String>,
+
+    /// Whether the browser is still running, its messages pipe being open.
+    alive: Arc<AtomicBool>,
+}
+
+impl Browser {
+    /// Start the browser, and the thread dispatching its messages.
+    fn launch(
+        browser: &str,
+        user_agent: Option<&str>,
+        no_sandbox: bool,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let mut command = Command::new(browser);
+
+        command.args(["--headless", "--disable-gpu", "--remote-debugging-pipe"]);
+
+        // Only for containers where the sandbox cannot run (e.g. as root), as rendered pages then
+        // run scripts without any isolation.
+        if no_sandbox {
+            command.arg("--no-sandbox");
+        }
+
+        if let Some(user_agent) = user_agent {
+            command.arg(format!("--user-agent={}", user_agent));
+        }
+
+        command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+
+        let (child, commands, messages) =
+            spawn(command).map_err(|e| format!("cannot run {}: {}", browser, e))?;
+        let answers: Waiting<u64> = Arc::default();
+        let sessions: Waiting<String> = Arc::default();
+
+        let alive = Arc::new(AtomicBool::new(true));
+
+        let (waiting, listening, running) =
+            (Arc::clone(&answers), Arc::clone(&sessions), Arc::clone(&alive));
+        thread::spawn(move || {
+            let mut messages = BufReader::new(messages);
+            let mut message = vec![];
+
+            while messages.read_until(0, &mut message).is_ok_and(|x| x > 0) {
+                let json = message.strip_suffix(b"\0").unwrap_or(&message);
+                let value = serde_json::from_slice::<Value>(json);
+                message.clear();
+
+                let value = match value {
+                    Ok(value) => value,
+                    Err(_) => continue,
+                };
+                let sender = match value["id"].as_u64() {
+                    Some(id) => waiting.lock().unwrap().remove(&id),
+                    None => value["sessionId"]
+                        .as_str()
+                        .and_then(|x| listening.lock().unwrap().get(x).cloned()),
+                };
+
+                if let Some(sender) = sender {
+                    let _ = sender.send(value);
+                }
+            }
+
+            // The browser is gone: the callers still waiting are disconnected.
+            running.store(false, Ordering::SeqCst);
+            waiting.lock().unwrap().clear();
+            listening.lock().unwrap().clear();
+        });
+
+        Ok(Self {
+            child,
+            commands: Mutex::new(commands),
+            ids: AtomicU64::new(1),
+            answers,
+            sessions,
+            alive,
+        })
+    }
+
+    /// Check if the browser is still running.
+    fn is_alive(&self) -> bool {
+        self.alive.load(Ordering::SeqCst)
+    }
+
+    /// Send a command (to a page, given its session) and wait for its result.
+    fn call(
+        &self,
+        session: Option<&str>,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let id = self.ids.fetch_add(1, Ordering::SeqCst);
+        let (sender, receiver) = mpsc::channel();
+        let mut message = json!({ "id": id, "method": method, "params": params });
+
+        if let Some(session) = session {
+            message["sessionId"] = json!(session);
+        }
+
+        self.answers.lock().unwrap().insert(id, sender);
+
+        // Checked once waiting, so the answers cannot be cleared in between.
+        if !self.is_alive() {
+            self.answers.lock().unwrap().remove(&id);
+            return Err("the browser has exited".into());
+        }
+
+        {
+            let mut commands = self.commands.lock().unwrap();
+
+            commands.write_all(&serde_json::to_vec(&message)?)?;
+            commands.write_all(b"\0")?;
+        }
+
+        let mut answer = receiver.recv_timeout(TIMEOUT).map_err(|e| {
+            self.answers.lock().unwrap().remove(&id);
+
+            match e {
+                RecvTimeoutError::Timeout => format!("no answer to {} from the browser", method),
+                RecvTimeoutError::Disconnected => "the browser has exited".to_string(),
+            }
+        })?;
+
+        if let Some(error) = answer.get("error") {
+            let message = error["message"].as_str().unwrap_or_default();
+
+            return Err(format!("{} failed: {}", method, message).into());
+        }
+
+        Ok(answer["result"].take())
+    }
+}
+
+/// A browser context (no cookie nor cache shared with the other ones) and its page, rendering
+/// several URLs in turn until recycled.
+#[derive(Debug)]
+struct Context {
+    browser: Arc<Browser>,
+    id: Value,
+    session: String,
+    events: Receiver<Value>,
+
+    /// How many URLs the page has rendered.
+    pages: usize,
+
+    /// The JavaScript heap size of the page, once the last URL has been rendered, in bytes.
+    heap: u64,
+
+    /// Whether the page has crashed (out of memory, most likely).
+    crashed: bool,
+
+    /// Whether the requests to the origin of the rendered URL are intercepted, to add headers.
+    intercepting: bool,
+}
+
+impl Context {
+    /// Create a context with its page, listening to the events of the page.
+    fn open(browser: Arc<Browser>) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let context = browser.call(None, "Target.createBrowserContext", json!({}))?;
+        let id = context["browserContextId"].clone();
+        let session = (|| {
+            let params = json!({ "url": "about:blank", "browserContextId": id });
+            let target = browser.call(None, "Target.createTarget", params)?;
+
+            let params = json!({ "targetId": target["targetId"], "flatten": true });
+            let session = browser.call(None, "Target.attachToTarget", params)?;
+
+            Ok::<_, Box<dyn Error + Send + Sync>>(
+                session["sessionId"].as_str().ok_or("no session for the page")?.to_string(),
+            )
+        })();
+        let session = match session {
+            Ok(session) => session,
+            Err(e) => {
+                let params = json!({ "browserContextId": id });
+
+                let _ = browser.call(None, "Target.disposeBrowserContext", params);
+                return Err(e);
+            }
+        };
+
+        let (sender, events) = mpsc::channel();
+        browser.sessions.lock().unwrap().insert(session.clone(), sender);
+
+        let context = Self {
+            browser,
+            id,
+            session,
+            events,
+            pages: 0,
+            heap: 0,
+            crashed: false,
+            intercepting: false,
+        };
+
+        for domain in ["Inspector", "Runtime", "Log", "Network", "Page"] {
+            if let Err(e) = context.call(&format!("{}.enable", domain), json!({})) {
+                context.close();
+                return Err(e);
+            }
+        }
+
+        Ok(context)
+    }
+
+    /// Send a command to the page.
+    fn call(&self, method: &str, params: Value) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        self.browser.call(Some(&self.session), method, params)
+    }
+
+    /// Render an URL, listening to the events of the page until its scripts are done.
+    fn render(
+        &mut self,
+        url: &str,
+        screenshot: bool,
+        navigation: &Navigation,
+    ) -> Result<Rendered, Box<dyn Error + Send + Sync>> {
+        // The events of the previous URL are left out.
+        while self.events.try_recv().is_ok() {}
+
+        self.prepare(url, navigation)?;
+
+        let navigated = self.call("Page.navigate", json!({ "url": url }))?;
+
+        if let Some(error) = navigated["errorText"].as_str() {
+            return Err(error.into());
+        }
+
+        let mut rendered = Rendered::default();
+        let mut deadline = Instant::now() + TIMEOUT;
+        let mut loaded = false;
+
+        // Scripts run until the page is quiet after its load event, within the budget.
+        while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+            match self.events.recv_timeout(if loaded { left.min(IDLE) } else { left }) {
+                Ok(event) if event["method"] == "Page.loadEventFired" => {
+                    loaded = true;
+                    deadline = Instant::now() + BUDGET;
+                }
+                Ok(event) if event["method"] == "Inspector.targetCrashed" => {
+                    self.crashed = true;
+                    return Err("the page has crashed".into());
+                }
+                Ok(event) if event["method"] == "Fetch.requestPaused" => {
+                    let params = json!({
+                        "requestId": event["params"]["requestId"],
+                        "headers": navigation.continued_headers(&event["params"]["request"]),
+                    });
+
+                    self.call("Fetch.continueRequest", params)?;
+                }
+                Ok(event) => rendered.on_event(&event),
+                Err(RecvTimeoutError::Timeout) if loaded => break,
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err("the browser has exited".into());
+                }
+            }
+        }
+
+        if !loaded {
+            return Err("the page did not load in time".into());
+        }
+
+        let params = json!({
+            "expression": "document.documentElement.outerHTML",
+            "returnByValue": true,
+        });
+        let dom = self.call("Runtime.evaluate", params)?;
+
+        rendered.html = dom["result"]["value"].as_str().ok_or("no DOM")?.to_string();
+
+        let params = json!({ "expression": TIMINGS, "returnByValue": true });
+        let timings = self.call("Runtime.evaluate", params)?;
+
+        rendered.timings = Timings::from_value(&timings["result"]["value"]);
+
+        if screenshot {
+            let png = self.call("Page.captureScreenshot", json!({ "format": "png" }))?;
+            let png = png["data"].as_str().ok_or("no screenshot")?;
+
+            rendered.screenshot = Some(base64::decode(png)?);
+        }
+
+        let heap = self.call("Runtime.getHeapUsage", json!({}))?;
+
+        self.heap = heap["totalSize"].as_f64().unwrap_or_default() as u64;
+        self.pages += 1;
+        Ok(rendered)
+    }
+
+    /// Send the headers and the cookies of the crawl with the requests of the page to render.
+    /// The origin headers are added to the requests of its origin, paused for that.
+    fn prepare(
+        &mut self,
+        url: &str,
+        navigation: &Navigation,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let headers: serde_json::Map<String, Value> =
+            navigation.headers.iter().map(|(name, value)| (name.clone(), json!(value))).collect();
+
+        self.call("Network.setExtraHTTPHeaders", json!({ "headers": headers }))?;
+
+        if !navigation.cookies.is_empty() {
+            self.call("Network.setCookies", json!({ "cookies": navigation.cookie_params() }))?;
+        }
+
+        let origin = Url::parse(url)?.origin().ascii_serialization();
+
+        match navigation.origin_headers.is_empty() {
+            true if self.intercepting => {
+                self.call("Fetch.disable", json!({}))?;
+                self.intercepting = false;
+            }
+            true => {}
+            false => {
+                let pattern = json!({
+                    "urlPattern": format!("{}/*", origin),
+                    "requestStage": "Request",
+                });
+
+                self.call("Fetch.enable", json!({ "patterns": [pattern] }))?;
+                self.intercepting = true;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Leave the rendered URL, so its scripts stop before the context is reused. Its requests
+    /// are no longer intercepted, as nothing would let the paused ones go.
+    fn reset(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if self.intercepting {
+            self.call("Fetch.disable", json!({}))?;
+            self.intercepting = false;
+        }
+
+        self.call("Page.navigate", json!({ "url": "about:blank" }))?;
+        Ok(())
+    }
+
+    /// Dispose of the context, closing its page.
+    fn close(self) {
+        self.browser.sessions.lock().unwrap().remove(&self.session);
+
+        let params = json!({ "browserContextId": self.id });
+        let _ = self.browser.call(None, "Target.disposeBrowserContext", params);
+    }
+}
+
+impl Drop for Browser {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Spawn the browser, its fd 3 reading the commands, its fd 4 writing the messages. Return the
+/// writing end of the commands pipe, and the reading end of the messages one.
+#[cfg(unix)]
+fn spawn(mut command: Command) -> std::io::Result<(Child, File, File)> {
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::process::CommandExt;
+
+    let (commands_read, commands) = pipe()?;
+    let (messages, messages_write) = pipe()?;
+    let fds = [commands_read.as_raw_fd(), messages_write.as_raw_fd()];
+
+    unsafe {
+        command.pre_exec(move || {
+            // Moved out of the way first, so a pipe already on the fd 3 or 4 isn't overwritten.
+            for (fd, target) in fds.iter().zip([3, 4]) {
+                let copy = libc::fcntl(*fd, libc::F_DUPFD, 5);
+
+                if copy < 0 || libc::dup2(copy, target) < 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+
+                libc::close(copy);
+            }
+
+            Ok(())
+        });
+    }
+
+    // The ends of the browser are closed once it has them, so its exit is seen as EOF.
+    Ok((command.spawn()?, commands, messages))
+}
+
+#[cfg(not(unix))]
+fn spawn(_: Command) -> std::io::Result<(Child, File, File)> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "--remote-debugging-pipe is only available on Unix",
+    ))
+}
+
+/// Create a pipe (reading end, writing end), not inherited by the other processes.
+#[cfg(unix)]
+fn pipe() -> std::io::Result<(File, File)> {
+    use std::os::unix::io::FromRawFd;
+
+    let mut fds = [0; 2];
+
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    for fd in fds {
+        unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+    }
+
+    Ok(unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) })
+}
+
+/// The browser and its idle contexts, at most one per page rendered at once.
+#[derive(Debug)]
+struct Pool {
+    browser: String,
+    user_agent: Option<String>,
+    no_sandbox: bool,
+
+    /// How many URLs a context renders before being replaced by a new one.
+    recycle_after: usize,
+
+    /// The JavaScript heap size from which a context is replaced by a new one, in bytes.
+    max_memory: u64,
+
+    running: Mutex<Option<Arc<Browser>>>,
+    idle: Mutex<Vec<Context>>,
+}
+
+impl Pool {
+    /// Render an URL with an idle context (or a new one). A crashed page, or browser, is replaced
+    /// and the URL rendered once more.
+    fn render(
+        &self,
+        url: &str,
+        screenshot: bool,
+        navigation: &Navigation,
+    ) -> Result<Rendered, Box<dyn Error + Send + Sync>> {
+        let mut context = self.context()?;
+        let mut rendered = context.render(url, screenshot, navigation);
+
+        if rendered.is_err() && (context.crashed || !context.browser.is_alive()) {
+            match context.crashed {
+                true => warn!("The browser page crashed rendering {}, retrying", url),
+                false => warn!("The browser exited rendering {}, restarting it", url),
+            }
+
+            context.close();
+            context = self.context()?;
+            rendered = context.render(url, screenshot, navigation);
+        }
+
+        match rendered.is_ok() {
+            true => self.release(context),
+            false => context.close(),
+        }
+
+        rendered
+    }
+
+    /// Get an idle context, or open a new one.
+    fn context(&self) -> Result<Context, Box<dyn Error + Send + Sync>> {
+        let browser = self.browser()?;
+        let idle = self.idle.lock().unwrap().pop();
+
+        match idle {
+            Some(context) => Ok(context),
+            None => Context::open(browser),
+        }
+    }
+
+    /// Get the running browser, starting it (again, if it has exited) if needed. The contexts of
+    /// an exited browser are dropped.
+    fn browser(&self) -> Result<Arc<Browser>, Box<dyn Error + Send + Sync>> {
+        let mut running = self.running.lock().unwrap();
+
+        if !running.as_ref().is_some_and(|x| x.is_alive()) {
+            if running.take().is_some() {
+                self.idle.lock().unwrap().clear();
+            }
+
+            let user_agent = self.user_agent.as_deref();
+            let browser = Browser::launch(&self.browser, user_agent, self.no_sandbox)?;
+
+            *running = Some(Arc::new(browser));
+        }
+
+        Ok(Arc::clone(running.as_ref().unwrap()))
+    }
+
+    /// Give a context back, or replace it once it has rendered enough URLs or grown too big.
+    fn release(&self, mut context: Context) {
+        if context.pages >= self.recycle_after || context.heap > self.max_memory {
+            debug!(
+                "Recycling a browser context after {} pages ({} bytes of JavaScript heap)",
+                context.pages, context.heap
+            );
+            context.close();
+            return;
+        }
+
+        match context.reset() {
+            Ok(()) => self.idle.lock().unwrap().push(context),
+            Err(_) => context.close(),
+        }
+    }
+}
+
+/// Render pages with a headless Chromium, so links inserted by scripts can be extracted from
+/// JavaScript applications. The browser is started with the first page, and restarted if it
+/// exits.
+#[derive(Debug)]
+pub struct Renderer {
+    pool: Arc<Pool>,
+
+    /// Limit the number of pages rendered at once, independently of plain HTTP requests.
+    slots: Semaphore,
+}
+
+impl Renderer {
+    pub fn new(
+        browser: &str,
+        concurrency: usize,
+        user_agent: Option<&str>,
+        no_sandbox: bool,
+        recycle_after: usize,
+        max_memory: u64,
+    ) -> Self {
+        let pool = Pool {
+            browser: browser.to_string(),
+            user_agent: user_agent.map(str::to_string),
+            no_sandbox,
+            recycle_after: recycle_after.max(1),
+            max_memory,
+            running: Mutex::new(None),
+            idle: Mutex::new(vec![]),
+        };
+
+        Self {
+            pool: Arc::new(pool),
+            slots: Semaphore::new(concurrency.max(1)),
+        }
+    }
+
+    /// Render a page: get its DOM once its scripts have run, its console errors, its failed
+    /// requests, its performance timings and a screenshot (if asked for).
+    pub async fn render(
+        &self,
+        url: &str,
+        screenshot: bool,
+        navigation: Navigation,
+    ) -> Result<Rendered, Box<dyn Error + Send + Sync>> {
+        let _slot = self.slots.acquire().await?;
+        let pool = Arc::clone(&self.pool);
+        let url = url.to_string();
+
+        tokio::task::spawn_blocking(move || pool.render(&url, screenshot, &navigation)).await?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(method: &str, params: Value) -> Value {
+        json!({ "method": method, "params": params })
+    }
+
+    #[test]
+    fn console_errors() {
+        let mut rendered = Rendered::default();
+
+        rendered.on_event(&event(
+            "Runtime.exceptionThrown",
+            json!({ "exceptionDetails": {
+                "text": "Uncaught",
+                "exception": { "description": "TypeError: x is undefined\n    at app.js:1:5" },
+            }}),
+        ));
+        rendered.on_event(&event(
+            "Runtime.consoleAPICalled",
+            json!({ "type": "error", "args": [{ "value": "failed:" }, { "value": 42 }] }),
+        ));
+        rendered.on_event(&event(
+            "Runtime.consoleAPICalled",
+            json!({ "type": "log", "args": [{ "value": "not an error" }] }),
+        ));
+        rendered.on_event(&event(
+            "Log.entryAdded",
+            json!({ "entry": { "level": "error", "source": "network", "text": "404" } }),
+        ));
+
+        assert_eq!(rendered.console_errors, ["TypeError: x is undefined", "failed: 42"]);
+    }
+
+    #[test]
+    fn failed_requests() {
+        let mut rendered = Rendered::default();
+        let request = |id: &str, kind: &str, url: &str| {
+            json!({ "requestId": id, "type": kind, "request": { "method": "GET", "url": url } })
+        };
+
+        for (id, kind) in [("1", "XHR"), ("2", "Fetch"), ("3", "Image"), ("4", "Fetch")] {
+            let url = format!("https://example.com/{}", id);
+            rendered.on_event(&event("Network.requestWillBeSent", request(id, kind, &url)));
+        }
+
+        for (id, status) in [("1", 500), ("3", 404), ("4", 200)] {
+            let params = json!({ "requestId": id, "response": { "status": status } });
+            rendered.on_event(&event("Network.responseReceived", params));
+        }
+
+        let params = json!({ "requestId": "2", "errorText": "net::ERR_FAILED", "canceled": false });
+        rendered.on_event(&event("Network.loadingFailed", params));
+
+        assert_eq!(
+            rendered.failed_requests,
+            ["GET https://example.com/1 (500)", "GET https://example.com/2 (net::ERR_FAILED)"]
+        );
+    }
+
+    #[test]
+    fn navigation() {
+        let cookies = json!([
+            { "domain": "example.com", "name": "session", "value": "1" },
+            { "domain": "example.com", "include_subdomains": true, "path": "/app", "secure": true,
+              "expires": 2000000000, "name": "theme", "value": "dark" },
+        ]);
+        let navigation = Navigation {
+            headers: vec![],
+            origin_headers: vec![("authorization".to_string(), "Basic dTpw".to_string())],
+            cookies: serde_json::from_value(cookies).unwrap(),
+        };
+
+        assert_eq!(
+            navigation.cookie_params(),
+            [
+                json!({ "name": "session", "value": "1", "path": "/", "secure": false,
+                        "url": "http://example.com/" }),
+                json!({ "name": "theme", "value": "dark", "path": "/app", "secure": true,
+                        "domain": ".example.com", "expires": 2000000000 }),
+            ]
+        );
+
+        let request = json!({ "headers": { "Accept": "*/*", "Authorization": "Bearer old" } });
+
+        assert_eq!(
+            navigation.continued_headers(&request),
+            [
+                json!({ "name": "Accept", "value": "*/*" }),
+                json!({ "name": "authorization", "value": "Basic dTpw" }),
+            ]
+        );
+    }
+
+    #[test]
+    fn timings() {
+        let timings = Timings::from_value(&json!({ "fcp": 812.4, "dom_content_loaded": 640.0 }));
+
+        assert_eq!(timings.fcp_ms, Some(812));
+        assert_eq!(timings.dom_content_loaded_ms, Some(640));
+        assert_eq!(timings.transfer_size, None);
+
+        // Nothing painted.
+        assert_eq!(Timings::from_value(&json!({ "fcp": null })), Timings::default());
+    }
+}
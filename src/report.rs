@@ -0,0 +1,1350 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::audit::HeaderAudit;
+use crate::baseline::{Change, ChangeKind};
+use crate::certs::Certificate;
+use crate::codeclimate;
+use crate::deprecation::Deprecation;
+use crate::equity::field;
+use crate::error_page::ErrorPage;
+use crate::extract::Resource;
+use crate::network::NetworkError;
+use crate::prime::{self, CacheStatus};
+use crate::render::Timings;
+use crate::history::name;
+use crate::seo::SeoAudit;
+use crate::suggest::Suggestion;
+use crate::usage;
+
+/// Get a percentile (nearest rank) of sorted values.
+pub fn percentile(sorted: &[u64], percent: usize) -> u64 {
+    sorted[(sorted.len() * percent).div_ceil(100).max(1) - 1]
+}
+
+/// How many pages of a duplicate content group are listed in the summary.
+const MAX_LISTED_DUPLICATES: usize = 10;
+
+/// How many of the most linked broken URLs are listed in the summary.
+const MAX_LISTED_BROKEN: usize = 10;
+
+/// How many hosts are listed in the summary, the least healthy first.
+const MAX_LISTED_HOSTS: usize = 10;
+
+/// The median response time above which a host is slow, lowering its health score.
+const SLOW_HOST_MS: u64 = 1000;
+
+/// The HTML report page, the report being injected in place of `/*REPORT*/`.
+const HTML_TEMPLATE: &str = include_str!("report.html");
+
+/// A crawl is split into several stages, executed in order.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// Queue the initial URLs.
+    Seeding,
+
+    /// Crawl pages and extract their links.
+    Discovery,
+
+    /// Check links that are not crawled themselves.
+    ExternalChecks,
+
+    /// Classify every collected response.
+    Verification,
+
+    /// Build and write the report.
+    Reporting,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Stage::Seeding => "seeding",
+            Stage::Discovery => "discovery",
+            Stage::Verification => "verification",
+            Stage::ExternalChecks => "external checks",
+            Stage::Reporting => "reporting",
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// Time spent in a finished stage.
+#[derive(Debug, Serialize)]
+pub struct StageTiming {
+    pub stage: Stage,
+    pub duration_ms: u128,
+}
+
+/// Keep track of the current stage and of the time spent in previous ones.
+#[derive(Debug, Default)]
+pub struct Stages {
+    current: Option<(Stage, Instant)>,
+    timings: Vec<StageTiming>,
+}
+
+impl Stages {
+    /// Finish the current stage (if any) and start the given one.
+    pub fn enter(&mut self, stage: Stage) {
+        self.finish();
+        info!("Entering {} stage", stage);
+        self.current = Some((stage, Instant::now()));
+    }
+
+    /// Finish the current stage.
+    pub fn finish(&mut self) {
+        if let Some((stage, started_at)) = self.current.take() {
+            let elapsed = started_at.elapsed();
+
+            info!("Finished {} stage in {:.2?}", stage, elapsed);
+            self.timings.push(StageTiming {
+                stage,
+                duration_ms: elapsed.as_millis(),
+            });
+        }
+    }
+
+    /// Get the timings of finished stages.
+    pub fn timings(&self) -> &[StageTiming] {
+        &self.timings
+    }
+}
+
+/// How a response must be considered.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Success,
+    Warning,
+    Failure,
+}
+
+/// A single URL in the report.
+#[derive(Debug, Serialize)]
+pub struct Entry {
+    pub url: String,
+    pub status: u16,
+    pub count: u32,
+    pub verdict: Verdict,
+    pub resource: Resource,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub redirects: Vec<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<String>,
+    /// The pages linking to this URL.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub found_on: Vec<String>,
+
+    /// The address the request has been sent to, telling IPv4 from IPv6.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+
+    /// The address each redirect has been sent to, in the order of `redirects`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub redirect_addresses: Vec<Option<String>>,
+
+    /// How long the response headers took to arrive (time to first byte), the redirects
+    /// included, in milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttfb_ms: Option<u64>,
+
+    /// How long the whole response took, its body included (if downloaded), in milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_ms: Option<u64>,
+
+    /// The HTTP version of the (last) response, e.g. `HTTP/1.1` or `HTTP/2.0`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_version: Option<String>,
+
+    /// Why HTTP/3 failed, the URL having been requested over TCP instead (--http3).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http3_fallback: Option<String>,
+
+    /// The compression of the response (e.g. `gzip` or `br`), if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_encoding: Option<String>,
+
+    /// The `Last-Modified` header of the response, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+
+    /// The `Age` header of the response (how long it has been cached), in seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub age_s: Option<u64>,
+
+    /// The `Expires` header of the response, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires: Option<String>,
+
+    /// The --capture-headers of the response, by lowercase name.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+
+    /// Whether the response has been served by a cache, according to its headers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_status: Option<CacheStatus>,
+
+    /// Why the request failed, if it did.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// The class of the failure, for network errors (DNS, TLS, timeout...).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_class: Option<NetworkError>,
+
+    /// The errors logged to the console by the page, if rendered (--render).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub console_errors: Vec<String>,
+
+    /// The XHR and fetch requests of the page which failed, if rendered (--render).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed_requests: Vec<String>,
+
+    /// The performance timings of the page, if rendered (--render).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timings: Option<Timings>,
+
+    /// Where the screenshot of the page has been saved, if it failed (--screenshots).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub screenshot: Option<String>,
+
+    /// The closest Wayback Machine snapshot of a dead external link (--wayback).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived: Option<String>,
+
+    /// The language section of the URL (--lang-prefixes), e.g. `fr`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+}
+
+#[cfg(test)]
+impl Entry {
+    /// An entry of a page, found once, with nothing more than its status.
+    pub fn page(url: &str, status: u16) -> Self {
+        Self {
+            url: url.to_string(),
+            status,
+            count: 1,
+            verdict: match status {
+                200..=299 => Verdict::Success,
+                _ => Verdict::Failure,
+            },
+            resource: Resource::Page,
+            redirects: vec![],
+            annotations: vec![],
+            found_on: vec![],
+            address: None,
+            redirect_addresses: vec![],
+            ttfb_ms: None,
+            time_ms: None,
+            http_version: None,
+            http3_fallback: None,
+            content_encoding: None,
+            last_modified: None,
+            age_s: None,
+            expires: None,
+            headers: BTreeMap::new(),
+            cache_status: None,
+            error: None,
+            error_class: None,
+            console_errors: vec![],
+            failed_requests: vec![],
+            timings: None,
+            screenshot: None,
+            archived: None,
+            lang: None,
+        }
+    }
+}
+
+/// A link to a fragment (`page.html#install`) missing from the target page.
+#[derive(Debug, Serialize)]
+pub struct BrokenAnchor {
+    pub url: String,
+    pub fragment: String,
+}
+
+/// The --max-per-pattern budget of an URL template, once hit.
+#[derive(Debug, Serialize)]
+pub struct Budget {
+    pub template: String,
+    pub crawled: usize,
+
+    /// The URLs matching the template which have not been crawled.
+    pub skipped: usize,
+}
+
+/// An alternate version of a page (`hreflang`) that is broken, redirected, or not linking back.
+#[derive(Debug, Serialize)]
+pub struct HreflangError {
+    pub url: String,
+    pub hreflang: String,
+    pub alternate: String,
+    pub reason: String,
+}
+
+/// A page whose body breaks a content rule (`[[content]]`), whatever its status.
+#[derive(Debug, Serialize)]
+pub struct ContentFailure {
+    pub url: String,
+
+    /// The URL pattern of the rule.
+    pub pattern: String,
+    pub reason: String,
+}
+
+/// A page collapsed into its canonical URL (`<link rel="canonical">`).
+#[derive(Debug, Serialize)]
+pub struct Duplicate {
+    pub url: String,
+    pub canonical: String,
+}
+
+/// An URL permanently redirected, and the pages linking to it.
+#[derive(Debug, Serialize)]
+pub struct RedirectedUrl {
+    pub url: String,
+    pub referrers: Vec<String>,
+}
+
+/// URLs permanently redirected (301 or 308, every hop) into the same destination: their links
+/// can be fixed at the source instead of relying on the redirects.
+#[derive(Debug, Serialize)]
+pub struct RedirectGroup {
+    pub destination: String,
+    pub urls: Vec<RedirectedUrl>,
+
+    /// How many distinct pages link to the URLs of the group.
+    pub referring_pages: usize,
+}
+
+/// Same-domain pages serving the same content (their whitespace aside).
+#[derive(Debug, Serialize)]
+pub struct DuplicateContent {
+    pub urls: Vec<String>,
+}
+
+/// An `http://` resource (image, script, stylesheet...) loaded by an `https://` page.
+#[derive(Debug, Serialize)]
+pub struct MixedContent {
+    pub page: String,
+    pub url: String,
+    pub resource: Resource,
+}
+
+/// A same-domain `http://` link found on an `https://` page (--https-only-links).
+#[derive(Debug, Serialize)]
+pub struct InsecureLink {
+    pub page: String,
+    pub url: String,
+
+    /// Whether the `http://` URL is redirected to `https://`, unless it has not been crawled.
+    pub redirects_to_https: Option<bool>,
+}
+
+/// A step of a redirect chain.
+#[derive(Debug, Serialize)]
+pub struct Hop {
+    pub url: String,
+    pub host: String,
+}
+
+/// An URL redirected to another domain: an internal URL leaving the site (an open redirect, a
+/// hijacked shortlink), or an external link landing elsewhere than on its own domain.
+#[derive(Debug, Serialize)]
+pub struct OffDomainRedirect {
+    pub url: String,
+    pub internal: bool,
+    pub destination: String,
+
+    /// Every URL followed after this one, the destination last.
+    pub chain: Vec<Hop>,
+}
+
+/// A host only reachable on one scheme (--scheme-fallback): its URLs failing over `https://` (a
+/// broken certificate) but served over `http://`, or the other way round.
+#[derive(Debug, Serialize)]
+pub struct SingleSchemeHost {
+    pub host: String,
+
+    /// The scheme serving the host.
+    pub scheme: String,
+
+    /// Why the other scheme failed, on the first URL.
+    pub error: String,
+    pub urls: usize,
+}
+
+/// A `mailto:` link whose addresses are invalid (--check-mailto).
+#[derive(Debug, Serialize)]
+pub struct InvalidMailto {
+    pub page: String,
+    pub href: String,
+    pub reason: String,
+}
+
+/// A link which cannot be parsed as an URL, even once resolved against its page.
+#[derive(Debug, Serialize)]
+pub struct InvalidUrl {
+    /// The page the link has been found on, unless given as an entrypoint (or by a plugin).
+    pub page: Option<String>,
+    pub href: String,
+    pub reason: String,
+}
+
+/// A broken link, as listed on a page.
+#[derive(Debug, Serialize)]
+pub struct BrokenLink {
+    pub url: String,
+    pub status: u16,
+}
+
+/// The broken links found on a page.
+#[derive(Debug, Serialize)]
+pub struct Page {
+    pub url: String,
+    pub broken: Vec<BrokenLink>,
+}
+
+/// Invert the results: for each page, list the broken links found on it (sorted by URL).
+pub fn group_by_page(results: &[Entry]) -> Vec<Page> {
+    let mut pages: BTreeMap<&str, Vec<BrokenLink>> = BTreeMap::new();
+
+    for entry in results.iter().filter(|x| x.verdict == Verdict::Failure) {
+        for page in &entry.found_on {
+            pages.entry(page).or_default().push(BrokenLink {
+                url: entry.url.clone(),
+                status: entry.status,
+            });
+        }
+    }
+
+    pages
+        .into_iter()
+        .map(|(url, broken)| Page {
+            url: url.to_string(),
+            broken,
+        })
+        .collect()
+}
+
+/// The results of a host, and how healthy it looks.
+#[derive(Debug, Serialize)]
+pub struct HostHealth {
+    pub host: String,
+    pub urls: usize,
+    pub failures: usize,
+    pub warnings: usize,
+
+    /// The share of failed URLs, between 0 and 1.
+    pub error_rate: f64,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub median_ms: Option<u64>,
+
+    /// How many requests failed on a TLS error (handshake, invalid certificate).
+    pub tls_errors: usize,
+
+    /// The days left before the certificate of the host expires (--cert-expiry-warn).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub certificate_days_left: Option<i32>,
+
+    /// From 100 (every URL works, quickly) down to 0: failures cost up to 100 points, warnings up
+    /// to 50, a slow median (over a second) or a certificate problem 20 each.
+    pub score: u8,
+}
+
+/// Aggregate the results by host, the least healthy first, when several hosts have been checked
+/// (external links, several sites): telling at a glance that everything broken is on a CDN.
+pub fn host_health(results: &[Entry], certificates: &[Certificate]) -> Vec<HostHealth> {
+    let mut hosts: BTreeMap<String, Vec<&Entry>> = BTreeMap::new();
+
+    for entry in results {
+        if let Some(host) = Url::parse(&entry.url).ok().as_ref().and_then(Url::host_str) {
+            hosts.entry(host.to_string()).or_default().push(entry);
+        }
+    }
+
+    if hosts.len() < 2 {
+        return vec![];
+    }
+
+    let mut health: Vec<HostHealth> = hosts
+        .into_iter()
+        .map(|(host, entries)| {
+            let count = |verdict| entries.iter().filter(|x| x.verdict == verdict).count();
+            let (failures, warnings) = (count(Verdict::Failure), count(Verdict::Warning));
+            let mut times: Vec<u64> = entries.iter().filter_map(|x| x.time_ms).collect();
+            let tls_errors = entries
+                .iter()
+                .filter(|x| x.error_class == Some(NetworkError::Tls))
+                .count();
+            let certificate = certificates.iter().find(|x| x.host == host);
+            let error_rate = failures as f64 / entries.len() as f64;
+
+            times.sort_unstable();
+
+            let median_ms = match times.is_empty() {
+                true => None,
+                false => Some(percentile(&times, 50)),
+            };
+            let mut penalty = 100.0 * error_rate + 50.0 * warnings as f64 / entries.len() as f64;
+
+            if median_ms.is_some_and(|x| x > SLOW_HOST_MS) {
+                penalty += 20.0;
+            }
+
+            if tls_errors > 0 || certificate.is_some_and(|x| x.expiring) {
+                penalty += 20.0;
+            }
+
+            HostHealth {
+                host,
+                urls: entries.len(),
+                failures,
+                warnings,
+                error_rate,
+                median_ms,
+                tls_errors,
+                certificate_days_left: certificate.map(|x| x.days_left),
+                score: (100.0 - penalty).round().max(0.0) as u8,
+            }
+        })
+        .collect();
+
+    health.sort_by(|a, b| a.score.cmp(&b.score).then_with(|| b.failures.cmp(&a.failures)));
+    health
+}
+
+/// The results of a language section (--lang-prefixes).
+#[derive(Debug, Serialize)]
+pub struct Language {
+    pub lang: String,
+    pub urls: usize,
+    pub failures: usize,
+    pub warnings: usize,
+}
+
+/// Count the results of every language section, in the order of the prefixes: each team can
+/// look at its own section.
+pub fn languages(results: &[Entry], prefixes: &[String]) -> Vec<Language> {
+    prefixes
+        .iter()
+        .map(|prefix| {
+            let lang = prefix.trim_start_matches('/');
+            let entries: Vec<&Entry> =
+                results.iter().filter(|x| x.lang.as_deref() == Some(lang)).collect();
+            let count = |verdict| entries.iter().filter(|x| x.verdict == verdict).count();
+
+            Language {
+                lang: lang.to_string(),
+                urls: entries.len(),
+                failures: count(Verdict::Failure),
+                warnings: count(Verdict::Warning),
+            }
+        })
+        .collect()
+}
+
+/// The final report of a crawl, printed as a summary or written as JSON.
+#[derive(Debug, Serialize)]
+pub struct Report<'a> {
+    pub entrypoint: &'a str,
+
+    /// Every crawled site, the entrypoint first.
+    pub entrypoints: Vec<&'a str>,
+
+    /// The response headers stored for every URL (--capture-headers), as CSV columns too.
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub captured_headers: &'a [String],
+
+    /// The cache hits and misses of the pages (--prime-cache).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache: Option<prime::Summary>,
+
+    /// Whether the crawl has been interrupted (Ctrl-C), its results being partial.
+    pub interrupted: bool,
+    pub stages: &'a [StageTiming],
+    pub results: Vec<Entry>,
+
+    /// How many successful URLs have been left out of the results (--failures-only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub omitted: Option<usize>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub broken_anchors: Vec<BrokenAnchor>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub duplicates: Vec<Duplicate>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub duplicate_content: Vec<DuplicateContent>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub redirect_groups: Vec<RedirectGroup>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hreflang_errors: Vec<HreflangError>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub budgets: Vec<Budget>,
+
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub content_failures: &'a [ContentFailure],
+
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub mixed_content: &'a [MixedContent],
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub insecure_links: Vec<InsecureLink>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub off_domain_redirects: Vec<OffDomainRedirect>,
+
+    /// How many links to other schemes than HTTP(S) have been skipped, by scheme.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub other_schemes: &'a BTreeMap<String, usize>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub single_scheme_hosts: Vec<&'a SingleSchemeHost>,
+
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub invalid_mailto: &'a [InvalidMailto],
+
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub invalid_urls: &'a [InvalidUrl],
+
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub header_audits: &'a [HeaderAudit],
+
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub seo: &'a [SeoAudit],
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub certificates: Vec<Certificate>,
+
+    /// The health of every checked host, when there are several of them.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hosts: Vec<HostHealth>,
+
+    /// The results of every language section, with --lang-prefixes.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub languages: Vec<Language>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub deprecations: Vec<Deprecation>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suggested_exclusions: Vec<Suggestion>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pages: Option<Vec<Page>>,
+
+    pub usage: usage::Summary,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_page: Option<&'a ErrorPage>,
+
+    /// The changes since the --baseline run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changes: Option<Vec<Change>>,
+}
+
+impl<'a> Report<'a> {
+    /// Get the hosts of the crawled sites, for notifications.
+    pub fn sites(&self) -> String {
+        let sites: Vec<String> = self
+            .entrypoints
+            .iter()
+            .map(|x| match Url::parse(x).ok().as_ref().and_then(Url::host_str) {
+                Some(host) => host.to_string(),
+                None => x.to_string(),
+            })
+            .collect();
+
+        sites.join(", ")
+    }
+
+    /// Get how many URLs have been checked, including the ones left out of the results.
+    pub fn checked(&self) -> usize {
+        self.results.len() + self.omitted.unwrap_or_default()
+    }
+
+    /// Get the failed URLs, the ones linked the most first.
+    pub fn most_linked_failures(&self) -> Vec<&Entry> {
+        let mut failures: Vec<&Entry> = self
+            .results
+            .iter()
+            .filter(|x| x.verdict == Verdict::Failure)
+            .collect();
+
+        failures.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.url.cmp(&b.url)));
+        failures
+    }
+
+    /// Get the (sorted) response times of every host, in milliseconds.
+    fn response_times(&self) -> BTreeMap<String, Vec<u64>> {
+        let mut times: BTreeMap<String, Vec<u64>> = BTreeMap::new();
+
+        for entry in &self.results {
+            let host = Url::parse(&entry.url).ok().and_then(|x| x.host_str().map(str::to_string));
+
+            if let (Some(host), Some(time)) = (host, entry.time_ms) {
+                times.entry(host).or_default().push(time);
+            }
+        }
+
+        times.values_mut().for_each(|x| x.sort_unstable());
+        times
+    }
+
+    /// Get the results as CSV, one row per URL (the pages it has been found on separated by
+    /// spaces), followed by a column per --capture-headers.
+    pub fn results_csv(&self) -> String {
+        let mut csv =
+            "url,status,verdict,resource,count,found_on,ttfb_ms,time_ms,http_version,error_class"
+                .to_string();
+        let millis = |x: Option<u64>| x.map(|x| x.to_string()).unwrap_or_default();
+        let headers: Vec<String> = self.captured_headers.iter().map(|x| x.to_lowercase()).collect();
+
+        if !self.languages.is_empty() {
+            csv.push_str(",lang");
+        }
+
+        for header in &headers {
+            csv.push(',');
+            csv.push_str(&field(header));
+        }
+
+        csv.push('\n');
+
+        for entry in &self.results {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{},{}",
+                field(&entry.url),
+                entry.status,
+                name(&entry.verdict),
+                name(&entry.resource),
+                entry.count,
+                field(&entry.found_on.join(" ")),
+                millis(entry.ttfb_ms),
+                millis(entry.time_ms),
+                entry.http_version.as_deref().unwrap_or_default(),
+                entry.error_class.as_ref().map(name).unwrap_or_default()
+            ));
+
+            if !self.languages.is_empty() {
+                csv.push(',');
+                csv.push_str(entry.lang.as_deref().unwrap_or_default());
+            }
+
+            for header in &headers {
+                csv.push(',');
+                csv.push_str(&field(entry.headers.get(header).map_or("", String::as_str)));
+            }
+
+            csv.push('\n');
+        }
+
+        csv
+    }
+
+    /// Print the results on stdout, for other tools: the whole report as `json`, `csv` (see
+    /// [`Report::results_csv`]), `ndjson` (one result per line) or `codeclimate` (an issue per
+    /// failed or warned URL).
+    pub fn print_results(&self, format: &str) -> Result<(), Box<dyn Error>> {
+        self.write_results(format, &mut io::stdout().lock())
+    }
+
+    /// Write the results in a format (see [`Report::print_results`]).
+    pub fn write_results(&self, format: &str, out: &mut impl Write) -> Result<(), Box<dyn Error>> {
+        match format {
+            "json" => {
+                serde_json::to_writer(&mut *out, self)?;
+                out.write_all(b"\n")?;
+            }
+            "csv" => out.write_all(self.results_csv().as_bytes())?,
+            "codeclimate" => {
+                serde_json::to_writer(&mut *out, &codeclimate::issues(&self.results))?;
+                out.write_all(b"\n")?;
+            }
+            _ => {
+                for entry in &self.results {
+                    serde_json::to_writer(&mut *out, entry)?;
+                    out.write_all(b"\n")?;
+                }
+            }
+        }
+
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Write the report as JSON into the given file.
+    pub fn write_json(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        serde_json::to_writer_pretty(File::create(path)?, self)?;
+        Ok(())
+    }
+
+    /// Write the report as a standalone HTML page, with search, filters, pagination and a detail
+    /// view for each URL (`#url=<url>` deep links).
+    pub fn write_html(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        // Escape `</` so the data cannot close the script tag.
+        let json = serde_json::to_string(self)?.replace("</", "<\\/");
+
+        fs::write(path, HTML_TEMPLATE.replace("/*REPORT*/", &json))?;
+        Ok(())
+    }
+
+    /// Print the changes since the --baseline run, grouped by kind.
+    pub fn print_changes(&self) {
+        let changes = match &self.changes {
+            Some(changes) => changes,
+            None => return,
+        };
+
+        println!(
+            "{} changes since the baseline ({} broken, {} recovered).",
+            changes.len(),
+            changes.iter().filter(|x| x.kind == ChangeKind::Broken).count(),
+            changes.iter().filter(|x| x.kind == ChangeKind::Recovered).count()
+        );
+
+        let status = |x: Option<u16>| x.map_or("-".to_string(), |x| x.to_string());
+        let groups = [
+            (ChangeKind::Broken, "Newly broken:"),
+            (ChangeKind::Recovered, "Recovered:"),
+            (ChangeKind::StatusChanged, "Status changes:"),
+            (ChangeKind::Gone, "No longer found:"),
+        ];
+
+        for (kind, title) in &groups {
+            let group: Vec<&Change> = changes.iter().filter(|x| x.kind == *kind).collect();
+
+            if group.is_empty() {
+                continue;
+            }
+
+            println!();
+            println!("{}", title);
+
+            for change in group {
+                println!(
+                    "  {} → {} {}",
+                    status(change.before),
+                    status(change.after),
+                    change.url
+                );
+            }
+        }
+    }
+
+    /// Print a human readable summary on stdout.
+    pub fn print_summary(&self) {
+        let count = |verdict| self.results.iter().filter(|x| x.verdict == verdict).count();
+
+        println!(
+            "Checked {} URLs, {} failed, {} warnings.",
+            self.checked(),
+            count(Verdict::Failure),
+            count(Verdict::Warning)
+        );
+
+        if let Some(omitted) = self.omitted {
+            println!("{} successful URLs left out of the results (--failures-only).", omitted);
+        }
+
+        let mut errors: BTreeMap<String, usize> = BTreeMap::new();
+
+        for class in self.results.iter().filter_map(|x| x.error_class.as_ref()) {
+            *errors.entry(name(class)).or_default() += 1;
+        }
+
+        if !errors.is_empty() {
+            let classes: Vec<String> = errors
+                .iter()
+                .map(|(class, count)| format!("{} {}", count, class))
+                .collect();
+
+            println!(
+                "{} network errors ({}).",
+                errors.values().sum::<usize>(),
+                classes.join(", ")
+            );
+        }
+
+        if let Some(cache) = &self.cache {
+            println!(
+                "Cache primed: {} pages, {} already cached, {} missed, {} bypassed, {} unknown.",
+                cache.pages, cache.hits, cache.misses, cache.bypassed, cache.unknown
+            );
+        }
+
+        if self.interrupted {
+            println!("The crawl has been interrupted: these results are partial.");
+        }
+
+        if !self.broken_anchors.is_empty() {
+            println!("{} broken anchors.", self.broken_anchors.len());
+        }
+
+        if !self.content_failures.is_empty() {
+            println!(
+                "{} content failures (pages breaking a content rule).",
+                self.content_failures.len()
+            );
+        }
+
+        if !self.mixed_content.is_empty() {
+            println!(
+                "{} mixed content resources (http:// on https:// pages).",
+                self.mixed_content.len()
+            );
+        }
+
+        if !self.duplicates.is_empty() {
+            println!(
+                "{} duplicate pages, collapsed into their canonical URL.",
+                self.duplicates.len()
+            );
+        }
+
+        if !self.other_schemes.is_empty() {
+            let schemes: Vec<String> = self
+                .other_schemes
+                .iter()
+                .map(|(scheme, count)| format!("{} {}", count, scheme))
+                .collect();
+
+            println!(
+                "{} links to other schemes skipped ({}).",
+                self.other_schemes.values().sum::<usize>(),
+                schemes.join(", ")
+            );
+        }
+
+        let linked: Vec<&Entry> = self
+            .most_linked_failures()
+            .into_iter()
+            .filter(|x| x.count > 0)
+            .take(MAX_LISTED_BROKEN)
+            .collect();
+
+        if !linked.is_empty() {
+            println!();
+            println!("Most linked broken URLs:");
+
+            for entry in linked {
+                println!("  {} {} (linked {} times)", entry.status, entry.url, entry.count);
+            }
+        }
+
+        let archived: Vec<&Entry> = self.results.iter().filter(|x| x.archived.is_some()).collect();
+
+        if !archived.is_empty() {
+            println!();
+            println!("Archived snapshots of dead links:");
+
+            for entry in archived {
+                println!("  {} {}", entry.url, entry.archived.as_deref().unwrap_or_default());
+            }
+        }
+
+        let scripted: Vec<&Entry> = self
+            .results
+            .iter()
+            .filter(|x| !x.console_errors.is_empty() || !x.failed_requests.is_empty())
+            .collect();
+
+        if !scripted.is_empty() {
+            println!();
+            println!("Script errors on rendered pages:");
+
+            for entry in scripted {
+                println!("  {}", entry.url);
+
+                for error in &entry.console_errors {
+                    println!("    console: {}", error);
+                }
+
+                for request in &entry.failed_requests {
+                    println!("    request: {}", request);
+                }
+            }
+        }
+
+        if !self.invalid_urls.is_empty() {
+            println!();
+            println!("Invalid URLs:");
+
+            for invalid in self.invalid_urls {
+                match &invalid.page {
+                    Some(page) => println!("  {} on {} ({})", invalid.href, page, invalid.reason),
+                    None => println!("  {} ({})", invalid.href, invalid.reason),
+                }
+            }
+        }
+
+        if !self.off_domain_redirects.is_empty() {
+            println!();
+            println!("Redirects to other domains:");
+
+            for redirect in &self.off_domain_redirects {
+                match redirect.internal {
+                    true => println!("  {} (leaves the site)", redirect.url),
+                    false => println!("  {} (lands on another domain)", redirect.url),
+                }
+
+                for hop in &redirect.chain {
+                    println!("    -> {}", hop.url);
+                }
+            }
+        }
+
+        if !self.single_scheme_hosts.is_empty() {
+            println!();
+            println!("Hosts only reachable on one scheme:");
+
+            for host in &self.single_scheme_hosts {
+                println!(
+                    "  {} only over {}:// ({} URLs, the other scheme failing: {})",
+                    host.host, host.scheme, host.urls, host.error
+                );
+            }
+        }
+
+        if !self.insecure_links.is_empty() {
+            println!();
+            println!("Plain http:// links on https:// pages:");
+
+            for link in &self.insecure_links {
+                let note = match link.redirects_to_https {
+                    Some(true) => "",
+                    Some(false) => " (not redirected to https://)",
+                    None => " (not crawled)",
+                };
+
+                println!("  {} on {}{}", link.url, link.page, note);
+            }
+        }
+
+        if !self.invalid_mailto.is_empty() {
+            println!();
+            println!("Invalid mailto links:");
+
+            for mailto in self.invalid_mailto {
+                println!("  {} on {} ({})", mailto.href, mailto.page, mailto.reason);
+            }
+        }
+
+        let missing_headers: Vec<&HeaderAudit> = self
+            .header_audits
+            .iter()
+            .filter(|x| !x.missing.is_empty())
+            .collect();
+
+        if !missing_headers.is_empty() {
+            println!();
+            println!("Pages missing security headers:");
+
+            for audit in missing_headers {
+                println!("  {} ({})", audit.url, audit.missing.join(", "));
+            }
+        }
+
+        let seo_issues: Vec<&SeoAudit> = self.seo.iter().filter(|x| !x.issues.is_empty()).collect();
+
+        if !seo_issues.is_empty() {
+            println!();
+            println!("SEO issues:");
+
+            for audit in seo_issues {
+                println!("  {} ({})", audit.url, audit.issues.join(", "));
+            }
+        }
+
+        if !self.hreflang_errors.is_empty() {
+            println!();
+            println!("Hreflang errors:");
+
+            for error in &self.hreflang_errors {
+                println!(
+                    "  {} -> {} ({}): {}",
+                    error.url, error.alternate, error.hreflang, error.reason
+                );
+            }
+        }
+
+        if !self.budgets.is_empty() {
+            println!();
+            println!("Crawl budgets hit:");
+
+            for budget in &self.budgets {
+                println!(
+                    "  {} ({} crawled, {} skipped)",
+                    budget.template, budget.crawled, budget.skipped
+                );
+            }
+        }
+
+        if !self.duplicate_content.is_empty() {
+            println!();
+            println!("Duplicate content:");
+
+            for group in &self.duplicate_content {
+                println!("  {} pages serve the same content:", group.urls.len());
+
+                for url in group.urls.iter().take(MAX_LISTED_DUPLICATES) {
+                    println!("    {}", url);
+                }
+
+                if group.urls.len() > MAX_LISTED_DUPLICATES {
+                    println!("    ... and {} more", group.urls.len() - MAX_LISTED_DUPLICATES);
+                }
+            }
+        }
+
+        if !self.redirect_groups.is_empty() {
+            println!();
+            println!("Permanent redirects into the same destination (links to fix at the source):");
+
+            for group in &self.redirect_groups {
+                println!(
+                    "  {} URLs redirect to {}, linked from {} pages:",
+                    group.urls.len(),
+                    group.destination,
+                    group.referring_pages
+                );
+
+                for url in group.urls.iter().take(MAX_LISTED_DUPLICATES) {
+                    println!("    {} (linked from {} pages)", url.url, url.referrers.len());
+                }
+
+                if group.urls.len() > MAX_LISTED_DUPLICATES {
+                    println!("    ... and {} more", group.urls.len() - MAX_LISTED_DUPLICATES);
+                }
+            }
+        }
+
+        let expiring: Vec<&Certificate> = self.certificates.iter().filter(|x| x.expiring).collect();
+
+        if !expiring.is_empty() {
+            println!();
+            println!("Expiring certificates:");
+
+            for cert in expiring {
+                println!(
+                    "  {}:{} expires on {} ({} days left)",
+                    cert.host, cert.port, cert.expires, cert.days_left
+                );
+            }
+        }
+
+        if !self.deprecations.is_empty() {
+            println!();
+            println!("Deprecated endpoints:");
+
+            for endpoint in &self.deprecations {
+                match &endpoint.sunset {
+                    Some(sunset) => println!("  {} (sunset on {})", endpoint.url, sunset),
+                    None => println!("  {}", endpoint.url),
+                }
+            }
+        }
+
+        if !self.suggested_exclusions.is_empty() {
+            println!();
+            println!("Suggested exclusions:");
+
+            for suggestion in &self.suggested_exclusions {
+                println!(
+                    "  --exclude '{}'  ({} URLs, {})",
+                    suggestion.pattern, suggestion.count, suggestion.reason
+                );
+            }
+        }
+
+        if let Some(pages) = &self.pages {
+            println!();
+            println!("Broken links by page:");
+
+            for page in pages {
+                println!("  {}", page.url);
+
+                for link in &page.broken {
+                    println!("    {} {}", link.status, link.url);
+                }
+            }
+        }
+
+        if let Some(page) = self.error_page {
+            println!();
+            println!("Error page: {} ({} bytes)", page.status, page.length);
+
+            for problem in &page.problems {
+                println!("  {}", problem);
+            }
+        }
+
+        if self.changes.is_some() {
+            println!();
+            self.print_changes();
+        }
+
+        println!();
+        println!(
+            "Network: {} requests to {} hosts ({} over TLS), {} connections ({} TLS handshakes), \
+             {} DNS lookups, {} sent, {} received.",
+            self.usage.requests,
+            self.usage.hosts,
+            self.usage.tls_hosts,
+            self.usage.connections,
+            self.usage.tls_handshakes,
+            self.usage.dns_lookups,
+            format_bytes(self.usage.bytes_sent),
+            format_bytes(self.usage.bytes_received)
+        );
+
+        let mut versions: BTreeMap<&str, usize> = BTreeMap::new();
+
+        for version in self.results.iter().filter_map(|x| x.http_version.as_deref()) {
+            *versions.entry(version).or_default() += 1;
+        }
+
+        if !versions.is_empty() {
+            let versions: Vec<String> =
+                versions.iter().map(|(k, v)| format!("{} ({})", k, v)).collect();
+
+            println!("HTTP versions: {}.", versions.join(", "));
+        }
+
+        let fallbacks = self.results.iter().filter(|x| x.http3_fallback.is_some()).count();
+
+        if fallbacks > 0 {
+            println!("{} URLs fell back from HTTP/3 to TCP.", fallbacks);
+        }
+
+        if let Some(ms) = self.usage.cpu_time_ms {
+            println!("CPU time: {:.2}s.", ms as f64 / 1000.0);
+        }
+
+        if !self.hosts.is_empty() {
+            println!();
+            println!("Hosts, the least healthy first:");
+
+            for host in self.hosts.iter().take(MAX_LISTED_HOSTS) {
+                let mut notes = vec![
+                    format!("{} URLs", host.urls),
+                    format!("{} failed ({:.0}%)", host.failures, host.error_rate * 100.0),
+                    format!("{} warnings", host.warnings),
+                ];
+
+                if let Some(ms) = host.median_ms {
+                    notes.push(format!("median {}ms", ms));
+                }
+
+                if host.tls_errors > 0 {
+                    notes.push(format!("{} TLS errors", host.tls_errors));
+                }
+
+                if let Some(days) = host.certificate_days_left {
+                    notes.push(format!("certificate expires in {} days", days));
+                }
+
+                println!("  {:<30} {:>3}/100  {}", host.host, host.score, notes.join(", "));
+            }
+
+            if self.hosts.len() > MAX_LISTED_HOSTS {
+                println!("  ... and {} more", self.hosts.len() - MAX_LISTED_HOSTS);
+            }
+        }
+
+        if !self.languages.is_empty() {
+            println!();
+            println!("Languages:");
+
+            for language in &self.languages {
+                println!(
+                    "  {:<10} {} URLs, {} failed, {} warnings",
+                    language.lang, language.urls, language.failures, language.warnings
+                );
+            }
+        }
+
+        if self.usage.requests > 0 {
+            let timeline: Vec<String> =
+                self.usage.timeline.iter().map(|x| format!("{:.1}", x)).collect();
+
+            println!();
+            println!(
+                "Throughput: {:.1} requests/s, {}/s over {:.1}s.",
+                self.usage.requests_per_second,
+                format_bytes(self.usage.bytes_per_second as u64),
+                self.usage.duration_ms as f64 / 1000.0
+            );
+            println!(
+                "  requests/s every {}s: {}",
+                self.usage.interval_s,
+                timeline.join(" ")
+            );
+
+            for host in &self.usage.per_host {
+                println!(
+                    "  {:<20} {:>6} requests {:>10} {:>8.1} requests/s",
+                    host.host,
+                    host.requests,
+                    format_bytes(host.bytes_received),
+                    host.requests_per_second
+                );
+            }
+        }
+
+        let response_times = self.response_times();
+
+        if !response_times.is_empty() {
+            println!();
+            println!("{:<22} {:>8} {:>8} {:>8}", "Response times:", "p50", "p95", "p99");
+
+            for (host, times) in response_times {
+                println!(
+                    "  {:<20} {:>6}ms {:>6}ms {:>6}ms",
+                    host,
+                    percentile(&times, 50),
+                    percentile(&times, 95),
+                    percentile(&times, 99)
+                );
+            }
+        }
+
+        println!();
+        println!("Stages:");
+
+        for timing in self.stages {
+            println!(
+                "  {:<16} {:>8.2}s",
+                timing.stage.to_string(),
+                timing.duration_ms as f64 / 1000.0
+            );
+        }
+    }
+}
+
+/// Format a size in bytes, with a binary unit.
+fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1_048_575 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / 1_048_576.0),
+    }
+}
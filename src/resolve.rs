@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Empty, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode, Version};
+use hyper_util::rt::TokioIo;
+use tokio::net::{TcpListener, TcpStream};
+use url::Url;
+
+/// Addresses to connect to instead of resolving some hosts (`host:port` => addresses, tried in
+/// order): the --resolve ones, and the hosts resolved with --dns or --doh.
+pub type Overrides = HashMap<(String, u16), Vec<SocketAddr>>;
+
+/// Parse a resolution override (`<host>:<port>:<address>`, curl `--resolve` style).
+pub fn parse(value: &str) -> Result<(String, u16, IpAddr), String> {
+    let invalid = || format!("invalid override `{}`, expected `<host>:<port>:<address>`", value);
+    let mut parts = value.splitn(3, ':');
+    let host = parts.next().filter(|x| !x.is_empty()).ok_or_else(invalid)?;
+    let port = parts.next().and_then(|x| x.parse().ok()).ok_or_else(invalid)?;
+    let address = parts
+        .next()
+        .map(|x| x.trim_start_matches('[').trim_end_matches(']'))
+        .and_then(|x| x.parse().ok())
+        .ok_or_else(invalid)?;
+
+    Ok((host.to_lowercase(), port, address))
+}
+
+/// Get the addresses to connect to for a request, if overridden.
+fn target(overrides: &Mutex<Overrides>, host: Option<&str>, port: u16) -> Option<Vec<SocketAddr>> {
+    overrides
+        .lock()
+        .unwrap()
+        .get(&(host?.to_lowercase(), port))
+        .filter(|x| !x.is_empty())
+        .cloned()
+}
+
+/// The body of the proxy responses: its own, or the one of the server.
+type Body = BoxBody<Bytes, hyper::Error>;
+
+/// Connect to the first address accepting the connection.
+async fn connect(addresses: &[SocketAddr]) -> std::io::Result<TcpStream> {
+    let mut last_error = None;
+
+    for address in addresses {
+        match TcpStream::connect(address).await {
+            Ok(stream) => return Ok(stream),
+            Err(e) => {
+                debug!("Cannot connect to {}: {}", address, e);
+                last_error = Some(e);
+            }
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| std::io::ErrorKind::AddrNotAvailable.into()))
+}
+
+/// Handle a proxied request: tunnel `CONNECT` requests (TLS, so the `Host` header and the SNI
+/// stay untouched) and forward plain HTTP ones, to the overriding address.
+async fn handle(
+    overrides: Arc<Mutex<Overrides>>,
+    mut request: Request<Incoming>,
+) -> Result<Response<Body>, Box<dyn Error + Send + Sync>> {
+    let uri = request.uri().clone();
+    let default_port = match uri.scheme_str() {
+        Some("http") => 80,
+        _ => 443,
+    };
+    let addresses = match target(&overrides, uri.host(), uri.port_u16().unwrap_or(default_port)) {
+        Some(addresses) => addresses,
+        None => {
+            let body = Full::from("not an overridden host").map_err(|x| match x {});
+            let mut response = Response::new(body.boxed());
+            *response.status_mut() = StatusCode::BAD_GATEWAY;
+            return Ok(response);
+        }
+    };
+
+    debug!("Connecting to {:?} for {}", addresses, uri);
+
+    if request.method() == Method::CONNECT {
+        tokio::spawn(async move {
+            let tunnel = async {
+                let upgraded = hyper::upgrade::on(&mut request).await?;
+                let mut server = connect(&addresses).await?;
+                let (mut client_read, mut client_write) = tokio::io::split(TokioIo::new(upgraded));
+                let (mut server_read, mut server_write) = server.split();
+
+                futures::future::try_join(
+                    tokio::io::copy(&mut client_read, &mut server_write),
+                    tokio::io::copy(&mut server_read, &mut client_write),
+                )
+                .await?;
+
+                Ok::<_, Box<dyn Error + Send + Sync>>(())
+            };
+
+            if let Err(e) = tunnel.await {
+                debug!("Tunnel to {} failed: {}", uri, e);
+            }
+        });
+
+        return Ok(Response::new(Empty::new().map_err(|x| match x {}).boxed()));
+    }
+
+    let stream = connect(&addresses).await?;
+    let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+
+    tokio::spawn(connection);
+    *request.uri_mut() = uri
+        .path_and_query()
+        .map_or("/", |x| x.as_str())
+        .parse()?;
+
+    let mut response = sender.send_request(request).await?;
+
+    // The response is sent back with our own framing, whatever the HTTP version of the server
+    // (close-delimited HTTP/1.0 bodies would never end, the client connection staying open).
+    *response.version_mut() = Version::HTTP_11;
+    Ok(response.map(BodyExt::boxed))
+}
+
+/// Start a local proxy connecting overridden hosts to their address, and get its URL. It
+/// listens on the IPv6 loopback if the client only connects to IPv6 addresses.
+///
+/// The HTTP client cannot be given a custom resolver, so overridden hosts are requested through
+/// this proxy instead.
+pub fn spawn(overrides: Arc<Mutex<Overrides>>, ipv6: bool) -> Result<Url, Box<dyn Error>> {
+    let addr = match ipv6 {
+        true => SocketAddr::from((std::net::Ipv6Addr::LOCALHOST, 0)),
+        false => SocketAddr::from(([127, 0, 0, 1], 0)),
+    };
+    let listener = std::net::TcpListener::bind(addr)?;
+
+    listener.set_nonblocking(true)?;
+
+    let url = Url::parse(&format!("http://{}", listener.local_addr()?))?;
+    let listener = TcpListener::from_std(listener)?;
+
+    tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    error!("Resolution proxy failed: {}", e);
+                    return;
+                }
+            };
+            let overrides = Arc::clone(&overrides);
+            let service = service_fn(move |request| handle(Arc::clone(&overrides), request));
+
+            tokio::spawn(async move {
+                let connection = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .with_upgrades();
+
+                if let Err(e) = connection.await {
+                    debug!("Resolution proxy connection failed: {}", e);
+                }
+            });
+        }
+    });
+
+    Ok(url)
+}
@@ -0,0 +1,105 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Mutex;
+
+use reqwest::header::{HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH};
+use reqwest::{Method, Request, Response};
+use serde::{Deserialize, Serialize};
+
+use crate::extract::{Alternate, Format, Link};
+use crate::middleware::Middleware;
+
+/// The file the pages are remembered into with --polite, when no --revalidate-cache is given.
+pub const DEFAULT_PATH: &str = ".http-status-check-pages.json";
+
+/// What has been extracted from a page, with its validators, to reuse it while it is unchanged.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Page {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+
+    pub status: u16,
+    pub format: Format,
+    pub links: Vec<Link>,
+    pub anchors: HashSet<String>,
+    pub noindex: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canonical: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alternates: Vec<Alternate>,
+}
+
+/// Revalidate the pages of the previous runs (`If-None-Match` and `If-Modified-Since`), so
+/// unchanged ones are neither downloaded nor parsed again.
+#[derive(Debug, Default)]
+pub struct Revalidation {
+    /// The pages of the previous run.
+    previous: HashMap<String, Page>,
+
+    /// The pages of this run, unchanged ones included, saved for the next one.
+    current: Mutex<HashMap<String, Page>>,
+}
+
+impl Revalidation {
+    /// Load the pages saved by the previous run, if any.
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        if !Path::new(path).exists() {
+            return Ok(Self::default());
+        }
+
+        let previous: HashMap<String, Page> = serde_json::from_reader(File::open(path)?)?;
+
+        debug!("Loaded the validators of {} pages from {}", previous.len(), path);
+        Ok(Self {
+            previous,
+            current: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Save the pages of this run, for the next one.
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        serde_json::to_writer(File::create(path)?, &*self.current.lock().unwrap())?;
+        Ok(())
+    }
+
+    /// Remember a page, if it can be revalidated.
+    pub fn insert(&self, url: &str, page: Page) {
+        if page.etag.is_some() || page.last_modified.is_some() {
+            self.current.lock().unwrap().insert(url.to_string(), page);
+        }
+    }
+
+    /// Get back an unchanged page of the previous run.
+    pub fn reuse(&self, url: &str) -> Option<Page> {
+        let page = self.previous.get(url)?.clone();
+
+        self.current.lock().unwrap().insert(url.to_string(), page.clone());
+        Some(page)
+    }
+}
+
+impl Middleware for Revalidation {
+    fn before(&self, request: &mut Request) -> Result<Option<Response>, Box<dyn Error>> {
+        let page = match self.previous.get(request.url().as_str()) {
+            Some(page) if request.method() == Method::GET => page,
+            _ => return Ok(None),
+        };
+
+        if let Some(etag) = &page.etag {
+            request.headers_mut().insert(IF_NONE_MATCH, HeaderValue::from_str(etag)?);
+        }
+
+        if let Some(date) = &page.last_modified {
+            request.headers_mut().insert(IF_MODIFIED_SINCE, HeaderValue::from_str(date)?);
+        }
+
+        Ok(None)
+    }
+}
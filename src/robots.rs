@@ -0,0 +1,243 @@
+use std::time::Duration;
+
+/// The rules of a `robots.txt` file (RFC 9309) applying to the crawler.
+#[derive(Debug, Default)]
+pub struct Robots {
+    /// `Allow` (true) and `Disallow` (false) path patterns.
+    rules: Vec<(bool, String)>,
+
+    /// The `Crawl-delay` extension, if any.
+    pub crawl_delay: Option<Duration>,
+}
+
+/// A group of rules, and the user agents it applies to.
+#[derive(Default)]
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<(bool, String)>,
+    crawl_delay: Option<Duration>,
+}
+
+/// Check if a path matches a pattern, `*` matching any sequence and a final `$` anchoring it.
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let mut rest = match path.strip_prefix(parts[0]) {
+        Some(rest) => rest,
+        None => return false,
+    };
+
+    if parts.len() == 1 {
+        return !anchored || rest.is_empty();
+    }
+
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    let last = parts[parts.len() - 1];
+
+    match anchored {
+        true => rest.ends_with(last),
+        false => rest.contains(last),
+    }
+}
+
+impl Robots {
+    /// Rules forbidding everything, for hosts whose `robots.txt` fails.
+    pub fn disallow_all() -> Self {
+        Self {
+            rules: vec![(false, "/".to_string())],
+            crawl_delay: None,
+        }
+    }
+
+    /// Parse a `robots.txt` file, keeping the group of the given user agent (product token),
+    /// or the `*` one.
+    pub fn parse(body: &str, agent: &str) -> Self {
+        let mut groups: Vec<Group> = vec![];
+        let mut in_agents = false;
+
+        for line in body.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim().to_lowercase(), value.trim()),
+                None => continue,
+            };
+
+            if key == "user-agent" {
+                if !in_agents {
+                    groups.push(Group::default());
+                }
+
+                in_agents = true;
+                groups.last_mut().unwrap().agents.push(value.to_lowercase());
+                continue;
+            }
+
+            in_agents = false;
+
+            let group = match groups.last_mut() {
+                Some(group) => group,
+                None => continue,
+            };
+
+            match key.as_str() {
+                "allow" if !value.is_empty() => group.rules.push((true, value.to_string())),
+                "disallow" if !value.is_empty() => group.rules.push((false, value.to_string())),
+                "crawl-delay" => {
+                    group.crawl_delay = value.parse::<f64>().ok().map(Duration::from_secs_f64)
+                }
+                _ => {}
+            }
+        }
+
+        let agent = agent.to_lowercase();
+        let name = match groups.iter().any(|x| x.agents.contains(&agent)) {
+            true => agent,
+            false => "*".to_string(),
+        };
+
+        let mut robots = Self::default();
+
+        for group in groups.iter().filter(|x| x.agents.contains(&name)) {
+            robots.rules.extend(group.rules.iter().cloned());
+            robots.crawl_delay = robots.crawl_delay.or(group.crawl_delay);
+        }
+
+        robots
+    }
+
+    /// Check if a path (and query) may be crawled: the longest matching rule wins, `Allow`
+    /// winning ties.
+    pub fn allows(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|(_, pattern)| matches(pattern, path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .is_none_or(|(allow, _)| *allow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The example of RFC 9309 (section 5.1), with grouped and empty groups.
+    const EXAMPLE: &str = "\
+User-Agent: *
+Disallow: *.gif$
+Disallow: /example/
+Allow: /publications/
+
+User-Agent: foobot
+Disallow:/
+Allow:/example/page.html
+Allow:/example/allowed.gif
+
+User-Agent: barbot
+User-Agent: bazbot
+Disallow: /example/page.html
+
+User-Agent: quxbot
+";
+
+    #[test]
+    fn groups() {
+        for (agent, path, allowed) in [
+            ("foobot", "/", false),
+            ("foobot", "/example/page.html", true),
+            ("foobot", "/example/allowed.gif", true),
+            ("foobot", "/example/other.html", false),
+            ("FooBot", "/example/page.html", true),
+            ("barbot", "/example/page.html", false),
+            ("bazbot", "/example/page.html", false),
+            ("bazbot", "/example/other.html", true),
+            ("bazbot", "/image.gif", true),
+            // An empty group allows everything.
+            ("quxbot", "/example/page.html", true),
+            // Other agents fall back on `*`.
+            ("otherbot", "/image.gif", false),
+            ("otherbot", "/image.gif?size=2", true),
+            ("otherbot", "/example/page.html", false),
+            ("otherbot", "/publications/", true),
+            ("otherbot", "/", true),
+        ] {
+            assert_eq!(Robots::parse(EXAMPLE, agent).allows(path), allowed, "{} {}", agent, path);
+        }
+    }
+
+    #[test]
+    fn longest_match() {
+        let robots = Robots::parse(
+            "User-Agent: foobot\n\
+             Allow: /example/page/\n\
+             Disallow: /example/page/disallowed.gif\n\
+             Allow: /tie\n\
+             Disallow: /tie\n\
+             Disallow: /*.php$\n\
+             Disallow: /fish*\n\
+             Allow: /fish/salmon",
+            "foobot",
+        );
+
+        for (path, allowed) in [
+            ("/example/page/", true),
+            ("/example/page/disallowed.gif", false),
+            // Equally long rules: allowing wins.
+            ("/tie", true),
+            ("/filename.php", false),
+            ("/folder/filename.php", false),
+            ("/filename.php?parameters", true),
+            ("/filename.php5", true),
+            ("/fish", false),
+            ("/fishheads/yummy.html", false),
+            ("/fish/salmon", true),
+            ("/Fish.asp", true),
+        ] {
+            assert_eq!(robots.allows(path), allowed, "{}", path);
+        }
+    }
+
+    #[test]
+    fn patterns() {
+        assert!(matches("/", "/anything"));
+        assert!(matches("/a*c", "/abc"));
+        assert!(matches("/a*c", "/ac/d"));
+        assert!(matches("/a*c$", "/abbc"));
+        assert!(!matches("/a*c$", "/abcd"));
+        assert!(matches("/a**b", "/ab"));
+        assert!(matches("*", "/"));
+        assert!(!matches("/a$", "/ab"));
+        assert!(!matches("/b", "/ab"));
+    }
+
+    #[test]
+    fn comments_and_crawl_delay() {
+        let robots = Robots::parse(
+            "# comment\nUser-agent: * # everyone\nCrawl-delay: 1.5\nDisallow: /private # mine\n\
+             User-agent: *\nCrawl-delay: 3\nDisallow: /tmp\nunknown line\n",
+            "foobot",
+        );
+
+        assert_eq!(robots.crawl_delay, Some(Duration::from_millis(1500)));
+        assert!(!robots.allows("/private/a"));
+        assert!(!robots.allows("/tmp"));
+        assert!(robots.allows("/public"));
+    }
+
+    #[test]
+    fn rules_before_any_group_are_ignored() {
+        let robots = Robots::parse("Disallow: /\nUser-agent: *\nDisallow: /a", "foobot");
+
+        assert!(robots.allows("/b"));
+        assert!(!robots.allows("/a"));
+        assert!(!Robots::disallow_all().allows("/"));
+    }
+}
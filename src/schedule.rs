@@ -0,0 +1,221 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How far the next run of a schedule is looked for, impossible dates (`30 2`) never matching.
+const HORIZON: u64 = 10 * 366 * 86400;
+
+/// When to crawl: a cron expression (`minute hour day-of-month month day-of-week`, in UTC),
+/// optionally followed by the entrypoints to crawl.
+#[derive(Clone, Debug)]
+pub struct Schedule {
+    pub expression: String,
+    pub entrypoints: Vec<String>,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+
+    /// Whether both the day of month and the day of week are restricted, any of them matching
+    /// then (as cron does).
+    any_day: bool,
+}
+
+/// Parse a schedule, e.g. `0 3 * * *` or `*/30 * * * 1-5 https://example.com`.
+pub fn parse(value: &str) -> Result<Schedule, String> {
+    let fields: Vec<&str> = value.split_whitespace().collect();
+    let invalid = || {
+        format!(
+            "invalid schedule `{}`, expected a cron expression (`minute hour day month weekday`) \
+             optionally followed by entrypoints",
+            value
+        )
+    };
+
+    if fields.len() < 5 {
+        return Err(invalid());
+    }
+
+    let field = |index: usize, min, max| parse_field(fields[index], min, max).ok_or_else(invalid);
+    // Sundays are either 0 or 7.
+    let weekdays = field(4, 0, 7)?;
+    let schedule = Schedule {
+        expression: fields[..5].join(" "),
+        entrypoints: fields[5..].iter().map(|x| x.to_string()).collect(),
+        minutes: field(0, 0, 59)?,
+        hours: field(1, 0, 23)?,
+        days: field(2, 1, 31)?,
+        months: field(3, 1, 12)?,
+        weekdays: (weekdays | weekdays >> 7) & 0x7F,
+        any_day: !fields[2].starts_with('*') && !fields[4].starts_with('*'),
+    };
+
+    match schedule.next(SystemTime::now()) {
+        Some(_) => Ok(schedule),
+        None => Err(format!("the schedule `{}` never runs", schedule.expression)),
+    }
+}
+
+/// Parse a field (`*`, `5`, `1-5`, `*/15`, `0-30/10`, or a list of them) into a set of bits.
+fn parse_field(value: &str, min: u32, max: u32) -> Option<u64> {
+    let mut bits = 0;
+
+    for part in value.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<usize>().ok().filter(|x| *x > 0)?),
+            None => (part, 1),
+        };
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (start.parse().ok()?, end.parse().ok()?),
+            // `5/10` means from 5 to the maximum, every 10.
+            None if step > 1 => (range.parse().ok()?, max),
+            None => (range.parse().ok()?, range.parse().ok()?),
+        };
+
+        if start < min || start > end || end > max {
+            return None;
+        }
+
+        for x in (start..=end).step_by(step) {
+            bits |= 1 << x;
+        }
+    }
+
+    Some(bits)
+}
+
+/// Get the year, month and day of a number of days since the UNIX epoch.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+
+    (yoe + era * 400 + (month <= 2) as u64, month, day)
+}
+
+impl Schedule {
+    /// Get the next time the schedule runs, after the given one.
+    pub fn next(&self, after: SystemTime) -> Option<SystemTime> {
+        let start = after.duration_since(UNIX_EPOCH).ok()?.as_secs() / 60 * 60 + 60;
+        let has = |bits: u64, x: u64| bits & (1 << x) != 0;
+        let mut time = start;
+
+        while time < start + HORIZON {
+            let days = time / 86400;
+            let (_, month, day) = civil_from_days(days);
+            // The UNIX epoch is a Thursday.
+            let weekday = (days + 4) % 7;
+            let day_matches = match self.any_day {
+                true => has(self.days, day) || has(self.weekdays, weekday),
+                false => has(self.days, day) && has(self.weekdays, weekday),
+            };
+
+            if !has(self.months, month) || !day_matches {
+                time = (days + 1) * 86400;
+            } else if !has(self.hours, time % 86400 / 3600) {
+                time = (time / 3600 + 1) * 3600;
+            } else if !has(self.minutes, time % 3600 / 60) {
+                time += 60;
+            } else {
+                return Some(UNIX_EPOCH + Duration::from_secs(time));
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The bits of these values.
+    fn bits(values: &[u64]) -> u64 {
+        values.iter().fold(0, |bits, x| bits | 1 << x)
+    }
+
+    fn at(seconds: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    fn next(expression: &str, after: u64) -> Option<u64> {
+        let next = parse(expression).unwrap().next(at(after))?;
+
+        Some(next.duration_since(UNIX_EPOCH).unwrap().as_secs())
+    }
+
+    #[test]
+    fn fields() {
+        assert_eq!(parse_field("*", 0, 59), Some((1 << 60) - 1));
+        assert_eq!(parse_field("5", 0, 59), Some(bits(&[5])));
+        assert_eq!(parse_field("1-5", 0, 59), Some(bits(&[1, 2, 3, 4, 5])));
+        assert_eq!(parse_field("*/15", 0, 59), Some(bits(&[0, 15, 30, 45])));
+        assert_eq!(parse_field("0-30/10", 0, 59), Some(bits(&[0, 10, 20, 30])));
+        assert_eq!(parse_field("5/20", 0, 59), Some(bits(&[5, 25, 45])));
+        assert_eq!(parse_field("1,3-4,10", 0, 59), Some(bits(&[1, 3, 4, 10])));
+        assert_eq!(parse_field("*", 1, 12), Some(bits(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12])));
+
+        for invalid in ["60", "0", "5-1", "*/0", "1-", "a", "", "1,,2", "*/x"] {
+            assert_eq!(parse_field(invalid, 1, 59), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn invalid_schedules() {
+        let invalid = ["", "* * * *", "60 * * * *", "* 24 * * *", "* * 0 * *", "* * * 13 *"];
+
+        for invalid in invalid.iter().chain(&["* * * * 8", "*/0 * * * *"]) {
+            assert!(parse(invalid).is_err(), "{}", invalid);
+        }
+
+        assert_eq!(parse("0 0 30 2 *").unwrap_err(), "the schedule `0 0 30 2 *` never runs");
+    }
+
+    #[test]
+    fn entrypoints() {
+        let schedule = parse("*/30 * * * 1-5 https://example.com https://example.org").unwrap();
+
+        assert_eq!(schedule.expression, "*/30 * * * 1-5");
+        assert_eq!(schedule.entrypoints, ["https://example.com", "https://example.org"]);
+    }
+
+    #[test]
+    fn days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(59), (1970, 3, 1));
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+        assert_eq!(civil_from_days(19722), (2023, 12, 31));
+        assert_eq!(civil_from_days(19723), (2024, 1, 1));
+    }
+
+    #[test]
+    fn next_runs() {
+        // 2023-12-31 23:30 to 2024-01-01 00:00, across the year.
+        assert_eq!(next("0 0 1 * *", 1_704_065_400), Some(1_704_067_200));
+        // 2024-01-31 13:00 to 2024-02-01 12:30, across the month.
+        assert_eq!(next("30 12 * * *", 1_706_706_000), Some(1_706_790_600));
+        // 2024-03-01 to the next leap day, 2028-02-29.
+        assert_eq!(next("0 0 29 2 *", 1_709_251_200), Some(1_835_395_200));
+        // The next run is strictly after: Sunday 2024-09-01 to Sunday 2024-09-08.
+        assert_eq!(next("0 0 * * 0", 1_725_148_800), Some(1_725_753_600));
+        assert_eq!(next("0 0 * * 7", 1_725_148_800), Some(1_725_753_600));
+        // Seconds are rounded to the next minute.
+        assert_eq!(next("* * * * *", 1_725_148_830), Some(1_725_148_860));
+    }
+
+    #[test]
+    fn day_of_month_or_day_of_week() {
+        // The 13th, or a Friday: Friday 2024-09-06 comes first.
+        assert_eq!(next("0 0 13 * 5", 1_725_148_800), Some(1_725_580_800));
+        // The 13th of any weekday.
+        assert_eq!(next("0 0 13 * *", 1_725_148_800), Some(1_725_148_800 + 12 * 86400));
+        // Fridays only.
+        assert_eq!(next("0 0 * * 5", 1_725_148_800), Some(1_725_580_800));
+    }
+}
@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+
+use select::document::Document;
+use select::predicate::Name;
+use serde::Serialize;
+
+/// The SEO basics of a page: its title, and what is wrong with it.
+#[derive(Debug, Serialize)]
+pub struct SeoAudit {
+    pub url: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub issues: Vec<String>,
+}
+
+impl SeoAudit {
+    /// Audit a page: missing (or empty) `<title>` and meta description, and multiple `<h1>`.
+    pub fn new(url: &str, document: &Document) -> Self {
+        let mut issues = vec![];
+        let title = document
+            .find(Name("title"))
+            .next()
+            .map(|x| x.text().split_whitespace().collect::<Vec<_>>().join(" "));
+
+        match &title {
+            None => issues.push("missing title".to_string()),
+            Some(x) if x.is_empty() => issues.push("empty title".to_string()),
+            _ => {}
+        }
+
+        let description = document
+            .find(Name("meta"))
+            .find(|x| x.attr("name").is_some_and(|x| x.eq_ignore_ascii_case("description")))
+            .map(|x| x.attr("content").unwrap_or_default().trim().to_string());
+
+        match description {
+            None => issues.push("missing meta description".to_string()),
+            Some(x) if x.is_empty() => issues.push("empty meta description".to_string()),
+            _ => {}
+        }
+
+        let headings = document.find(Name("h1")).count();
+
+        if headings > 1 {
+            issues.push(format!("{} h1 headings", headings));
+        }
+
+        Self {
+            url: url.to_string(),
+            title: title.filter(|x| !x.is_empty()),
+            issues,
+        }
+    }
+}
+
+/// Flag the pages sharing their title with other pages.
+pub fn flag_duplicate_titles(audits: &mut [SeoAudit]) {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+
+    for title in audits.iter().filter_map(|x| x.title.clone()) {
+        *counts.entry(title).or_default() += 1;
+    }
+
+    for audit in audits.iter_mut() {
+        let count = audit.title.as_ref().and_then(|x| counts.get(x)).copied();
+
+        if let Some(count) = count.filter(|x| *x > 1) {
+            audit.issues.push(format!("duplicate title ({} pages)", count));
+        }
+    }
+}
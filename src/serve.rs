@@ -0,0 +1,279 @@
+use std::convert::Infallible;
+use std::error::Error;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{HeaderName, HeaderValue, CONTENT_TYPE, LOCATION};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde::Deserialize;
+use tokio::net::TcpListener;
+use url::Url;
+
+use crate::static_dir::{self, StaticDir};
+
+/// The body of the responses of the local file server.
+pub type Body = Full<Bytes>;
+
+/// A middleware of the local file server, simulating the hosting platform behavior.
+pub trait Middleware: Send + Sync {
+    /// Handle a request before looking for a file. Returning a response short-circuits the
+    /// file lookup.
+    fn before(&self, _path: &str) -> Option<Response<Body>> {
+        None
+    }
+
+    /// Transform the response of a request.
+    fn after(&self, _path: &str, _response: &mut Response<Body>) {}
+}
+
+/// Check if a path matches a pattern, supporting a trailing `*` splat.
+fn matches(pattern: &str, path: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => path.starts_with(prefix),
+        None => pattern == path || pattern.trim_end_matches('/') == path.trim_end_matches('/'),
+    }
+}
+
+/// A redirect rule (`_redirects` file or `[[redirects]]` of `netlify.toml`).
+#[derive(Debug, Deserialize)]
+struct Redirect {
+    from: String,
+    to: String,
+    #[serde(default = "default_redirect_status")]
+    status: u16,
+}
+
+fn default_redirect_status() -> u16 {
+    301
+}
+
+/// A header rule (`_headers` file or `[[headers]]` of `netlify.toml`).
+#[derive(Debug)]
+struct Headers {
+    path: String,
+    values: Vec<(String, String)>,
+}
+
+/// Redirect and header rules, following the Netlify/Cloudflare Pages conventions.
+#[derive(Debug, Default)]
+pub struct Rules {
+    redirects: Vec<Redirect>,
+    headers: Vec<Headers>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NetlifyHeaders {
+    #[serde(rename = "for")]
+    path: String,
+    #[serde(default)]
+    values: toml::Table,
+}
+
+#[derive(Debug, Deserialize)]
+struct NetlifyConfig {
+    #[serde(default)]
+    redirects: Vec<Redirect>,
+    #[serde(default)]
+    headers: Vec<NetlifyHeaders>,
+}
+
+impl Rules {
+    /// Load the rules from `_redirects`, `_headers` and `netlify.toml` in the given directory.
+    pub fn load(dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut rules = Self::default();
+
+        if let Ok(content) = fs::read_to_string(dir.join("_redirects")) {
+            for line in content.lines().map(str::trim) {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+
+                if line.starts_with('#') || parts.len() < 2 {
+                    continue;
+                }
+
+                rules.redirects.push(Redirect {
+                    from: parts[0].to_string(),
+                    to: parts[1].to_string(),
+                    status: parts
+                        .get(2)
+                        .and_then(|x| x.trim_end_matches('!').parse().ok())
+                        .unwrap_or_else(default_redirect_status),
+                });
+            }
+        }
+
+        if let Ok(content) = fs::read_to_string(dir.join("_headers")) {
+            for line in content.lines() {
+                if line.trim().is_empty() || line.trim().starts_with('#') {
+                    continue;
+                }
+
+                if !line.starts_with(char::is_whitespace) {
+                    rules.headers.push(Headers {
+                        path: line.trim().to_string(),
+                        values: vec![],
+                    });
+                } else if let (Some(headers), Some((name, value))) =
+                    (rules.headers.last_mut(), line.split_once(':'))
+                {
+                    headers
+                        .values
+                        .push((name.trim().to_string(), value.trim().to_string()));
+                }
+            }
+        }
+
+        if let Ok(content) = fs::read_to_string(dir.join("netlify.toml")) {
+            let config: NetlifyConfig = toml::from_str(&content)?;
+
+            rules.redirects.extend(config.redirects);
+            rules.headers.extend(config.headers.into_iter().map(|x| Headers {
+                path: x.path,
+                values: x
+                    .values
+                    .into_iter()
+                    .map(|(k, v)| (k, v.as_str().map_or(v.to_string(), String::from)))
+                    .collect(),
+            }));
+        }
+
+        Ok(rules)
+    }
+}
+
+impl Middleware for Rules {
+    fn before(&self, path: &str) -> Option<Response<Body>> {
+        let redirect = self.redirects.iter().find(|x| matches(&x.from, path))?;
+        let status = StatusCode::from_u16(redirect.status).ok()?;
+
+        // Rewrites (200) are not simulated, only real redirects.
+        if !status.is_redirection() {
+            return None;
+        }
+
+        let to = match redirect.from.strip_suffix('*') {
+            Some(prefix) => redirect.to.replace(":splat", &path[prefix.len()..]),
+            None => redirect.to.clone(),
+        };
+
+        Response::builder()
+            .status(status)
+            .header(LOCATION, to)
+            .body(Body::default())
+            .ok()
+    }
+
+    fn after(&self, path: &str, response: &mut Response<Body>) {
+        for rule in self.headers.iter().filter(|x| matches(&x.path, path)) {
+            for (name, value) in &rule.values {
+                if let (Ok(name), Ok(value)) = (
+                    HeaderName::from_bytes(name.as_bytes()),
+                    HeaderValue::from_str(value),
+                ) {
+                    response.headers_mut().insert(name, value);
+                }
+            }
+        }
+    }
+}
+
+/// The state shared by every request of the local server.
+struct State {
+    dir: StaticDir,
+    base: Url,
+    middlewares: Vec<Box<dyn Middleware>>,
+}
+
+impl State {
+    /// Serve a request from the directory, through the middlewares.
+    fn handle(&self, request: Request<Incoming>) -> Response<Body> {
+        let path = request.uri().path().to_string();
+
+        if let Some(response) = self.middlewares.iter().find_map(|x| x.before(&path)) {
+            return response;
+        }
+
+        let file = self.base.join(&path).ok().and_then(|x| self.dir.find(&x));
+        let mut response = match file.and_then(|x| fs::read(&x).ok().map(|body| (x, body))) {
+            Some((file, body)) => {
+                let extension = file
+                    .extension()
+                    .and_then(|x| x.to_str())
+                    .unwrap_or_default()
+                    .to_lowercase();
+                let mut response = Response::new(Body::from(body));
+
+                response.headers_mut().insert(
+                    CONTENT_TYPE,
+                    HeaderValue::from_static(static_dir::content_type(&extension)),
+                );
+                response
+            }
+            None => {
+                let mut response = Response::new(Body::default());
+
+                *response.status_mut() = StatusCode::NOT_FOUND;
+                response
+            }
+        };
+
+        for middleware in &self.middlewares {
+            middleware.after(&path, &mut response);
+        }
+
+        response
+    }
+}
+
+/// Serve the directory on a random local port, returning the base URL of the server.
+pub fn spawn(dir: &str, middlewares: Vec<Box<dyn Middleware>>) -> Result<Url, Box<dyn Error>> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+    let listener = std::net::TcpListener::bind(addr)?;
+    let mut base = Url::parse("http://127.0.0.1/")?;
+    let state = Arc::new(State {
+        dir: StaticDir::new(dir, &base),
+        base: base.clone(),
+        middlewares,
+    });
+
+    listener.set_nonblocking(true)?;
+    base.set_port(Some(listener.local_addr()?.port()))
+        .map_err(|_| "cannot set the server port")?;
+
+    let listener = TcpListener::from_std(listener)?;
+
+    info!("Serving {} on {}", dir, base);
+    tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    error!("Local server failed: {}", e);
+                    return;
+                }
+            };
+            let state = Arc::clone(&state);
+            let service = service_fn(move |request| {
+                let state = Arc::clone(&state);
+
+                async move { Ok::<_, Infallible>(state.handle(request)) }
+            });
+
+            tokio::spawn(async move {
+                let connection = http1::Builder::new().serve_connection(TokioIo::new(stream), service);
+
+                if let Err(e) = connection.await {
+                    debug!("Local server connection failed: {}", e);
+                }
+            });
+        }
+    });
+
+    Ok(base)
+}
@@ -0,0 +1,209 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::time::SystemTime;
+
+use futures::future::BoxFuture;
+use futures::{Stream, StreamExt};
+use lettre::message::Mailbox;
+use url::Url;
+
+use crate::email;
+use crate::events::CrawlEvent;
+use crate::history;
+use crate::notify::{Chat, Webhook};
+use crate::report::Report;
+
+/// The formats an --output file can be written in.
+pub const FORMATS: &[&str] = &["json", "html", "csv", "ndjson", "codeclimate", "events"];
+
+/// Writing (or sending) the final report of a crawl.
+pub type Finish<'a> = BoxFuture<'a, Result<(), Box<dyn Error>>>;
+
+/// Where the results of a crawl go: told about the crawl events as they happen, then given the
+/// final report. Several sinks are active at once, a new output being a new sink.
+pub trait OutputSink: Send {
+    /// Handle an event of the crawl (run in the background, while crawling).
+    fn event(&mut self, _event: &CrawlEvent) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    /// Write (or send) the final report.
+    fn finish<'a>(&'a mut self, report: &'a Report<'_>) -> Finish<'a>;
+}
+
+/// An --output file: a format and where to write it (`csv=results.csv`).
+#[derive(Clone, Debug)]
+pub struct Output {
+    pub format: String,
+    pub path: String,
+}
+
+/// Parse an --output file (`<format>=<path>`).
+pub fn parse_output(value: &str) -> Result<Output, String> {
+    match value.split_once('=') {
+        Some((format, path)) if FORMATS.contains(&format) && !path.is_empty() => Ok(Output {
+            format: format.to_string(),
+            path: path.to_string(),
+        }),
+        _ => Err(format!(
+            "invalid output `{}`, expected `<format>=<path>` ({})",
+            value,
+            FORMATS.join(", ")
+        )),
+    }
+}
+
+/// Feed the crawl events to the sinks until the crawl is finished, then give the sinks back
+/// for the final report. A failing sink is warned about once, the others still being fed.
+pub async fn forward(
+    events: impl Stream<Item = CrawlEvent>,
+    mut sinks: Vec<Box<dyn OutputSink>>,
+) -> Vec<Box<dyn OutputSink>> {
+    let mut failed = vec![false; sinks.len()];
+
+    futures::pin_mut!(events);
+
+    while let Some(event) = events.next().await {
+        for (sink, failed) in sinks.iter_mut().zip(failed.iter_mut()) {
+            if let Err(e) = sink.event(&event).map_err(|e| e.to_string()) {
+                if !*failed {
+                    warn!("Cannot write the crawl event: {}", e);
+                }
+
+                *failed = true;
+            }
+        }
+    }
+
+    sinks
+}
+
+/// What is printed on stdout at the end of the crawl.
+pub enum Console {
+    Summary,
+
+    /// The changes since the --baseline run (--diff).
+    Changes,
+
+    /// The results in a --format.
+    Results(String),
+}
+
+impl OutputSink for Console {
+    fn finish<'a>(&'a mut self, report: &'a Report<'_>) -> Finish<'a> {
+        let result = match self {
+            Self::Results(format) => report.print_results(format).map_err(|e| e.to_string()),
+            Self::Summary => {
+                report.print_summary();
+                Ok(())
+            }
+            Self::Changes => {
+                report.print_changes();
+                Ok(())
+            }
+        };
+
+        Box::pin(async { Ok(result?) })
+    }
+}
+
+/// A report written into a file (--report, --html-report and --output).
+///
+/// The `events` format streams the crawl events instead, one JSON object per line.
+pub struct FileSink {
+    output: Output,
+    events: Option<BufWriter<File>>,
+}
+
+impl FileSink {
+    pub fn new(output: Output) -> Result<Self, Box<dyn Error>> {
+        let events = match output.format.as_str() {
+            "events" => Some(BufWriter::new(File::create(&output.path)?)),
+            _ => None,
+        };
+
+        Ok(Self { output, events })
+    }
+}
+
+impl OutputSink for FileSink {
+    fn event(&mut self, event: &CrawlEvent) -> Result<(), Box<dyn Error>> {
+        if let Some(file) = &mut self.events {
+            serde_json::to_writer(&mut *file, event)?;
+            file.write_all(b"\n")?;
+        }
+
+        Ok(())
+    }
+
+    fn finish<'a>(&'a mut self, report: &'a Report<'_>) -> Finish<'a> {
+        let path = self.output.path.as_str();
+        let result = match (self.output.format.as_str(), &mut self.events) {
+            (_, Some(file)) => file.flush().map_err(|e| e.to_string()),
+            ("json", _) => report.write_json(path).map_err(|e| e.to_string()),
+            ("html", _) => report.write_html(path).map_err(|e| e.to_string()),
+            (format, _) => File::create(path)
+                .map_err(|e| e.to_string())
+                .and_then(|x| {
+                    report
+                        .write_results(format, &mut BufWriter::new(x))
+                        .map_err(|e| e.to_string())
+                }),
+        };
+
+        Box::pin(async move { Ok(result.map_err(|e| format!("{}: {}", path, e))?) })
+    }
+}
+
+impl OutputSink for Webhook {
+    fn finish<'a>(&'a mut self, report: &'a Report<'_>) -> Finish<'a> {
+        Box::pin(Webhook::finish(self, report))
+    }
+}
+
+/// A chat notification (--notify), linking to the published report.
+pub struct Notification {
+    pub chat: Chat,
+    pub report_url: Option<Url>,
+}
+
+impl OutputSink for Notification {
+    fn finish<'a>(&'a mut self, report: &'a Report<'_>) -> Finish<'a> {
+        Box::pin(self.chat.send(report, self.report_url.as_ref()))
+    }
+}
+
+/// The summary emailed once the crawl is done (--email-to), the HTML report attached.
+pub struct Email {
+    pub from: Mailbox,
+    pub to: Vec<Mailbox>,
+    pub smtp: String,
+    pub attachment: Option<String>,
+}
+
+impl OutputSink for Email {
+    fn finish<'a>(&'a mut self, report: &'a Report<'_>) -> Finish<'a> {
+        let message = email::message(&self.from, &self.to, report, self.attachment.as_deref())
+            .map_err(|e| e.to_string());
+
+        Box::pin(async move { email::send(&self.smtp, message?).await })
+    }
+}
+
+/// The results stored into a SQLite database (--db).
+pub struct Database {
+    pub path: String,
+    pub started_at: SystemTime,
+}
+
+impl OutputSink for Database {
+    fn finish<'a>(&'a mut self, report: &'a Report<'_>) -> Finish<'a> {
+        let entrypoints = report.entrypoints.join(" ");
+        let result = history::record(&self.path, &entrypoints, self.started_at, &report.results)
+            .map(|run| info!("Results stored into {} (run {})", self.path, run))
+            .map_err(|e| e.to_string());
+
+        Box::pin(async { Ok(result?) })
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::graph::xml_escape;
+use crate::report::{Entry, Verdict};
+
+/// The most URLs a sitemap can list.
+const MAX_URLS: usize = 50_000;
+
+/// Get the URLs of the sitemap: the final URL of each indexable page, unless it failed.
+///
+/// The pages left out of the results (--failures-only) answered 2xx without being redirected.
+fn urls<'a>(indexable: &'a HashSet<String>, results: &'a [Entry]) -> BTreeSet<&'a str> {
+    let entries: HashMap<&str, &Entry> = results.iter().map(|x| (x.url.as_str(), x)).collect();
+
+    indexable
+        .iter()
+        .filter_map(|url| match entries.get(url.as_str()) {
+            Some(x) if (200..300).contains(&x.status) && x.verdict != Verdict::Failure => {
+                Some(x.redirects.last().unwrap_or(&x.url).as_str())
+            }
+            Some(_) => None,
+            None => Some(url.as_str()),
+        })
+        .collect()
+}
+
+/// Write the sitemap of the indexable pages into the given file. Past 50,000 URLs, the next ones
+/// are left out, a sitemap being limited to that.
+pub fn write(
+    indexable: &HashSet<String>,
+    results: &[Entry],
+    path: &str,
+) -> Result<(), Box<dyn Error>> {
+    let urls = urls(indexable, results);
+    let mut out = BufWriter::new(File::create(path)?);
+
+    if urls.len() > MAX_URLS {
+        warn!("{} pages found, only the first {} are in the sitemap", urls.len(), MAX_URLS);
+    }
+
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(out, r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#)?;
+
+    for url in urls.iter().take(MAX_URLS) {
+        writeln!(out, "  <url><loc>{}</loc></url>", xml_escape(url))?;
+    }
+
+    writeln!(out, "</urlset>")?;
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indexable_pages() {
+        let mut redirected = Entry::page("http://example.com/old", 200);
+        let indexable = ["/", "/old", "/missing", "/a?b&c", "/omitted"]
+            .iter()
+            .map(|x| format!("http://example.com{}", x))
+            .collect();
+
+        redirected.redirects = vec!["http://example.com/new".to_string()];
+
+        let results = [
+            Entry::page("http://example.com/", 200),
+            redirected,
+            Entry::page("http://example.com/missing", 404),
+            Entry::page("http://example.com/a?b&c", 200),
+            // A `noindex` page, never indexable.
+            Entry::page("http://example.com/private", 200),
+            // `/omitted` is left out of the results, as with --failures-only.
+        ];
+
+        assert_eq!(
+            urls(&indexable, &results).into_iter().collect::<Vec<_>>(),
+            [
+                "http://example.com/",
+                "http://example.com/a?b&c",
+                "http://example.com/new",
+                "http://example.com/omitted",
+            ]
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use percent_encoding::percent_decode_str;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::StatusCode;
+use url::Url;
+
+/// A local build directory, served as if it was deployed on `base`.
+#[derive(Debug)]
+pub struct StaticDir {
+    root: PathBuf,
+    base: Url,
+}
+
+impl StaticDir {
+    /// Create a new static directory, deployed on the given base URL.
+    pub fn new(root: &str, base: &Url) -> Self {
+        Self {
+            root: PathBuf::from(root),
+            base: base.clone(),
+        }
+    }
+
+    /// Check if the URL is served by this directory (same origin, under the base path).
+    pub fn serves(&self, url: &Url) -> bool {
+        url.origin() == self.base.origin() && url.path().starts_with(self.base.path())
+    }
+
+    /// Find the file matching the URL: `index.html` for directories, and `<path>.html` for
+    /// extension-less paths (pretty URLs).
+    pub fn find(&self, url: &Url) -> Option<PathBuf> {
+        let relative = url.path().strip_prefix(self.base.path()).unwrap_or_default();
+        let mut path = self.root.clone();
+
+        for segment in relative.split('/').filter(|x| !x.is_empty()) {
+            let segment = percent_decode_str(segment).decode_utf8().ok()?;
+
+            if segment == ".." {
+                return None;
+            }
+
+            path.push(segment.as_ref());
+        }
+
+        if path.is_dir() {
+            path.push("index.html");
+        } else if !path.exists() && path.extension().is_none() {
+            path.set_extension("html");
+        }
+
+        Some(path).filter(|x| x.is_file())
+    }
+
+    /// Build a response for the URL from the filesystem, with a guessed `Content-Type`.
+    pub fn response(&self, url: &Url) -> Result<reqwest::Response, Box<dyn Error>> {
+        let path = match self.find(url) {
+            Some(path) => path,
+            None => {
+                return Ok(http::Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(vec![])?
+                    .into())
+            }
+        };
+
+        let extension = path
+            .extension()
+            .and_then(|x| x.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+
+        Ok(http::Response::builder()
+            .status(StatusCode::OK)
+            .header(CONTENT_TYPE, content_type(&extension))
+            .body(fs::read(&path)?)?
+            .into())
+    }
+}
+
+/// Guess a content type from a file extension.
+pub fn content_type(extension: &str) -> &'static str {
+    match extension {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css",
+        "js" | "mjs" => "application/javascript",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "txt" => "text/plain",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "pdf" => "application/pdf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::Serialize;
+use url::Url;
+
+use crate::report::Entry;
+
+/// The minimal number of URLs in a family before suggesting to exclude it.
+const MIN_FAMILY_SIZE: usize = 50;
+
+/// The minimal share of a family answering with the same status (near-identical results).
+const MIN_SAME_STATUS: f64 = 0.9;
+
+/// Query parameters names commonly holding session identifiers.
+const SESSION_PARAMS: &[&str] = &["sid", "session", "sessionid", "phpsessid", "jsessionid"];
+
+/// An exclusion pattern suggested after a crawl.
+#[derive(Debug, Serialize)]
+pub struct Suggestion {
+    /// A regex for `--exclude`.
+    pub pattern: String,
+
+    /// The number of crawled URLs matching it.
+    pub count: usize,
+
+    pub reason: String,
+}
+
+/// Get the placeholder of a path segment looking like a variable: a number, a date or an
+/// identifier.
+fn placeholder(segment: &str) -> Option<&'static str> {
+    let digits = segment.chars().filter(char::is_ascii_digit).count();
+    let hex = segment.chars().all(|x| x.is_ascii_hexdigit() || x == '-');
+
+    match segment {
+        "" => None,
+        _ if digits == segment.len() => Some("{n}"),
+        _ if digits >= 4 && segment.chars().all(|x| x.is_ascii_digit() || x == '-') => {
+            Some("{date}")
+        }
+        _ if hex && segment.len() >= 16 => Some("{id}"),
+        _ => None,
+    }
+}
+
+/// Check if a path segment looks like a variable.
+fn is_variable(segment: &str) -> bool {
+    placeholder(segment).is_some()
+}
+
+/// Build the template of an URL, its variable path segments and query values being replaced by
+/// placeholders (e.g. `https://example.com/events/{date}?view={}`).
+///
+/// URLs without variable parts have no template.
+pub fn template(url: &Url) -> Option<String> {
+    let mut template = url[..url::Position::BeforePath].to_string();
+    let mut variable = false;
+
+    for segment in url.path_segments()? {
+        template.push('/');
+
+        match placeholder(segment) {
+            Some(placeholder) => {
+                template.push_str(placeholder);
+                variable = true;
+            }
+            None => template.push_str(segment),
+        }
+    }
+
+    let mut names: Vec<String> = url.query_pairs().map(|(x, _)| x.into_owned()).collect();
+
+    names.sort();
+    names.dedup();
+
+    if !names.is_empty() {
+        let query: Vec<String> = names.iter().map(|x| format!("{}={{}}", x)).collect();
+
+        template.push('?');
+        template.push_str(&query.join("&"));
+        variable = true;
+    }
+
+    Some(template).filter(|_| variable)
+}
+
+/// Build the family pattern of an URL: variable path segments are replaced by wildcards.
+fn family(url: &Url) -> Option<String> {
+    let mut pattern = regex::escape(&url[..url::Position::BeforePath]);
+    let mut variable = false;
+
+    for segment in url.path_segments()? {
+        pattern.push('/');
+
+        if is_variable(segment) {
+            pattern.push_str("[^/]+");
+            variable = true;
+        } else {
+            pattern.push_str(&regex::escape(segment));
+        }
+    }
+
+    Some(format!("^{}", pattern)).filter(|_| variable)
+}
+
+/// Analyze the results for URL families producing many near-identical pages (calendars,
+/// paginations...) and for session identifiers in query strings, and suggest exclusions.
+pub fn suggestions(results: &[Entry]) -> Vec<Suggestion> {
+    let mut families: HashMap<String, Vec<u16>> = HashMap::new();
+    let mut params: BTreeMap<String, HashSet<String>> = BTreeMap::new();
+    let mut suggestions = vec![];
+
+    for entry in results {
+        let url = match Url::parse(&entry.url) {
+            Ok(url) => url,
+            Err(_) => continue,
+        };
+
+        if let Some(family) = family(&url) {
+            families.entry(family).or_default().push(entry.status);
+        }
+
+        for (name, value) in url.query_pairs() {
+            params
+                .entry(name.to_lowercase())
+                .or_default()
+                .insert(value.into_owned());
+        }
+    }
+
+    for (pattern, statuses) in families {
+        if statuses.len() < MIN_FAMILY_SIZE {
+            continue;
+        }
+
+        let mut counts: HashMap<u16, usize> = HashMap::new();
+        statuses.iter().for_each(|x| *counts.entry(*x).or_default() += 1);
+
+        let (status, same) = counts.into_iter().max_by_key(|(_, x)| *x).unwrap_or_default();
+
+        if same as f64 / statuses.len() as f64 >= MIN_SAME_STATUS {
+            suggestions.push(Suggestion {
+                pattern,
+                count: statuses.len(),
+                reason: format!("near-identical URLs, {} of them answering {}", same, status),
+            });
+        }
+    }
+
+    for (name, values) in params {
+        if SESSION_PARAMS.contains(&name.as_str()) && values.len() > 1 {
+            suggestions.push(Suggestion {
+                pattern: format!("(?i)[?&]{}=", regex::escape(&name)),
+                count: values.len(),
+                reason: "session identifier in the query string".to_string(),
+            });
+        }
+    }
+
+    suggestions.sort_by(|a, b| b.count.cmp(&a.count).then(a.pattern.cmp(&b.pattern)));
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    #[test]
+    fn placeholders() {
+        assert_eq!(placeholder("2024"), Some("{n}"));
+        assert_eq!(placeholder("2024-01-31"), Some("{date}"));
+        assert_eq!(placeholder("0123456789abcdef"), Some("{id}"));
+        assert_eq!(placeholder("3f2a8c1e-9b4d-4e7a-8c6f-1d2e3f4a5b6c"), Some("{id}"));
+        assert_eq!(placeholder("deadbeef"), None);
+        assert_eq!(placeholder("page-2"), None);
+        assert_eq!(placeholder("about"), None);
+        assert_eq!(placeholder(""), None);
+    }
+
+    #[test]
+    fn templates() {
+        let template = |x| template(&url(x));
+
+        assert_eq!(
+            template("https://example.com/events/2024-01-31/42?view=week&a=1&view=day").as_deref(),
+            Some("https://example.com/events/{date}/{n}?a={}&view={}")
+        );
+        assert_eq!(template("https://example.com/blog/"), None);
+        assert_eq!(template("https://example.com/"), None);
+    }
+
+    #[test]
+    fn families() {
+        assert_eq!(
+            family(&url("https://example.com/calendar/2024-01-31/day.html")).as_deref(),
+            Some("^https://example\\.com/calendar/[^/]+/day\\.html")
+        );
+        assert_eq!(family(&url("https://example.com/about?id=1")), None);
+    }
+
+    #[test]
+    fn near_identical_families() {
+        let mut results: Vec<Entry> = (0..60)
+            .map(|x| Entry::page(&format!("https://example.com/calendar/{}", 2000 + x), 200))
+            .collect();
+
+        // Too few to matter.
+        results.extend((0..10).map(|x| Entry::page(&format!("https://example.com/p/{}", x), 200)));
+        // As many, but not answering the same.
+        results.extend((0..60).map(|x| {
+            let status = if x % 2 == 0 { 200 } else { 404 };
+
+            Entry::page(&format!("https://example.com/items/{}", x), status)
+        }));
+
+        let suggestions = suggestions(&results);
+
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].pattern, "^https://example\\.com/calendar/[^/]+");
+        assert_eq!(suggestions[0].count, 60);
+        assert_eq!(suggestions[0].reason, "near-identical URLs, 60 of them answering 200");
+        assert!(regex::Regex::new(&suggestions[0].pattern).unwrap().is_match(&results[0].url));
+    }
+
+    #[test]
+    fn session_parameters() {
+        let results = [
+            Entry::page("https://example.com/a?PHPSESSID=1", 200),
+            Entry::page("https://example.com/b?phpsessid=2&page=1", 200),
+            Entry::page("https://example.com/c?page=2", 200),
+            // A single value is no session.
+            Entry::page("https://example.com/d?sid=1", 200),
+        ];
+        let suggestions = suggestions(&results);
+
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].pattern, "(?i)[?&]phpsessid=");
+        assert_eq!(suggestions[0].count, 2);
+    }
+}
@@ -0,0 +1,42 @@
+use std::error::Error;
+use std::fs;
+
+use openssl::pkcs12::Pkcs12;
+use openssl::pkey::PKey;
+use openssl::x509::X509;
+use reqwest::{Certificate, Identity};
+
+/// Load a client identity (mTLS) from a PEM certificate and its PEM private key.
+///
+/// The native TLS backend only accepts PKCS #12 archives, so both are bundled into one.
+pub fn identity(cert: &str, key: &str) -> Result<Identity, Box<dyn Error>> {
+    let cert = X509::from_pem(&fs::read(cert)?)?;
+    let key = PKey::private_key_from_pem(&fs::read(key)?)?;
+    let archive = Pkcs12::builder()
+        .name("client")
+        .pkey(&key)
+        .cert(&cert)
+        .build2("")?;
+
+    Ok(Identity::from_pkcs12_der(&archive.to_der()?, "")?)
+}
+
+/// A bundle of trusted root certificates.
+#[derive(Clone, Debug)]
+pub struct Bundle(pub Vec<Certificate>);
+
+/// Load every certificate of a PEM file (a private CA or a whole bundle).
+pub fn bundle(path: &str) -> Result<Bundle, String> {
+    let load = || -> Result<Vec<Certificate>, Box<dyn Error>> {
+        X509::stack_from_pem(&fs::read(path)?)?
+            .iter()
+            .map(|x| Ok(Certificate::from_der(&x.to_der()?)?))
+            .collect()
+    };
+
+    match load() {
+        Ok(certs) if !certs.is_empty() => Ok(Bundle(certs)),
+        Ok(_) => Err(format!("no certificate found in {}", path)),
+        Err(e) => Err(format!("cannot load {}: {}", path, e)),
+    }
+}
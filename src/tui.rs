@@ -0,0 +1,281 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::{Stream, StreamExt};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Gauge, List, ListItem, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use url::Url;
+
+use crate::events::CrawlEvent;
+use crate::logging;
+use crate::report::percentile;
+
+/// How often the dashboard is redrawn, and the keyboard read.
+const TICK: Duration = Duration::from_millis(250);
+
+/// How many failures are kept, the oldest ones scrolling out.
+const RECENT_FAILURES: usize = 500;
+
+/// A failed URL, as listed on the dashboard.
+#[derive(Debug)]
+struct Failure {
+    url: String,
+    status: u16,
+    error: Option<String>,
+}
+
+/// What the dashboard shows, updated from the crawl events.
+#[derive(Debug)]
+struct Dashboard {
+    started: Instant,
+    queued: usize,
+    crawled: usize,
+    statuses: BTreeMap<u16, usize>,
+    failures: VecDeque<Failure>,
+
+    /// The response times of every host, in milliseconds.
+    hosts: HashMap<String, Vec<u64>>,
+}
+
+impl Dashboard {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            queued: 0,
+            crawled: 0,
+            statuses: BTreeMap::new(),
+            failures: VecDeque::new(),
+            hosts: HashMap::new(),
+        }
+    }
+
+    fn update(&mut self, event: CrawlEvent) {
+        let (url, status, time_ms) = match event {
+            CrawlEvent::Queued { .. } => {
+                self.queued += 1;
+                return;
+            }
+            CrawlEvent::Fetched {
+                url,
+                status,
+                time_ms,
+                ..
+            } => (url, status, time_ms),
+            CrawlEvent::Failed {
+                url,
+                status,
+                error,
+                time_ms,
+            } => {
+                if self.failures.len() == RECENT_FAILURES {
+                    self.failures.pop_front();
+                }
+
+                self.failures.push_back(Failure {
+                    url: url.clone(),
+                    status,
+                    error,
+                });
+
+                (url, status, time_ms)
+            }
+            CrawlEvent::Finished => return,
+        };
+
+        self.crawled += 1;
+        *self.statuses.entry(status).or_default() += 1;
+
+        let host = Url::parse(&url).ok().and_then(|x| x.host_str().map(str::to_string));
+
+        if let (Some(host), Some(time)) = (host, time_ms) {
+            self.hosts.entry(host).or_default().push(time);
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, middle, failures] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Percentage(40),
+            Constraint::Min(5),
+        ])
+        .areas(frame.area());
+        let [statuses, hosts] =
+            Layout::horizontal([Constraint::Length(28), Constraint::Min(40)]).areas(middle);
+
+        self.draw_header(frame, header);
+        self.draw_statuses(frame, statuses);
+        self.draw_hosts(frame, hosts);
+        self.draw_failures(frame, failures);
+    }
+
+    fn draw_header(&self, frame: &mut Frame, area: Rect) {
+        let elapsed = self.started.elapsed();
+        let rate = self.crawled as f64 / elapsed.as_secs_f64().max(0.001);
+        let depth = self.queued.saturating_sub(self.crawled);
+        let title = format!(
+            " {} crawled, {} in the queue, {:.1} req/s, {}s elapsed (q to close) ",
+            self.crawled,
+            depth,
+            rate,
+            elapsed.as_secs()
+        );
+        let ratio = match self.queued {
+            0 => 0.0,
+            queued => self.crawled as f64 / queued as f64,
+        };
+        let gauge = Gauge::default()
+            .block(Block::bordered().title(title))
+            .gauge_style(Style::default().fg(Color::Green))
+            .ratio(ratio.min(1.0));
+
+        frame.render_widget(gauge, area);
+    }
+
+    fn draw_statuses(&self, frame: &mut Frame, area: Rect) {
+        let rows = self.statuses.iter().map(|(status, count)| {
+            let label = match status {
+                0 => "no response".to_string(),
+                status => status.to_string(),
+            };
+            let color = match status {
+                200..=299 => Color::Green,
+                300..=399 => Color::Yellow,
+                _ => Color::Red,
+            };
+
+            Row::new(vec![label, count.to_string()]).style(Style::default().fg(color))
+        });
+        let table = Table::new(rows, [Constraint::Length(12), Constraint::Min(8)])
+            .header(Row::new(vec!["Status", "Count"]).bold())
+            .block(Block::bordered().title(" Statuses "));
+
+        frame.render_widget(table, area);
+    }
+
+    fn draw_hosts(&mut self, frame: &mut Frame, area: Rect) {
+        let mut hosts: Vec<(&String, &mut Vec<u64>)> = self.hosts.iter_mut().collect();
+
+        hosts.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(b.0)));
+
+        let rows = hosts.into_iter().map(|(host, times)| {
+            times.sort_unstable();
+
+            Row::new(vec![
+                host.clone(),
+                times.len().to_string(),
+                format!("{}ms", percentile(times, 50)),
+                format!("{}ms", percentile(times, 95)),
+            ])
+        });
+        let widths = [
+            Constraint::Min(20),
+            Constraint::Length(9),
+            Constraint::Length(9),
+            Constraint::Length(9),
+        ];
+        let table = Table::new(rows, widths)
+            .header(Row::new(vec!["Host", "Requests", "p50", "p95"]).bold())
+            .block(Block::bordered().title(" Response times "));
+
+        frame.render_widget(table, area);
+    }
+
+    fn draw_failures(&self, frame: &mut Frame, area: Rect) {
+        // The most recent failures are at the bottom, scrolling up the older ones.
+        let visible = area.height.saturating_sub(2) as usize;
+        let items: Vec<ListItem> = self
+            .failures
+            .iter()
+            .skip(self.failures.len().saturating_sub(visible))
+            .map(|x| {
+                let line = match &x.error {
+                    Some(error) => format!("{:>3} {} ({})", x.status, x.url, error),
+                    None => format!("{:>3} {}", x.status, x.url),
+                };
+
+                ListItem::new(Line::from(line).red())
+            })
+            .collect();
+        let title = format!(" Recent failures ({}) ", self.failures.len());
+
+        frame.render_widget(List::new(items).block(Block::bordered().title(title)), area);
+    }
+}
+
+/// Read the keys pressed, telling whether the dashboard is closed (`q` or `Esc`).
+///
+/// The terminal being in raw mode, `Ctrl-C` is a key too: the crawl is then interrupted (and the
+/// dashboard closed), as on `SIGINT`.
+fn closed(interrupted: &AtomicBool) -> io::Result<bool> {
+    while event::poll(Duration::ZERO)? {
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                interrupted.store(true, Ordering::Relaxed);
+                return Ok(true);
+            }
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(true),
+            _ => {}
+        }
+    }
+
+    Ok(false)
+}
+
+fn restore(terminal: &mut DefaultTerminal) -> io::Result<()> {
+    terminal.clear()?;
+    ratatui::restore();
+    Ok(())
+}
+
+/// Show the dashboard of a crawl until it is finished (or closed), the logs being muted
+/// meanwhile.
+pub async fn show(
+    events: impl Stream<Item = CrawlEvent>,
+    interrupted: Arc<AtomicBool>,
+) -> io::Result<()> {
+    let mut terminal = ratatui::try_init()?;
+    let mut dashboard = Dashboard::new();
+    let mut drawn_at: Option<Instant> = None;
+
+    logging::mute(true);
+    futures::pin_mut!(events);
+
+    let result = loop {
+        match tokio::time::timeout(TICK, events.next()).await {
+            Ok(Some(CrawlEvent::Finished)) | Ok(None) => break Ok(()),
+            Ok(Some(event)) => dashboard.update(event),
+            Err(_) => {}
+        }
+
+        if drawn_at.is_some_and(|x| x.elapsed() < TICK) {
+            continue;
+        }
+
+        match closed(&interrupted) {
+            Ok(false) => {}
+            result => break result.map(drop),
+        }
+
+        if let Err(e) = terminal.draw(|frame| dashboard.draw(frame)) {
+            break Err(e);
+        }
+
+        drawn_at = Some(Instant::now());
+    };
+
+    restore(&mut terminal)?;
+    logging::mute(false);
+    result
+}
@@ -0,0 +1,397 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use futures::future::BoxFuture;
+use hyper_util::client::legacy::connect::Connection;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::tls::TlsInfo;
+use reqwest::{Request, Response};
+use serde::Serialize;
+use tower::{Layer, Service};
+
+use crate::middleware::Middleware;
+
+/// How many throughput values the summary lists at most, the seconds being grouped to fit.
+const MAX_TIMELINE: usize = 20;
+
+/// Count the network resources used by a crawl, from the requests going through the network.
+///
+/// The clients resolve hosts through it (see `Resolve`) and open their connections through its
+/// `Connections` layer, so DNS lookups, connections and TLS handshakes are counted as they
+/// happen. The bodies are counted as they are read (see `received`).
+#[derive(Debug, Default)]
+pub struct Usage {
+    requests: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    hosts: Mutex<HashSet<(String, u16, bool)>>,
+    dns_lookups: AtomicU64,
+    connections: AtomicU64,
+    tls_handshakes: AtomicU64,
+
+    /// When the first request has been answered.
+    started: OnceLock<Instant>,
+
+    /// How many requests have been answered during every second since the first one.
+    seconds: Mutex<Vec<u64>>,
+    per_host: Mutex<HashMap<String, HostCounters>>,
+}
+
+/// The requests of a host, and when the first and last ones have been answered.
+#[derive(Debug)]
+struct HostCounters {
+    requests: u64,
+    bytes_received: u64,
+    first: Instant,
+    last: Instant,
+}
+
+/// The throughput achieved with a host.
+#[derive(Debug, Serialize)]
+pub struct HostUsage {
+    pub host: String,
+    pub requests: u64,
+    pub bytes_received: u64,
+
+    /// The requests per second, between the first and the last request of the host (a second
+    /// at least, for the hosts requested once).
+    pub requests_per_second: f64,
+}
+
+/// The resources used by a crawl, as reported.
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub requests: u64,
+    pub hosts: usize,
+    pub tls_hosts: usize,
+
+    /// The connections opened (over TCP, HTTP/3 ones aside), and the TLS handshakes among them.
+    pub connections: u64,
+    pub tls_handshakes: u64,
+    pub dns_lookups: u64,
+    pub bytes_sent: u64,
+
+    /// The status lines and headers of the responses, and the bodies read.
+    pub bytes_received: u64,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_time_ms: Option<u128>,
+
+    /// How long requests have been answered for, from the first one to the last one.
+    pub duration_ms: u128,
+    pub requests_per_second: f64,
+    pub bytes_per_second: f64,
+
+    /// The requests per second over time, averaged over windows of `interval_s` seconds.
+    pub timeline: Vec<f64>,
+    pub interval_s: usize,
+
+    /// The hosts, the most requested first.
+    pub per_host: Vec<HostUsage>,
+}
+
+/// Get a rate per second, over a duration in seconds.
+fn rate(count: u64, seconds: f64) -> f64 {
+    count as f64 / seconds.max(0.001)
+}
+
+/// Get the requests per second over time, from the requests of every second (grouped by
+/// windows of the same count of seconds to fit), and the count of seconds of a window.
+fn timeline(seconds: &[u64]) -> (Vec<f64>, usize) {
+    let interval = seconds.len().div_ceil(MAX_TIMELINE).max(1);
+    let timeline = seconds
+        .chunks(interval)
+        .map(|x| rate(x.iter().sum(), interval as f64))
+        .collect();
+
+    (timeline, interval)
+}
+
+/// Estimate the size of a header block (`Name: value\r\n` lines and the final `\r\n`).
+fn headers_size(headers: &reqwest::header::HeaderMap) -> u64 {
+    let size: usize = headers
+        .iter()
+        .map(|(k, v)| k.as_str().len() + v.len() + 4)
+        .sum();
+
+    size as u64 + 2
+}
+
+impl Usage {
+    /// Take a snapshot of the counters.
+    pub fn summary(&self) -> Summary {
+        let hosts = self.hosts.lock().unwrap();
+        let requests = self.requests.load(Ordering::Relaxed);
+        let bytes_received = self.bytes_received.load(Ordering::Relaxed);
+        let per_host = self.per_host.lock().unwrap();
+        let duration = per_host
+            .values()
+            .map(|x| x.last)
+            .max()
+            .zip(self.started.get())
+            .map_or(0.0, |(last, first)| (last - *first).as_secs_f64());
+        let (timeline, interval) = timeline(&self.seconds.lock().unwrap());
+        let mut hosts_usage: Vec<HostUsage> = per_host
+            .iter()
+            .map(|(host, x)| HostUsage {
+                host: host.clone(),
+                requests: x.requests,
+                bytes_received: x.bytes_received,
+                requests_per_second: rate(x.requests, (x.last - x.first).as_secs_f64().max(1.0)),
+            })
+            .collect();
+
+        hosts_usage.sort_by(|a, b| b.requests.cmp(&a.requests).then_with(|| a.host.cmp(&b.host)));
+
+        Summary {
+            requests,
+            hosts: hosts.len(),
+            tls_hosts: hosts.iter().filter(|(_, _, tls)| *tls).count(),
+            connections: self.connections.load(Ordering::Relaxed),
+            tls_handshakes: self.tls_handshakes.load(Ordering::Relaxed),
+            dns_lookups: self.dns_lookups.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received,
+            cpu_time_ms: cpu_time_ms(),
+            duration_ms: (duration * 1000.0) as u128,
+            requests_per_second: rate(requests, duration),
+            bytes_per_second: rate(bytes_received, duration),
+            timeline,
+            interval_s: interval,
+            per_host: hosts_usage,
+        }
+    }
+
+    /// Count an answered request in the throughput of its second, and of its host.
+    fn count(&self, host: &str, bytes: u64) {
+        let now = Instant::now();
+        let second = (now - *self.started.get_or_init(|| now)).as_secs() as usize;
+        let mut seconds = self.seconds.lock().unwrap();
+
+        if seconds.len() <= second {
+            seconds.resize(second + 1, 0);
+        }
+
+        seconds[second] += 1;
+
+        let mut per_host = self.per_host.lock().unwrap();
+        let counters = per_host.entry(host.to_string()).or_insert(HostCounters {
+            requests: 0,
+            bytes_received: 0,
+            first: now,
+            last: now,
+        });
+
+        counters.requests += 1;
+        counters.bytes_received += bytes;
+        counters.last = now;
+    }
+
+    /// Count the bytes of a body read from a host, as it is read.
+    pub fn received(&self, host: &str, bytes: u64) {
+        self.bytes_received.fetch_add(bytes, Ordering::Relaxed);
+
+        if let Some(counters) = self.per_host.lock().unwrap().get_mut(host) {
+            counters.bytes_received += bytes;
+        }
+    }
+
+    /// List the hosts (and ports) contacted over TLS, sorted.
+    pub fn tls_hosts(&self) -> Vec<(String, u16)> {
+        let mut hosts: Vec<(String, u16)> = self
+            .hosts
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, _, tls)| *tls)
+            .map(|(host, port, _)| (host.clone(), *port))
+            .collect();
+
+        hosts.sort();
+        hosts
+    }
+}
+
+impl Middleware for Usage {
+    fn after(&self, request: &Request, response: &mut Response) {
+        let url = request.url();
+        // Request line (`GET /path HTTP/1.1\r\n`), `Host` header and the other headers.
+        let sent = request.method().as_str().len()
+            + url[url::Position::BeforePath..url::Position::AfterQuery].len()
+            + url.host_str().unwrap_or_default().len()
+            + 19;
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.bytes_sent.fetch_add(
+            sent as u64 + headers_size(request.headers()),
+            Ordering::Relaxed,
+        );
+        // Status line (`HTTP/1.1 200 OK\r\n`) and headers, the body being counted as it is read.
+        let received = 17 + headers_size(response.headers());
+
+        self.bytes_received.fetch_add(received, Ordering::Relaxed);
+        self.count(url.host_str().unwrap_or_default(), received);
+        self.hosts.lock().unwrap().insert((
+            url.host_str().unwrap_or_default().to_string(),
+            url.port_or_known_default().unwrap_or_default(),
+            url.scheme() == "https",
+        ));
+    }
+}
+
+impl Resolve for Usage {
+    /// Resolve a host with the system resolver (as reqwest does by default), counting the lookup.
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+
+        self.dns_lookups.fetch_add(1, Ordering::Relaxed);
+
+        Box::pin(async move {
+            let addresses: Vec<_> = tokio::net::lookup_host((host, 0)).await?.collect();
+
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// A connector layer counting the connections opened, and the TLS handshakes among them (told
+/// apart by their TLS information, the clients being built with `tls_info`).
+#[derive(Clone, Debug)]
+pub struct Connections(pub Arc<Usage>);
+
+impl<S> Layer<S> for Connections {
+    type Service = Connecting<S>;
+
+    fn layer(&self, inner: S) -> Connecting<S> {
+        Connecting {
+            inner,
+            usage: self.0.clone(),
+        }
+    }
+}
+
+/// A connector counting the connections it opens.
+#[derive(Clone, Debug)]
+pub struct Connecting<S> {
+    inner: S,
+    usage: Arc<Usage>,
+}
+
+impl<S, R> Service<R> for Connecting<S>
+where
+    S: Service<R>,
+    S::Response: Connection,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<S::Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, destination: R) -> Self::Future {
+        let connecting = self.inner.call(destination);
+        let usage = self.usage.clone();
+
+        Box::pin(async move {
+            let connection = connecting.await?;
+            let mut extras = http::Extensions::new();
+
+            connection.connected().get_extras(&mut extras);
+            usage.connections.fetch_add(1, Ordering::Relaxed);
+
+            if extras.get::<TlsInfo>().is_some() {
+                usage.tls_handshakes.fetch_add(1, Ordering::Relaxed);
+            }
+
+            Ok(connection)
+        })
+    }
+}
+
+/// Get the CPU time (user and system) used by the process so far.
+#[cfg(unix)]
+fn cpu_time_ms() -> Option<u128> {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return None;
+    }
+
+    let ms = |x: libc::timeval| x.tv_sec as u128 * 1000 + x.tv_usec as u128 / 1000;
+
+    Some(ms(usage.ru_utime) + ms(usage.ru_stime))
+}
+
+#[cfg(not(unix))]
+fn cpu_time_ms() -> Option<u128> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates() {
+        assert_eq!(rate(10, 4.0), 2.5);
+        // Requests answered at once do not divide by zero.
+        assert_eq!(rate(3, 0.0), 3000.0);
+    }
+
+    #[test]
+    fn timeline_windows() {
+        assert_eq!(timeline(&[]), (vec![], 1));
+        assert_eq!(timeline(&[4, 2, 0]), (vec![4.0, 2.0, 0.0], 1));
+
+        // 45 seconds are grouped 3 by 3, the last window being averaged over 3 seconds too.
+        let mut seconds = vec![3; 44];
+
+        seconds.push(6);
+        seconds[0] = 0;
+
+        let (values, interval) = timeline(&seconds);
+
+        assert_eq!(interval, 3);
+        assert_eq!(values.len(), 15);
+        assert_eq!(values[0], 2.0);
+        assert_eq!(values[14], 4.0);
+
+        let (values, interval) = timeline(&[1; 41]);
+
+        assert_eq!((values.len(), interval), (14, 3));
+        assert_eq!(values[13], 2.0 / 3.0);
+    }
+
+    #[test]
+    fn bytes_received() {
+        let usage = Usage::default();
+        let request = Request::new(
+            reqwest::Method::GET,
+            "https://example.com/page".parse().unwrap(),
+        );
+        let mut response: Response = http::Response::builder()
+            .header("content-type", "text/html")
+            .body("")
+            .unwrap()
+            .into();
+
+        usage.after(&request, &mut response);
+        // A chunked body has no length: it is counted as it is read.
+        usage.received("example.com", 1000);
+        usage.received("example.com", 24);
+
+        let summary = usage.summary();
+        let headers = 17 + "content-type".len() as u64 + "text/html".len() as u64 + 4 + 2;
+
+        assert_eq!(summary.requests, 1);
+        assert_eq!((summary.hosts, summary.tls_hosts), (1, 1));
+        assert_eq!(summary.bytes_received, headers + 1024);
+        assert_eq!(summary.per_host[0].bytes_received, headers + 1024);
+        assert_eq!(usage.tls_hosts(), [("example.com".to_string(), 443)]);
+    }
+}
@@ -0,0 +1,399 @@
+use std::fmt;
+use std::sync::Arc;
+
+use regex::Regex;
+use reqwest::header::HeaderMap;
+
+/// A downloaded response, checked by the validators.
+#[derive(Debug)]
+pub struct CheckedResponse<'a> {
+    pub url: &'a str,
+    pub status: u16,
+    pub headers: &'a HeaderMap,
+    pub body: &'a str,
+}
+
+/// Check the responses whose body is downloaded (HTML documents, stylesheets, feeds...), e.g.
+/// to fail truncated pages or error messages served with a 200 status.
+///
+/// Closures taking a [`CheckedResponse`] and returning why it is invalid are validators.
+pub trait Validator: Send + Sync {
+    fn validate(&self, response: &CheckedResponse) -> Result<(), String>;
+}
+
+impl<F> Validator for F
+where
+    F: Fn(&CheckedResponse) -> Result<(), String> + Send + Sync,
+{
+    fn validate(&self, response: &CheckedResponse) -> Result<(), String> {
+        self(response)
+    }
+}
+
+/// The validators of a crawl.
+#[derive(Clone, Default)]
+pub struct Validators(Vec<Arc<dyn Validator>>);
+
+impl fmt::Debug for Validators {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Validators({})", self.0.len())
+    }
+}
+
+impl Validators {
+    pub fn push(&mut self, validator: Arc<dyn Validator>) {
+        self.0.push(validator);
+    }
+
+    pub fn extend(&mut self, other: Validators) {
+        self.0.extend(other.0);
+    }
+
+    /// Get why the response is invalid, for every validator rejecting it.
+    pub fn validate(&self, response: &CheckedResponse) -> Vec<String> {
+        self.0
+            .iter()
+            .filter_map(|x| x.validate(response).err())
+            .collect()
+    }
+}
+
+/// A value a response is compared with.
+#[derive(Clone, Debug)]
+enum Operand {
+    Number(u16),
+    Text(String),
+    Pattern(Regex),
+}
+
+/// A part of a response.
+#[derive(Clone, Debug)]
+enum Field {
+    Status,
+    Url,
+    Body,
+    Header(String),
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Compare(Field, String, Operand),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+/// A `--assert` rule, e.g. `status == 200 && body contains "</html>"`.
+///
+/// Comparisons are made on `status` (`==`, `!=`, `<`, `<=`, `>`, `>=`), and on `url`, `body` and
+/// `header.<name>` (`==`, `!=`, `contains`, `matches` a regex), combined with `&&`, `||`, `!`
+/// and parentheses.
+#[derive(Clone, Debug)]
+pub struct Assertion {
+    source: String,
+    expr: Expr,
+}
+
+/// Parse a `--assert` rule.
+pub fn parse_assertion(value: &str) -> Result<Assertion, String> {
+    let tokens = tokenize(value)?;
+    let mut parser = Parser { tokens, position: 0 };
+    let expr = parser.or()?;
+
+    match parser.tokens.get(parser.position) {
+        Some(token) => Err(format!("unexpected `{}` in `{}`", token, value)),
+        None => Ok(Assertion {
+            source: value.to_string(),
+            expr,
+        }),
+    }
+}
+
+/// Split a rule into tokens: operators, names, numbers and quoted strings (kept quoted).
+fn tokenize(value: &str) -> Result<Vec<String>, String> {
+    let mut tokens = vec![];
+    let mut chars = value.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            _ if c.is_whitespace() => {
+                chars.next();
+            }
+            '"' => {
+                let mut token = String::from('"');
+
+                chars.next();
+
+                loop {
+                    match chars.next() {
+                        Some('\\') => token.extend(chars.next()),
+                        Some('"') => {
+                            token.push('"');
+                            break;
+                        }
+                        Some(c) => token.push(c),
+                        None => return Err(format!("unterminated string in `{}`", value)),
+                    }
+                }
+
+                tokens.push(token);
+            }
+            '(' | ')' => {
+                tokens.push(c.to_string());
+                chars.next();
+            }
+            '=' | '!' | '<' | '>' | '&' | '|' => {
+                let mut token = String::new();
+
+                while let Some(&c) = chars.peek().filter(|x| "=!<>&|".contains(**x)) {
+                    // A `!` following another operator is a negation (`&&!`).
+                    if c == '!' && !token.is_empty() {
+                        break;
+                    }
+
+                    token.push(c);
+                    chars.next();
+                }
+
+                tokens.push(token);
+            }
+            _ => {
+                let mut token = String::new();
+
+                while let Some(&c) = chars
+                    .peek()
+                    .filter(|x| x.is_alphanumeric() || "_-.".contains(**x))
+                {
+                    token.push(c);
+                    chars.next();
+                }
+
+                if token.is_empty() {
+                    return Err(format!("unexpected `{}` in `{}`", c, value));
+                }
+
+                tokens.push(token);
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<String>,
+    position: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<String> {
+        let token = self.tokens.get(self.position).cloned();
+
+        self.position += 1;
+        token
+    }
+
+    fn accept(&mut self, token: &str) -> bool {
+        let found = self.tokens.get(self.position).is_some_and(|x| x == token);
+
+        if found {
+            self.position += 1;
+        }
+
+        found
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+
+        while self.accept("||") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+
+        while self.accept("&&") {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.accept("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+
+        if self.accept("(") {
+            let expr = self.or()?;
+
+            return match self.accept(")") {
+                true => Ok(expr),
+                false => Err("missing `)`".to_string()),
+            };
+        }
+
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let name = self.next().ok_or("missing comparison")?;
+        let field = match name.as_str() {
+            "status" => Field::Status,
+            "url" => Field::Url,
+            "body" => Field::Body,
+            x if x.starts_with("header.") => Field::Header(x["header.".len()..].to_string()),
+            x => {
+                return Err(format!(
+                    "unknown field `{}`, expected status, url, body or header.<name>",
+                    x
+                ))
+            }
+        };
+        let operator = self.next().ok_or("missing operator")?;
+        let value = self.next().ok_or("missing value")?;
+        let text = value.strip_prefix('"').and_then(|x| x.strip_suffix('"'));
+        let operand = match (&field, operator.as_str(), text) {
+            (Field::Status, "==" | "!=" | "<" | "<=" | ">" | ">=", None) => Operand::Number(
+                value
+                    .parse()
+                    .map_err(|_| format!("invalid status `{}`", value))?,
+            ),
+            (Field::Status, _, _) => {
+                return Err(format!("invalid comparison `status {} {}`", operator, value))
+            }
+            (_, "matches", Some(text)) => {
+                Operand::Pattern(Regex::new(text).map_err(|e| e.to_string())?)
+            }
+            (_, "==" | "!=" | "contains", Some(text)) => Operand::Text(text.to_string()),
+            _ => return Err(format!("invalid comparison `{} {} {}`", name, operator, value)),
+        };
+
+        Ok(Expr::Compare(field, operator, operand))
+    }
+}
+
+impl Expr {
+    fn eval(&self, response: &CheckedResponse) -> bool {
+        let (field, operator, operand) = match self {
+            Self::Not(x) => return !x.eval(response),
+            Self::And(a, b) => return a.eval(response) && b.eval(response),
+            Self::Or(a, b) => return a.eval(response) || b.eval(response),
+            Self::Compare(field, operator, operand) => (field, operator.as_str(), operand),
+        };
+        let text = match field {
+            Field::Status => "",
+            Field::Url => response.url,
+            Field::Body => response.body,
+            Field::Header(name) => response
+                .headers
+                .get(name.as_str())
+                .and_then(|x| x.to_str().ok())
+                .unwrap_or_default(),
+        };
+
+        match (operand, operator) {
+            (Operand::Number(x), "==") => response.status == *x,
+            (Operand::Number(x), "!=") => response.status != *x,
+            (Operand::Number(x), "<") => response.status < *x,
+            (Operand::Number(x), "<=") => response.status <= *x,
+            (Operand::Number(x), ">") => response.status > *x,
+            (Operand::Number(x), _) => response.status >= *x,
+            (Operand::Pattern(x), _) => x.is_match(text),
+            (Operand::Text(x), "==") => text == x,
+            (Operand::Text(x), "!=") => text != x,
+            (Operand::Text(x), _) => text.contains(x.as_str()),
+        }
+    }
+}
+
+impl Validator for Assertion {
+    fn validate(&self, response: &CheckedResponse) -> Result<(), String> {
+        match self.expr.eval(response) {
+            true => Ok(()),
+            false => Err(format!("assertion failed: {}", self.source)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_operators() {
+        // The expected tokens are separated by spaces.
+        let cases = [
+            ("status!=200", "status != 200"),
+            ("status == 200&&!(url contains \"x\")", "status == 200 && ! ( url contains \"x\" )"),
+            ("!!body", "! ! body"),
+            ("header.x-cache == \"a\\\"b\"", "header.x-cache == \"a\"b\""),
+            ("status>=500||status<300", "status >= 500 || status < 300"),
+        ];
+
+        for (rule, tokens) in &cases {
+            assert_eq!(tokenize(rule).unwrap(), tokens.split(' ').collect::<Vec<_>>(), "{}", rule);
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        let cases = [
+            ("body contains \"x", "unterminated string in `body contains \"x`"),
+            ("(status == 200", "missing `)`"),
+            ("status contains \"x\"", "invalid comparison `status contains \"x\"`"),
+            ("status == \"200\"", "invalid comparison `status == \"200\"`"),
+            ("status == abc", "invalid status `abc`"),
+            ("body == x", "invalid comparison `body == x`"),
+            ("size > 3", "unknown field `size`, expected status, url, body or header.<name>"),
+            ("status ==", "missing value"),
+            ("", "missing comparison"),
+            ("status == 200 )", "unexpected `)` in `status == 200 )`"),
+            ("status == 200 # x", "unexpected `#` in `status == 200 # x`"),
+            ("body matches \"(\"", "regex parse error"),
+        ];
+
+        for (rule, error) in &cases {
+            let found = parse_assertion(rule).unwrap_err();
+
+            assert!(found.starts_with(error), "{}: {}", rule, found);
+        }
+    }
+
+    #[test]
+    fn eval() {
+        let mut headers = HeaderMap::new();
+
+        headers.insert("x-cache", "HIT".parse().unwrap());
+
+        let response = CheckedResponse {
+            url: "https://example.com/page",
+            status: 200,
+            headers: &headers,
+            body: "<html>ok</html>",
+        };
+        let cases = [
+            ("status == 200", true),
+            ("status != 200", false),
+            ("status < 300 && status >= 200", true),
+            ("status > 200 || status <= 199", false),
+            ("status == 200 &&!(body contains \"error\")", true),
+            ("status == 200 && !body contains \"ok\"", false),
+            ("!status != 200", true),
+            ("status == 404 || status == 200 && url contains \"/page\"", true),
+            ("(status == 404 || status == 200) && url contains \"/other\"", false),
+            ("body matches \"^<html>.*</html>$\"", true),
+            ("url == \"https://example.com/page\"", true),
+            ("header.x-cache == \"HIT\" && header.age != \"\"", false),
+            ("header.X-Cache contains \"HI\"", true),
+        ];
+
+        for (rule, expected) in &cases {
+            assert_eq!(parse_assertion(rule).unwrap().expr.eval(&response), *expected, "{}", rule);
+        }
+    }
+}
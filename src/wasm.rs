@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use serde::Serialize;
+use wasmtime::{Engine, Instance, Memory, Module, Store, TypedFunc};
+
+/// The input given to a WASM plugin, serialized as JSON.
+#[derive(Debug, Serialize)]
+pub struct Input<'a> {
+    pub url: &'a str,
+    pub status: u16,
+    pub headers: BTreeMap<String, String>,
+    pub body: &'a str,
+}
+
+/// A WASM module implementing the check interface.
+///
+/// The module must export:
+///
+/// - `memory`, its linear memory;
+/// - `alloc(len: i32) -> i32`, returning a pointer to `len` writable bytes;
+/// - `check(ptr: i32, len: i32) -> i64`, receiving the JSON input and returning a pointer and a
+///   length packed as `(ptr << 32) | len`, pointing to a JSON array of findings (strings). `0`
+///   means no finding.
+struct WasmPlugin {
+    path: String,
+    store: Store<()>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    check: TypedFunc<(i32, i32), i64>,
+}
+
+impl WasmPlugin {
+    /// Load and instantiate a module.
+    fn load(engine: &Engine, path: &str) -> Result<Self, Box<dyn Error>> {
+        let module = Module::from_file(engine, path)?;
+        let mut store = Store::new(engine, ());
+        let instance = Instance::new(&mut store, &module, &[])?;
+
+        Ok(Self {
+            path: path.to_string(),
+            memory: instance
+                .get_memory(&mut store, "memory")
+                .ok_or("missing `memory` export")?,
+            alloc: instance.get_typed_func(&mut store, "alloc")?,
+            check: instance.get_typed_func(&mut store, "check")?,
+            store,
+        })
+    }
+
+    /// Run the check on the given input.
+    fn check(&mut self, input: &[u8]) -> Result<Vec<String>, Box<dyn Error>> {
+        let ptr = self.alloc.call(&mut self.store, input.len() as i32)?;
+
+        self.memory.write(&mut self.store, ptr as usize, input)?;
+
+        let packed = self
+            .check
+            .call(&mut self.store, (ptr, input.len() as i32))? as u64;
+
+        if packed == 0 {
+            return Ok(vec![]);
+        }
+
+        let mut output = vec![0; (packed & 0xffff_ffff) as usize];
+
+        self.memory
+            .read(&self.store, (packed >> 32) as usize, &mut output)?;
+
+        Ok(serde_json::from_slice(&output)?)
+    }
+}
+
+/// Every loaded WASM plugin.
+#[derive(Default)]
+pub struct WasmPlugins {
+    plugins: Vec<WasmPlugin>,
+}
+
+impl std::fmt::Debug for WasmPlugins {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.plugins.iter().map(|x| &x.path))
+            .finish()
+    }
+}
+
+impl WasmPlugins {
+    /// Load every given module.
+    pub fn load(paths: &[String]) -> Result<Self, Box<dyn Error>> {
+        let engine = Engine::default();
+        let plugins = paths
+            .iter()
+            .map(|x| WasmPlugin::load(&engine, x))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { plugins })
+    }
+
+    /// Run every plugin on the given input, collecting their findings. A failing plugin is
+    /// logged and ignored.
+    pub fn check(&mut self, input: &Input) -> Vec<String> {
+        let input = match serde_json::to_vec(input) {
+            Ok(input) => input,
+            Err(e) => {
+                error!("Cannot serialize WASM plugin input: {}", e);
+                return vec![];
+            }
+        };
+
+        let mut findings = vec![];
+
+        for plugin in &mut self.plugins {
+            match plugin.check(&input) {
+                Ok(x) => findings.extend(x),
+                Err(e) => error!("WASM plugin `{}` failed: {}", plugin.path, e),
+            }
+        }
+
+        findings
+    }
+}
@@ -0,0 +1,45 @@
+use std::error::Error;
+
+use reqwest::Client;
+use serde::Deserialize;
+
+/// The availability API of the Internet Archive, giving the closest snapshot of an URL.
+pub const API: &str = "https://archive.org/wayback/available";
+
+#[derive(Debug, Deserialize)]
+struct Availability {
+    archived_snapshots: Snapshots,
+}
+
+#[derive(Debug, Deserialize)]
+struct Snapshots {
+    closest: Option<Snapshot>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Snapshot {
+    available: bool,
+    url: String,
+}
+
+/// Get the closest snapshot of an URL archived by the Wayback Machine, if any.
+///
+/// The API gives `http://` snapshot URLs, the archive being served over `https://` too.
+pub async fn closest(client: &Client, url: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let response = client
+        .get(API)
+        .query(&[("url", url)])
+        .send()
+        .await?
+        .error_for_status()?;
+    let availability: Availability = serde_json::from_str(&response.text().await?)?;
+
+    Ok(availability
+        .archived_snapshots
+        .closest
+        .filter(|x| x.available)
+        .map(|x| match x.url.strip_prefix("http://") {
+            Some(url) => format!("https://{}", url),
+            None => x.url,
+        }))
+}
@@ -0,0 +1,1348 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::error::Error;
+use std::hash::{Hash, Hasher};
+use std::io::Read;
+use std::net::SocketAddr;
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use futures::future;
+use futures::lock::{Mutex, OwnedMutexGuard};
+use futures::Future;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, Method, Request, StatusCode};
+use select::document::Document;
+use tracing::{field, Instrument};
+use url::Url;
+
+use crate::audit::HeaderAudit;
+use crate::crawler::{Crawler, Response};
+use crate::error_page::ErrorPage;
+use crate::events::CrawlEvent;
+use crate::extract::{Format, Resource};
+use crate::fixes::{BrokenLink, Fixes, MixedContent};
+use crate::middleware::Middleware;
+use crate::network::NetworkError;
+use crate::report::{Entry, Verdict};
+use crate::robots::Robots;
+use crate::usage::Usage;
+use crate::{error_page, extract, fixes, network, redirects, render, report, revalidate, seo};
+use crate::{host, is_static_resource, validate, POLITE_DELAY};
+#[cfg(feature = "wasm")]
+use crate::wasm;
+
+/// A worker's hold on the shared crawler: locked, except while waiting on the network (or a
+/// browser), so that requests of several workers are in flight at once.
+pub(crate) struct Worker {
+    pub(crate) crawler: Arc<Mutex<Crawler>>,
+    guard: Option<OwnedMutexGuard<Crawler>>,
+
+    /// How many requests the current URL took, to count its retries (HEAD falling back to GET,
+    /// the fallback host...).
+    fetches: u32,
+}
+
+impl Deref for Worker {
+    type Target = Crawler;
+
+    fn deref(&self) -> &Crawler {
+        self.guard.as_deref().expect("the crawler is locked")
+    }
+}
+
+impl DerefMut for Worker {
+    fn deref_mut(&mut self) -> &mut Crawler {
+        self.guard.as_deref_mut().expect("the crawler is locked")
+    }
+}
+
+impl Worker {
+    async fn lock(crawler: Arc<Mutex<Crawler>>) -> Self {
+        let guard = Arc::clone(&crawler).lock_owned().await;
+
+        Self {
+            crawler,
+            guard: Some(guard),
+            fetches: 0,
+        }
+    }
+
+    /// Hold a crawler which is not shared (yet).
+    pub(crate) async fn new(crawler: Crawler) -> Self {
+        Self::lock(Arc::new(Mutex::new(crawler))).await
+    }
+
+    /// Give the crawler back, once the other workers are finished.
+    pub(crate) fn into_inner(mut self) -> Crawler {
+        self.guard = None;
+
+        match Arc::try_unwrap(self.crawler) {
+            Ok(crawler) => crawler.into_inner(),
+            Err(_) => unreachable!("the workers are finished"),
+        }
+    }
+
+    /// Let the other workers in, until [`Worker::relock`].
+    pub(crate) fn unlock(&mut self) {
+        self.guard = None;
+    }
+
+    /// Lock the crawler again, if it has been unlocked.
+    pub(crate) async fn relock(&mut self) {
+        if self.guard.is_none() {
+            self.guard = Some(Arc::clone(&self.crawler).lock_owned().await);
+        }
+    }
+
+    /// Wait on a future without holding the crawler, e.g. a request or a body being downloaded.
+    async fn unlocked<T>(&mut self, future: impl Future<Output = T>) -> T {
+        self.unlock();
+
+        let output = future.await;
+
+        self.relock().await;
+        output
+    }
+
+    /// Read the body of a response (see [`read_body`]), the crawler unlocked.
+    async fn read_body(
+        &mut self,
+        response: reqwest::Response,
+        format: Format,
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        let max_body_size = self.opts.max_body_size;
+        let usage = Arc::clone(&self.usage);
+
+        self.unlocked(read_body(response, format, max_body_size, &usage))
+            .await
+            .map_err(|error| error as Box<dyn Error>)
+    }
+
+    /// Build the remediation plan: redirect shortcuts, broken internal links with a suggested
+    /// target and `http://` resources of `https://` pages whose `https://` equivalent exists.
+    pub(crate) async fn fixes(&mut self, results: &[Entry]) -> Fixes {
+        let pages: Vec<Url> = results
+            .iter()
+            .filter(|x| x.verdict == Verdict::Success && x.resource == Resource::Page)
+            .filter_map(|x| Url::parse(&x.url).ok())
+            .collect();
+        let mut fixes = Fixes {
+            redirects: redirects::shortcuts(&self.redirects),
+            ..Fixes::default()
+        };
+
+        for entry in results {
+            let url = match Url::parse(&entry.url) {
+                Ok(url) => url,
+                Err(_) => continue,
+            };
+
+            if entry.verdict == Verdict::Failure
+                && self.is_same_domain(&url)
+                && !entry.found_on.is_empty()
+            {
+                fixes.broken_links.push(BrokenLink {
+                    url: entry.url.clone(),
+                    status: entry.status,
+                    found_on: entry.found_on.clone(),
+                    suggestion: fixes::suggest_target(&url, &pages),
+                });
+            }
+
+            let found_on: Vec<String> = entry
+                .found_on
+                .iter()
+                .filter(|x| x.starts_with("https://"))
+                .cloned()
+                .collect();
+
+            if url.scheme() != "http" || found_on.is_empty() {
+                continue;
+            }
+
+            let mut https = url.clone();
+            let _ = https.set_scheme("https");
+
+            match self.fetch_head(https.as_str()).await {
+                Ok((response, _)) if response.status().is_success() => {
+                    fixes.mixed_content.push(MixedContent {
+                        url: entry.url.clone(),
+                        https: https.to_string(),
+                        found_on,
+                    })
+                }
+                _ => debug!("{} has no https equivalent", url),
+            }
+        }
+
+        fixes
+    }
+
+    /// Check if a successful page is a missing page in disguise: its body matches the soft 404
+    /// pattern, or the fingerprint of its host error page (probed once per host).
+    async fn is_soft_404(&mut self, url: &str, body: &str) -> bool {
+        if self.opts.soft_404_pattern.as_ref().is_some_and(|x| x.is_match(body)) {
+            return true;
+        }
+
+        let url = match Url::parse(url) {
+            Ok(url) => url,
+            Err(_) => return false,
+        };
+        let origin = url.origin().ascii_serialization();
+
+        // A build directory has no server, thus no error page of its own.
+        if !self.error_pages.contains_key(&origin) && self.static_dir.is_none() {
+            if let Err(e) = self.probe_error_page(&url).await {
+                warn!("Cannot probe the error page of {}: {}", origin, e);
+            }
+        }
+
+        self.error_pages.get(&origin).is_some_and(|x| {
+            (200..300).contains(&x.status) && x.hash == error_page::hash(body, &url)
+        })
+    }
+
+    /// Save the screenshot of a failed page into the --screenshots directory, named after the
+    /// hash of its URL. Return its path.
+    fn save_screenshot(&self, url: &str, png: &[u8]) -> Option<String> {
+        let directory = PathBuf::from(self.opts.screenshots.as_deref()?);
+        let mut hasher = DefaultHasher::new();
+
+        url.hash(&mut hasher);
+
+        let path = directory.join(format!("{:016x}.png", hasher.finish()));
+
+        match std::fs::create_dir_all(&directory).and_then(|_| std::fs::write(&path, png)) {
+            Ok(()) => Some(path.display().to_string()),
+            Err(e) => {
+                warn!("Cannot save the screenshot of {}: {}", url, e);
+                None
+            }
+        }
+    }
+
+    /// Get how the browser of --render has to request a page, the way it has been requested here:
+    /// with the `-H` headers and `From`, the headers and credentials of its host, and the cookies
+    /// of the crawl (unless priming caches).
+    fn navigation(&self, url: &str) -> render::Navigation {
+        let mut request = match Url::parse(url) {
+            Ok(url) => Request::new(Method::GET, url),
+            Err(_) => return render::Navigation::default(),
+        };
+        let mut headers: HeaderMap = self.opts.header.iter().cloned().collect();
+
+        if let Some(from) = &self.opts.from {
+            headers.insert(reqwest::header::FROM, from.clone());
+        }
+
+        // Both have already been applied to the request of the page, without error.
+        if let Some(policy) = self.policies.find(request.url()) {
+            let _ = policy.apply(&mut request);
+        }
+
+        for auth in &self.auths {
+            let _ = auth.before(&mut request);
+        }
+
+        let text = |(name, value): (&HeaderName, &HeaderValue)| {
+            Some((name.to_string(), value.to_str().ok()?.to_string()))
+        };
+
+        render::Navigation {
+            headers: headers.iter().filter_map(text).collect(),
+            origin_headers: request.headers().iter().filter_map(text).collect(),
+            cookies: match self.opts.prime_cache {
+                true => vec![],
+                false => self.cookies.current(),
+            },
+        }
+    }
+
+    /// Probe a random (missing) URL of the site to fingerprint its error page. The links of the
+    /// error page are queued, so its own assets get checked too.
+    pub(crate) async fn probe_error_page(&mut self, site: &Url) -> Result<(), Box<dyn Error>> {
+        let url = error_page::probe_url(site);
+        let (response, redirects) = self.fetch(Method::GET, url.as_str()).await?;
+        let status = response.status().as_u16();
+        let body = self.read_body(response, Format::Html).await?.unwrap_or_default();
+        let page = ErrorPage::new(&url, status, &redirects, &body);
+
+        debug!("{} - error page fingerprint {}", status, page.hash);
+
+        for problem in &page.problems {
+            warn!("Error page: {}", problem);
+        }
+
+        let referrer = redirects.last().map_or(url.as_str(), String::as_str).to_string();
+
+        for link in Format::Html.links(&body) {
+            self.queue(&link.href, link.resource, Some(&referrer));
+        }
+
+        self.error_pages
+            .insert(site.origin().ascii_serialization(), page);
+        Ok(())
+    }
+
+    /// Handle a response (after the request get executed).
+    async fn on_response(
+        &mut self,
+        url: &str,
+        response: reqwest::Response,
+        redirects: Vec<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        let status = response.status();
+        // Links are found on the final page of the redirect chain.
+        let referrer = redirects.last().map_or(url, String::as_str).to_string();
+
+        // Unchanged pages are not parsed again, their links are reused.
+        if status == StatusCode::NOT_MODIFIED {
+            if let Some(page) = self.revalidation.as_ref().and_then(|x| x.reuse(&referrer)) {
+                debug!("{} is not modified, reusing its links", referrer);
+                self.on_page(url, &referrer, StatusCode::from_u16(page.status)?, redirects, page);
+
+                if let Some(response) = self.responses.get_mut(url) {
+                    response.annotations.push("not modified".to_string());
+                }
+
+                self.pages -= 1;
+                return Ok(());
+            }
+        }
+
+        self.check_social(url, status, response.headers());
+
+        let format = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|x| x.to_str().ok())
+            .and_then(|x| self.extractors.format(x))
+            .filter(|x| *x != Format::Pdf || self.opts.parse_pdf);
+
+        let manifest = self.opts.check_icons
+            && self.responses.get(url).is_some_and(|x| x.resource == Resource::Manifest);
+
+        let format = match format {
+            Some(format) => format,
+            // Manifests are often served as `application/json`, or without a content type.
+            None if manifest => Format::Manifest,
+            None => {
+                debug!("{} has no extractor for its content type, skipping its content", url);
+                self.record(url, status, redirects);
+                self.pages -= 1;
+                return Ok(());
+            }
+        };
+
+        // The page has been counted when requested (see `execute`).
+        if format != Format::Html {
+            self.pages -= 1;
+        }
+
+        if format == Format::Html && self.opts.audit_headers && status.is_success() {
+            // Pages are audited once, on the final URL of the redirect chain.
+            let audit = Url::parse(&referrer)
+                .ok()
+                .filter(|x| self.is_same_domain(x))
+                .filter(|x| !self.header_audits.iter().any(|a| a.url == x.as_str()))
+                .map(|x| HeaderAudit::new(&x, response.headers()));
+
+            self.header_audits.extend(audit);
+        }
+
+        let validator = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|x| x.to_str().ok())
+                .map(str::to_string)
+        };
+        let headers = response.headers().clone();
+        let etag = validator(reqwest::header::ETAG);
+        let last_modified = validator(reqwest::header::LAST_MODIFIED);
+
+        let started = Instant::now();
+        let body = self.read_body(response, format).await?;
+
+        if let Some(response) = self.responses.get_mut(url) {
+            response.download = Some(started.elapsed());
+            response.length = response.length.or(body.as_ref().map(|x| x.len() as u64));
+        }
+
+        let body = match body {
+            Some(body) => body,
+            None => {
+                warn!("{} - {} is too large, skipping its content", status, url);
+                self.record(url, status, redirects);
+
+                if let Some(response) = self.responses.get_mut(url) {
+                    response.too_large = true;
+                    response.annotations.push("too large".to_string());
+                }
+
+                return Ok(());
+            }
+        };
+
+        let soft_404 = format == Format::Html
+            && status.is_success()
+            && self.is_soft_404(&referrer, &body).await;
+        let same_domain = Url::parse(&referrer).is_ok_and(|x| self.is_same_domain(&x));
+        let render = format == Format::Html && status.is_success() && same_domain;
+        let failed = soft_404 || status.is_client_error() || status.is_server_error();
+        let screenshot =
+            self.opts.screenshots.is_some() && format == Format::Html && same_domain && failed;
+
+        if render && !soft_404 {
+            self.contents
+                .entry(content_hash(&body))
+                .or_default()
+                .insert(referrer.clone());
+        }
+
+        // Links are extracted from the rendered page, falling back on the downloaded one. Failed
+        // pages are rendered for their screenshot only.
+        let body = match self.renderer.clone().filter(|_| render || screenshot) {
+            Some(renderer) => {
+                let navigation = self.navigation(&referrer);
+
+                match self.unlocked(renderer.render(&referrer, screenshot, navigation)).await {
+                    Ok(rendered) => {
+                        let path = rendered.screenshot.and_then(|x| self.save_screenshot(url, &x));
+
+                        if let Some(response) = self.responses.get_mut(url) {
+                            response.screenshot = path;
+
+                            if render {
+                                response.console_errors = rendered.console_errors;
+                                response.failed_requests = rendered.failed_requests;
+                                response.timings = Some(rendered.timings);
+                            }
+                        }
+
+                        match render {
+                            true => rendered.html,
+                            false => body,
+                        }
+                    }
+                    Err(e) => {
+                        warn!("Cannot render {}: {}", referrer, e);
+                        body
+                    }
+                }
+            }
+            None => body,
+        };
+        let mut page = revalidate::Page {
+            etag,
+            last_modified,
+            status: status.as_u16(),
+            format,
+            links: format.links(&body),
+            anchors: HashSet::new(),
+            noindex: false,
+            canonical: None,
+            alternates: vec![],
+        };
+
+        if format == Format::Html {
+            let document = Document::from(body.as_str());
+
+            // Pages are audited once, on the final URL of the redirect chain.
+            let audit_seo = self.opts.seo
+                && status.is_success()
+                && Url::parse(&referrer).is_ok_and(|x| self.is_same_domain(&x))
+                && !self.seo_audits.iter().any(|x| x.url == referrer);
+
+            if audit_seo {
+                self.seo_audits.push(seo::SeoAudit::new(&referrer, &document));
+            }
+
+            if self.opts.social {
+                for (property, link) in extract::social(&document) {
+                    let href = self.format_url(&link.href, Some(&referrer));
+                    let target = self.normalize(&href);
+
+                    self.social.insert(target, property);
+                    page.links.push(link);
+                }
+            }
+
+            page.anchors = extract::anchors(&document);
+            page.noindex = extract::robots(&document).iter().any(|x| x == "noindex");
+            page.canonical = extract::canonical(&document)
+                .and_then(|x| Url::parse(&referrer).and_then(|base| base.join(&x)).ok())
+                .map(|mut x| {
+                    x.set_fragment(None);
+                    x.to_string()
+                })
+                .filter(|x| *x != referrer);
+            page.alternates = extract::alternates(&document);
+        }
+
+        if let Some(revalidation) = self.revalidation.as_ref().filter(|_| status.is_success()) {
+            revalidation.insert(&referrer, page.clone());
+        }
+
+        let failures = self.validators.validate(&validate::CheckedResponse {
+            url,
+            status: status.as_u16(),
+            headers: &headers,
+            body: &body,
+        });
+
+        if let Some(response) = self.responses.get_mut(url).filter(|_| !failures.is_empty()) {
+            response.invalid = true;
+            response.annotations.extend(failures);
+        }
+
+        let invalid_manifest = format == Format::Manifest
+            && status.is_success()
+            && serde_json::from_str::<serde_json::Value>(&body).is_err();
+
+        if let Some(response) = self.responses.get_mut(url).filter(|_| invalid_manifest) {
+            warn!("{} - {} is not a valid manifest", status, url);
+            response.invalid = true;
+            response.annotations.push("invalid manifest".to_string());
+        }
+
+        if format == Format::Html {
+            for failure in self.content_rules.check(url, &body) {
+                warn!("Content failure - {} {}", url, failure.reason);
+                self.content_failures.push(failure);
+            }
+        }
+
+        self.on_page(url, &referrer, status, redirects, page);
+
+        if soft_404 {
+            warn!("{} - {} looks like a missing page (soft 404)", status, url);
+
+            if let Some(response) = self.responses.get_mut(url) {
+                response.soft_404 = true;
+                response.annotations.push("soft 404".to_string());
+            }
+
+            self.indexable.remove(url);
+        }
+
+        #[cfg(feature = "wasm")]
+        {
+            let findings = self.wasm_plugins.check(&wasm::Input {
+                url,
+                status: status.as_u16(),
+                headers: headers
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or_default().to_string()))
+                    .collect(),
+                body: &body,
+            });
+
+            if let Some(response) = self.responses.get_mut(url) {
+                response.annotations.extend(findings);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Wait until the host policy delay is elapsed since the last request to the same host.
+    async fn throttle(&mut self, url: &str) {
+        let url = match Url::parse(url) {
+            Ok(url) => url,
+            Err(_) => return,
+        };
+
+        let mut delay = self.policies.find(&url).map(|x| x.delay()).unwrap_or_default();
+
+        if self.opts.polite {
+            let crawl_delay = self
+                .robots
+                .get(&url.origin().ascii_serialization())
+                .and_then(|x| x.crawl_delay);
+
+            delay = delay.max(POLITE_DELAY).max(crawl_delay.unwrap_or_default());
+        }
+
+        if delay.as_millis() == 0 {
+            return;
+        }
+
+        let host = url.host_str().unwrap_or_default().to_string();
+        let now = Instant::now();
+
+        // The slot is booked before waiting, the crawler unlocked, so that the next request to
+        // the same host waits for this one.
+        let at = match self.last_requests.get(&host) {
+            Some(last) => (*last + delay).max(now),
+            None => now,
+        };
+
+        self.last_requests.insert(host, at);
+
+        if at > now {
+            debug!("Waiting {:?} before requesting {}", at - now, url);
+            self.unlocked(tokio::time::sleep(at - now)).await;
+        }
+    }
+
+    /// Check if `robots.txt` allows crawling an URL, fetching it once per origin (polite mode
+    /// only). Failing `robots.txt` files (server errors) forbid the whole origin.
+    async fn is_allowed_by_robots(&mut self, url: &str) -> bool {
+        let url = match Url::parse(url) {
+            Ok(url) if self.opts.polite => url,
+            _ => return true,
+        };
+
+        if self.static_dir.as_ref().is_some_and(|dir| dir.serves(&url)) {
+            return true;
+        }
+
+        let origin = url.origin().ascii_serialization();
+
+        if !self.robots.contains_key(&origin) {
+            let robots_url = format!("{}/robots.txt", origin);
+            let robots = match self.fetch(Method::GET, &robots_url).await.ok() {
+                Some((response, _)) if response.status().is_success() => {
+                    let body = self.unlocked(response.bytes()).await.unwrap_or_default();
+
+                    self.usage.received(url.host_str().unwrap_or_default(), body.len() as u64);
+                    Robots::parse(&String::from_utf8_lossy(&body), ROBOTS_AGENT)
+                }
+                Some((response, _)) if response.status().is_server_error() => {
+                    Robots::disallow_all()
+                }
+                // Missing files allow everything, and unreachable hosts fail their own checks.
+                _ => Robots::default(),
+            };
+
+            debug!("Loaded {}: {:?}", robots_url, robots);
+            self.robots.insert(origin.clone(), robots);
+        }
+
+        self.robots[&origin].allows(&url[url::Position::BeforePath..url::Position::AfterQuery])
+    }
+
+    /// Request the given URL, following redirects manually in order to keep track of them.
+    ///
+    /// Returns the final response and the chain of followed URLs.
+    #[tracing::instrument(name = "request", skip_all, fields(%method, url, status = field::Empty))]
+    pub(crate) async fn fetch(
+        &mut self,
+        method: Method,
+        url: &str,
+    ) -> Result<(reqwest::Response, Vec<String>), Box<dyn Error>> {
+        self.fetches += 1;
+
+        let mut redirects: Vec<String> = vec![];
+        let mut current = Url::parse(url)?;
+        // Throttling delays are not part of the response time.
+        let mut ttfb = Duration::default();
+
+        loop {
+            let mut request = self.client.request(method.clone(), current.clone()).build()?;
+
+            if let Some(policy) = self.policies.find(&current) {
+                policy.apply(&mut request)?;
+            }
+
+            let answered = self.middlewares.before(&mut request)?;
+            let response = match answered {
+                Some(response) => response,
+                None => {
+                    self.throttle(current.as_str()).await;
+                    self.resolve(&current).await?;
+
+                    let sent = request.try_clone().ok_or("Cannot clone the request")?;
+                    let client = self.client_for(&current)?;
+                    let started = Instant::now();
+                    let result = self.send(&current, &client, sent).await;
+                    ttfb += started.elapsed();
+                    self.spend(&current, started.elapsed());
+                    let mut response = result?;
+
+                    // Overridden hosts are connected to by the resolution proxy. Each hop of a
+                    // redirect chain gets its own address.
+                    if let Some(address) = self.override_for(&current).or(response.remote_addr()) {
+                        self.addresses.insert(current.to_string(), address);
+                    }
+
+                    self.middlewares.after(&request, &mut response);
+                    response
+                }
+            };
+
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|x| x.to_str().ok())
+                .and_then(|x| current.join(x).ok())
+                .map(|x| match self.rewrites.apply(&current) == current {
+                    true => x,
+                    false => self.rewrites.revert(&x),
+                });
+
+            let next = match location {
+                Some(next) if response.status().is_redirection() => next,
+                _ => {
+                    self.answered(url, &response, ttfb);
+                    return Ok((response, redirects));
+                }
+            };
+
+            if self.is_in_scope(&current) {
+                self.redirects.insert(current.to_string(), next.to_string());
+
+                if [StatusCode::MOVED_PERMANENTLY, StatusCode::PERMANENT_REDIRECT]
+                    .contains(&response.status())
+                {
+                    self.permanent_redirects.insert(current.to_string());
+                }
+            }
+
+            if redirects.len() >= redirects::MAX_REDIRECTS
+                || next.as_str() == url
+                || redirects.contains(&next.to_string())
+            {
+                warn!("Too many redirects (or redirect loop) for {}", url);
+                self.answered(url, &response, ttfb);
+                return Ok((response, redirects));
+            }
+
+            debug!("{} - {} redirects to {}", response.status(), current, next);
+            redirects.push(next.to_string());
+            current = next;
+        }
+    }
+
+    /// Send a request (a hop of the requested URL), over HTTP/3 first with --http3.
+    async fn send(
+        &mut self,
+        current: &Url,
+        client: &Client,
+        request: Request,
+    ) -> Result<reqwest::Response, Box<dyn Error>> {
+        #[cfg(feature = "http3")]
+        if let Some((quic, tcp)) = self.http3_for(current) {
+            return self.send_http3(current, &quic, &tcp, request).await;
+        }
+
+        self.send_with(client, current, request).await
+    }
+
+    /// Send a request with the given client, the crawler unlocked, aborting it if its response
+    /// headers do not arrive within --ttfb-timeout.
+    async fn send_with(
+        &mut self,
+        client: &Client,
+        current: &Url,
+        request: Request,
+    ) -> Result<reqwest::Response, Box<dyn Error>> {
+        let answer = client.execute(request);
+
+        match self.opts.ttfb_timeout {
+            Some(limit) => match self.unlocked(tokio::time::timeout(limit, answer)).await {
+                Ok(result) => result.map_err(Into::into),
+                Err(_) => Err(Box::new(network::Stalled {
+                    url: current.to_string(),
+                    limit,
+                })),
+            },
+            None => self.unlocked(answer).await.map_err(Into::into),
+        }
+    }
+
+    /// Get the --http3 clients to request an URL with: `https://` URLs requested with the shared
+    /// client only, the others being sent through a proxy or with a client certificate.
+    #[cfg(feature = "http3")]
+    fn http3_for(&self, url: &Url) -> Option<(Client, Client)> {
+        match url.scheme() == "https" && self.client_key(url).is_none() {
+            true => self.http3.clone(),
+            false => None,
+        }
+    }
+
+    /// Send a request over HTTP/3, falling back to TCP (negotiating HTTP/2) when it fails, or has
+    /// already failed on the same host. The response tells why it fell back.
+    #[cfg(feature = "http3")]
+    async fn send_http3(
+        &mut self,
+        current: &Url,
+        quic: &Client,
+        tcp: &Client,
+        request: Request,
+    ) -> Result<reqwest::Response, Box<dyn Error>> {
+        let host = current.host_str().unwrap_or_default().to_string();
+
+        if !self.http3_failures.contains_key(&host) {
+            let mut attempt = request.try_clone().ok_or("Cannot clone the request")?;
+
+            *attempt.version_mut() = reqwest::Version::HTTP_3;
+
+            match self.send_with(quic, current, attempt).await {
+                Ok(response) => return Ok(response),
+                Err(e) => {
+                    // The reason stands for the whole host, not the URL it was first met on.
+                    let reason = match e.downcast::<reqwest::Error>() {
+                        Ok(e) => network::describe(&e.without_url()),
+                        Err(e) => network::describe(&*e),
+                    };
+
+                    warn!("HTTP/3 failed on {}, falling back to TCP: {}", host, reason);
+                    self.http3_failures.insert(host.clone(), reason);
+                }
+            }
+        }
+
+        let mut response = self.send_with(tcp, current, request).await?;
+        let reason = self.http3_failures[&host].clone();
+
+        response.extensions_mut().insert(network::Http3Fallback(reason));
+        Ok(response)
+    }
+
+    /// Request the given URL using HEAD, falling back to GET when the server rejects it.
+    ///
+    /// A lot of servers do not implement HEAD properly, so any error status is double-checked.
+    async fn fetch_head(
+        &mut self,
+        url: &str,
+    ) -> Result<(reqwest::Response, Vec<String>), Box<dyn Error>> {
+        let (response, redirects) = self.fetch(Method::HEAD, url).await?;
+        let status = response.status();
+
+        if status.is_client_error() || status.is_server_error() {
+            debug!("{} - HEAD {} rejected, falling back to GET", status, url);
+            return self.fetch(Method::GET, url).await;
+        }
+
+        Ok((response, redirects))
+    }
+
+    /// Resolve the host of an URL with --dns or --doh, once, the resolution proxy then connecting
+    /// to the first found address accepting the connection. Hosts requested through another
+    /// proxy are resolved by this proxy.
+    async fn resolve(&mut self, url: &Url) -> Result<(), Box<dyn Error>> {
+        let resolver = match &self.dns {
+            Some(resolver) => Arc::clone(resolver),
+            None => return Ok(()),
+        };
+        let host = match url.host() {
+            Some(url::Host::Domain(host)) => host.to_lowercase(),
+            _ => return Ok(()),
+        };
+
+        if self.override_for(url).is_some() || self.proxy_for(url).is_some() {
+            return Ok(());
+        }
+
+        let port = url.port_or_known_default().unwrap_or_default();
+        let addresses = self.unlocked(resolver.lookup(&host)).await?;
+
+        self.overrides.lock().unwrap().insert(
+            (host, port),
+            addresses.into_iter().map(|x| SocketAddr::new(x, port)).collect(),
+        );
+
+        Ok(())
+    }
+
+    /// Execute a request using the given URL.
+    ///
+    /// Obviously non-HTML resources are only checked, without downloading their content. In a
+    /// differential crawl, only seeds are crawled and every other URL is only checked.
+    async fn execute(&mut self, url: &str) -> Result<(), Box<dyn Error>> {
+        let url = self.format_url(url, None);
+
+        if self.is_exhausted(&url) {
+            self.skip(&url, "host budget exhausted");
+            return Ok(());
+        }
+
+        if !self.is_allowed_by_robots(&url).await {
+            self.skip(&url, "disallowed by robots.txt");
+            return Ok(());
+        }
+
+        let is_seed = self.seeds.as_ref().is_none_or(|x| x.contains(&url));
+        // PDF documents are downloaded for their links to be extracted.
+        let is_pdf = self.opts.parse_pdf
+            && Url::parse(&url).is_ok_and(|x| x.path().to_lowercase().ends_with(".pdf"));
+
+        // The other language sections than --lang are checked only.
+        let is_other_language = self.opts.lang.is_some()
+            && self.language(&url).is_some_and(|x| Some(x) != self.opts.lang.as_deref());
+
+        if !is_seed || is_other_language || (is_static_resource(&url) && !is_pdf) {
+            return match self.opts.dry_run {
+                true => Ok(()),
+                false => self.check(&url).await,
+            };
+        }
+
+        if self.opts.max_pages.is_some_and(|max| self.pages >= max) {
+            self.skip(&url, "--max-pages reached");
+            return Ok(());
+        }
+
+        if self.is_over_checks(&url) {
+            return Ok(());
+        }
+
+        // The page is counted before being requested, for concurrent requests not to overshoot
+        // --max-pages, and uncounted if it turns out not to be an HTML page.
+        self.pages += 1;
+
+        let mut class = None;
+        let result = self.fetch(Method::GET, &url).await.map_err(|e| {
+            class = network::classify(&*e);
+            network::describe(&*e)
+        });
+
+        if let Some(failure) = self.failure(&url, &result) {
+            if self.retry_on_fallback(&url, &failure).await
+                || self.retry_on_other_scheme(&url, class, &failure).await
+            {
+                self.pages -= 1;
+                return Ok(());
+            }
+        }
+
+        self.set_error_class(&url, class);
+
+        let (response, redirects) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                self.pages -= 1;
+                return Err(e.into());
+            }
+        };
+
+        self.on_response(&url, response, redirects).await
+    }
+
+    /// Execute a single request using the given URL, without extracting any link.
+    async fn check(&mut self, url: &str) -> Result<(), Box<dyn Error>> {
+        if self.is_exhausted(url) {
+            self.skip(url, "host budget exhausted");
+            return Ok(());
+        }
+
+        if self.is_over_checks(url) {
+            return Ok(());
+        }
+
+        if !self.is_allowed_by_robots(url).await {
+            self.skip(url, "disallowed by robots.txt");
+            return Ok(());
+        }
+
+        let mut class = None;
+        let result = self.fetch_head(url).await.map_err(|e| {
+            class = network::classify(&*e);
+            network::describe(&*e)
+        });
+
+        if let Some(failure) = self.failure(url, &result) {
+            if self.retry_on_fallback(url, &failure).await
+                || self.retry_on_other_scheme(url, class, &failure).await
+            {
+                return Ok(());
+            }
+        }
+
+        self.set_error_class(url, class);
+
+        let (response, redirects) = result?;
+
+        self.check_social(url, response.status(), response.headers());
+        self.record(url, response.status(), redirects);
+        Ok(())
+    }
+
+    /// Retry a failed URL of an entrypoint host against the fallback host (a mirror). When the
+    /// mirror serves it, the origin is failing rather than the content missing: the URL is
+    /// recorded with the mirror status, as a warning.
+    ///
+    /// Returns whether the mirror served the URL.
+    async fn retry_on_fallback(&mut self, url: &str, failure: &str) -> bool {
+        let fallback = match (&self.opts.fallback_host, Url::parse(url)) {
+            (Some(host), Ok(fallback))
+                if self.bases.iter().any(|x| x.host_str() == fallback.host_str()) =>
+            {
+                match with_host(fallback, host) {
+                    Some(fallback) => fallback,
+                    None => return false,
+                }
+            }
+            _ => return false,
+        };
+
+        debug!("{} failed ({}), retrying on {}", url, failure, fallback);
+
+        let status = match self.fetch_head(fallback.as_str()).await.ok() {
+            Some((response, redirects)) if redirects.is_empty() => response.status(),
+            _ => return false,
+        };
+
+        if self.verdict(fallback.as_str(), status.as_u16()) != Verdict::Success {
+            debug!("{} - {} is also failing on the fallback host", status, url);
+            return false;
+        }
+
+        warn!("{} failed ({}), but is served by {}", url, failure, fallback);
+        self.record(url, status, vec![]);
+
+        if let Some(response) = self.responses.get_mut(url) {
+            response.fallback = true;
+            response.annotations.push(format!(
+                "origin failed ({}), served by the fallback host",
+                failure
+            ));
+        }
+
+        true
+    }
+
+    /// Retry an URL failing with a network error over the other scheme (--scheme-fallback):
+    /// `https://` ones on a TLS (or protocol) error or an unreachable port, `http://` ones when
+    /// unreachable.
+    /// When the other scheme serves it, the URL is recorded with its status, as a warning, and
+    /// its host reported as only reachable on that scheme.
+    ///
+    /// Returns whether the other scheme served the URL.
+    async fn retry_on_other_scheme(
+        &mut self,
+        url: &str,
+        class: Option<NetworkError>,
+        failure: &str,
+    ) -> bool {
+        if !self.opts.scheme_fallback {
+            return false;
+        }
+
+        let mut other = match Url::parse(url) {
+            Ok(url) => url,
+            Err(_) => return false,
+        };
+        let scheme = match (other.scheme(), class) {
+            ("https", Some(NetworkError::Dns | NetworkError::Timeout | NetworkError::Stalled)) => {
+                return false
+            }
+            ("https", Some(_)) => "http",
+            ("http", Some(NetworkError::ConnectionRefused | NetworkError::Protocol)) => "https",
+            _ => return false,
+        };
+
+        if other.set_scheme(scheme).is_err() {
+            return false;
+        }
+
+        debug!("{} failed ({}), retrying over {}://", url, failure, scheme);
+
+        let status = match self.fetch_head(other.as_str()).await.ok() {
+            Some((response, _)) => response.status(),
+            _ => return false,
+        };
+
+        if self.verdict(other.as_str(), status.as_u16()) != Verdict::Success {
+            debug!("{} - {} is also failing over {}://", status, url, scheme);
+            return false;
+        }
+
+        warn!("{} failed ({}), but is served over {}://", url, failure, scheme);
+        self.record(url, status, vec![]);
+
+        if let Some(response) = self.responses.get_mut(url) {
+            response.fallback = true;
+            response
+                .annotations
+                .push(format!("only reachable over {}:// ({})", scheme, failure));
+        }
+
+        let host = other.host_str().unwrap_or_default().to_string();
+        let host = self
+            .single_scheme_hosts
+            .entry(host.clone())
+            .or_insert_with(|| report::SingleSchemeHost {
+                host,
+                scheme: scheme.to_string(),
+                error: failure.to_string(),
+                urls: 0,
+            });
+
+        host.urls += 1;
+        true
+    }
+}
+
+/// Stream and decode the response body, aborting once the maximum body size is exceeded.
+///
+/// Returns `None` if the body is too large.
+async fn read_body(
+    mut response: reqwest::Response,
+    format: Format,
+    max_body_size: Option<u64>,
+    usage: &Usage,
+) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+    let host = response.url().host_str().unwrap_or_default().to_string();
+    let advertised = charset(&response).and_then(|x| Encoding::for_label(x.as_bytes()));
+    let compression = response
+        .headers()
+        .get(reqwest::header::CONTENT_ENCODING)
+        .and_then(|x| x.to_str().ok())
+        .map(|x| x.trim().to_lowercase())
+        .unwrap_or_default();
+    let mut body: Vec<u8> = vec![];
+
+    while let Some(chunk) = response.chunk().await? {
+        usage.received(&host, chunk.len() as u64);
+        body.extend_from_slice(&chunk);
+
+        if max_body_size.is_some_and(|max| body.len() as u64 > max) {
+            return Ok(None);
+        }
+    }
+
+    let body = match decompress(&compression, body, max_body_size)? {
+        Some(body) => body,
+        None => return Ok(None),
+    };
+
+    // PDF documents are binary: every byte is kept as a char (Latin-1).
+    if format == Format::Pdf {
+        return Ok(Some(body.into_iter().map(char::from).collect()));
+    }
+
+    // A byte order mark comes first, then the `Content-Type` header, then the document itself.
+    let sniffed = || Some(&body[..]).filter(|_| format == Format::Html).and_then(meta_charset);
+    let encoding = advertised.or_else(sniffed).unwrap_or(UTF_8);
+
+    Ok(Some(encoding.decode(&body).0.into_owned()))
+}
+
+/// Move an URL to another host, given as `host` or `host:port`.
+fn with_host(mut url: Url, host: &str) -> Option<Url> {
+    match host.rsplit_once(':') {
+        Some((host, port)) => {
+            url.set_host(Some(host)).ok()?;
+            url.set_port(Some(port.parse().ok()?)).ok()?;
+        }
+        None => url.set_host(Some(host)).ok()?,
+    }
+
+    Some(url)
+}
+
+/// Hash a body, its whitespace being normalized.
+fn content_hash(body: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    body.split_whitespace().for_each(|x| x.hash(&mut hasher));
+    hasher.finish()
+}
+
+/// Decompress a body according to its `Content-Encoding`.
+///
+/// Returns `None` if the decompressed body is bigger than the maximum body size.
+fn decompress(
+    encoding: &str,
+    body: Vec<u8>,
+    max: Option<u64>,
+) -> Result<Option<Vec<u8>>, String> {
+    let decoder: Box<dyn Read> = match encoding {
+        "gzip" | "x-gzip" => Box::new(GzDecoder::new(&body[..])),
+        "deflate" => Box::new(ZlibDecoder::new(&body[..])),
+        "br" => Box::new(brotli_decompressor::Decompressor::new(&body[..], 4096)),
+        _ => return Ok(Some(body)),
+    };
+    let mut decompressed = vec![];
+
+    decoder
+        .take(max.map_or(u64::MAX, |x| x + 1))
+        .read_to_end(&mut decompressed)
+        .map_err(|e| format!("cannot decompress the {} body: {}", encoding, e))?;
+
+    match max {
+        Some(max) if decompressed.len() as u64 > max => Ok(None),
+        _ => Ok(Some(decompressed)),
+    }
+}
+
+/// Get the charset advertised by the response `Content-Type` header.
+fn charset(response: &reqwest::Response) -> Option<String> {
+    let content_type = response.headers().get(reqwest::header::CONTENT_TYPE)?.to_str().ok()?;
+
+    content_type.split(';').skip(1).find_map(|x| {
+        let (name, value) = x.split_once('=')?;
+
+        if name.trim().eq_ignore_ascii_case("charset") {
+            Some(value.trim().trim_matches('"').to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// Get the encoding declared by the `<meta charset>` (or `<meta http-equiv="Content-Type">`)
+/// tag of an HTML document, looked for in its first 1024 bytes.
+fn meta_charset(body: &[u8]) -> Option<&'static Encoding> {
+    static META: OnceLock<regex::bytes::Regex> = OnceLock::new();
+
+    let meta = META.get_or_init(|| {
+        regex::bytes::Regex::new(r#"(?i)<meta[^>]+charset\s*=\s*["']?\s*([a-z0-9_:.\-]+)"#).unwrap()
+    });
+    let label = meta.captures(&body[..body.len().min(1024)])?.get(1)?;
+
+    // A document cannot declare itself in UTF-16, its declaration being ASCII.
+    match Encoding::for_label(label.as_bytes())? {
+        x if x == UTF_16LE || x == UTF_16BE => Some(UTF_8),
+        x => Some(x),
+    }
+}
+
+/// The product token `robots.txt` groups are matched against.
+const ROBOTS_AGENT: &str = "http-status-check";
+
+/// How long a worker waits when every queued URL is on a busy host (--per-host-concurrency), or
+/// when the queue is empty but the pages of other workers may still add links to it.
+const BUSY_HOST_DELAY: Duration = Duration::from_millis(10);
+
+/// How long the requests in flight may take once the crawl is interrupted, before being
+/// abandoned.
+const SHUTDOWN_DEADLINE: Duration = Duration::from_secs(10);
+
+/// How often a request in flight checks whether the crawl has been interrupted.
+const INTERRUPT_POLL: Duration = Duration::from_millis(100);
+
+/// Resolve once the crawl has been interrupted for [`SHUTDOWN_DEADLINE`].
+async fn shutdown_deadline(interrupted: Arc<AtomicBool>) {
+    while !interrupted.load(Ordering::Relaxed) {
+        tokio::time::sleep(INTERRUPT_POLL).await;
+    }
+
+    tokio::time::sleep(SHUTDOWN_DEADLINE).await;
+}
+
+/// Spawn workers until the pending queue (or the external queue) is empty.
+pub(crate) async fn run_workers(crawler: &Arc<Mutex<Crawler>>, external: bool) {
+    let mut threads = vec![];
+    let workers = match &crawler.lock().await.opts {
+        opts if opts.polite => 1,
+        opts => opts.concurrency.max(1),
+    };
+
+    for _ in 0..workers {
+        let crawler = Arc::clone(crawler);
+
+        threads.push(tokio::spawn(async move {
+            let mut worker = Worker::lock(crawler).await;
+
+            loop {
+                worker.relock().await;
+
+                if worker.is_interrupted() {
+                    break;
+                }
+
+                let url = worker.next_url(external).map(|x| x.to_string());
+                let queued = match external {
+                    true => worker.external.len(),
+                    false => worker.pending.len(),
+                };
+
+                if let Some(url) = url {
+                    let host = host(&url);
+
+                    *worker.in_flight.entry(host.clone()).or_default() += 1;
+                    worker.fetches = 0;
+
+                    let span = info_span!(
+                        "check",
+                        url = url.as_str(),
+                        external,
+                        status = field::Empty,
+                        duration_ms = field::Empty,
+                        retries = field::Empty,
+                    );
+                    let started = Instant::now();
+                    let deadline = shutdown_deadline(Arc::clone(&worker.interrupted));
+                    let request = async {
+                        match external {
+                            true => worker.check(&url).instrument(span.clone()).await,
+                            false => worker.execute(&url).instrument(span.clone()).await,
+                        }
+                    };
+                    let result = match future::select(Box::pin(request), Box::pin(deadline)).await
+                    {
+                        future::Either::Left((result, _)) => result.map_err(|e| e.to_string()),
+                        future::Either::Right(_) => {
+                            Err("abandoned, the crawl being interrupted".to_string())
+                        }
+                    };
+
+                    // An abandoned request may be waiting on the network, the crawler unlocked.
+                    worker.relock().await;
+
+                    if let Some(count) = worker.in_flight.get_mut(&host) {
+                        *count -= 1;
+                    }
+
+                    let status = worker.responses.get(url.as_str()).map_or(0, |x| x.status);
+
+                    span.record("status", status);
+                    span.record("duration_ms", started.elapsed().as_millis() as u64);
+                    span.record("retries", worker.fetches.saturating_sub(1));
+
+                    if worker.opts.failures_only && result.is_ok() {
+                        worker.forget(&url);
+                    }
+
+                    if let Err(e) = result {
+                        if let Some(response) = worker.responses.get_mut(url.as_str()) {
+                            response.error = Some(e.to_string());
+                        }
+
+                        let response = worker.responses.get(url.as_str());
+                        let class = response.and_then(|x| x.error_class);
+
+                        error!(url = url.as_str(), error_class = ?class, "{}", e);
+
+                        let status = response.map_or(0, |x| x.status);
+                        let time_ms = response.and_then(Response::time);
+
+                        worker.events.emit(CrawlEvent::Failed {
+                            url,
+                            status,
+                            error: Some(e.to_string()),
+                            time_ms: time_ms.map(|x| x.as_millis() as u64),
+                        });
+                    }
+
+                    if let Err(e) = worker.checkpoint(false) {
+                        warn!("Cannot save the crawl state: {}", e);
+                    }
+                } else if queued > 0 || worker.in_flight.values().any(|x| *x > 0) {
+                    // Every queued URL is on a busy host, or requests in flight may find new ones.
+                    worker.unlock();
+                    tokio::time::sleep(BUSY_HOST_DELAY).await;
+                } else {
+                    break;
+                }
+            }
+        }));
+    }
+
+    futures::future::join_all(threads).await;
+
+    if let Err(e) = crawler.lock().await.checkpoint(true) {
+        warn!("Cannot save the crawl state: {}", e);
+    }
+}
@@ -0,0 +1,15 @@
+use http_status_check::CrawlerBuilder;
+
+#[test]
+fn invalid_entrypoint_is_an_error() {
+    let error = CrawlerBuilder::new("not an url").build().err().unwrap();
+
+    assert!(error.to_string().starts_with("cannot parse the entrypoint not an url"));
+}
+
+#[test]
+fn polite_requires_a_contact_address() {
+    let error = CrawlerBuilder::new("http://example.com/").polite(true).build().err().unwrap();
+
+    assert_eq!(error.to_string(), "--polite requires a contact address (--from)");
+}
@@ -0,0 +1,76 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use http_status_check::CrawlerBuilder;
+
+/// Serve a home page linking to two slow pages, counting how many of them are requested at once.
+fn serve(overlap: Arc<AtomicUsize>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let active = Arc::new(AtomicUsize::new(0));
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let active = Arc::clone(&active);
+            let overlap = Arc::clone(&overlap);
+
+            thread::spawn(move || {
+                let mut line = String::new();
+                BufReader::new(&stream).read_line(&mut line).unwrap();
+
+                let path = line.split(' ').nth(1).unwrap_or_default().to_string();
+                let (status, body) = match path.as_str() {
+                    "/" => ("200 OK", r#"<a href="/slow/a">a</a><a href="/slow/b">b</a>"#),
+                    x if x.starts_with("/slow/") => {
+                        let count = active.fetch_add(1, Ordering::SeqCst) + 1;
+
+                        overlap.fetch_max(count, Ordering::SeqCst);
+                        thread::sleep(Duration::from_millis(500));
+                        active.fetch_sub(1, Ordering::SeqCst);
+                        ("200 OK", "slow")
+                    }
+                    _ => ("404 Not Found", "missing"),
+                };
+
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Type: text/html\r\nContent-Length: {}\r\n\
+                     Connection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+            });
+        }
+    });
+
+    format!("http://{}/", address)
+}
+
+#[tokio::test]
+async fn slow_requests_overlap() {
+    let overlap = Arc::new(AtomicUsize::new(0));
+    let url = serve(Arc::clone(&overlap));
+
+    let results = CrawlerBuilder::new(&url).concurrency(2).build().unwrap().run().await;
+
+    let slow = results.iter().filter(|x| x.url.contains("/slow/"));
+
+    assert_eq!(slow.filter(|x| x.status == 200).count(), 2);
+    assert_eq!(overlap.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn per_host_concurrency_limits_requests() {
+    let overlap = Arc::new(AtomicUsize::new(0));
+    let url = serve(Arc::clone(&overlap));
+
+    CrawlerBuilder::new(&url).concurrency(2).per_host_concurrency(1).build().unwrap().run().await;
+
+    assert_eq!(overlap.load(Ordering::SeqCst), 1);
+}